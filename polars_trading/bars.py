@@ -35,7 +35,11 @@ def _generate_output_schema(symbol_col: str) -> dict[str, PolarsDataType]:
 
 
 def _bar_groups_expr(
-    expr: IntoExpr, bar_size: float, allow_splits: bool = True
+    expr: IntoExpr,
+    bar_size: float,
+    allow_splits: bool = True,
+    state: IntoExpr | None = None,
+    close_before_overflow: bool = False,
 ) -> pl.Expr:
     """Generate bar groups for a given expression.
 
@@ -49,26 +53,383 @@ def _bar_groups_expr(
     update the value to be the amount for the first one and the remainder for
     the duplicate. Then you can genrate your bars.
 
+    For a frame with several symbols, use `.over("symbol")` to compute the bar
+    groups of each symbol independently. The plugin is called once per group,
+    and each call starts from an empty bar (or from `state`), so the open bar of
+    one symbol never carries over into the next. Without `.over`, the values of
+    all symbols are accumulated into the same bars.
+
     Args:
     ----
         expr (IntoExpr): The expression to generate bar groups for.
         bar_size (float): The size of the bars to generate.
         allow_splits (bool): Whether to allow splitting a trade across multiple bars.
+        state (IntoExpr | None): The `bar_group_state` of the previous chunk of
+            data, to continue its bars. If None, the first bar starts empty.
+        close_before_overflow (bool): When splits are not allowed, close the bar
+            before a trade that would take it past `bar_size`, so the trade starts
+            the next bar, rather than keeping the trade in the overflowing bar.
 
     Returns:
     -------
         pl.Expr: The expression with bar groups.
 
     """
+    args = [expr] if state is None else [expr, state]
     return register_plugin_function(
         plugin_path=LIB,
-        args=[expr],
-        kwargs={"bar_size": bar_size, "allow_splits": allow_splits},
+        args=args,
+        kwargs={
+            "bar_size": bar_size,
+            "allow_splits": allow_splits,
+            "close_before_overflow": close_before_overflow,
+        },
         is_elementwise=False,
         function_name="bar_groups",
     )
 
 
+def bar_group_state(
+    expr: IntoExpr,
+    bar_size: float,
+    allow_splits: bool = True,
+    state: IntoExpr | None = None,
+    close_before_overflow: bool = False,
+) -> pl.Expr:
+    """Generate the bar grouping state after the last value of a chunk of data.
+
+    For live data processed in chunks, passing this state as the `state` of the
+    next chunk's bar groups continues the open bar rather than starting a new
+    one, so the groups match grouping all the data at once. As with the bar
+    groups, use `.over("symbol")` to get the state of each symbol separately.
+
+    Args:
+    ----
+        expr (IntoExpr): The expression to generate bar groups for.
+        bar_size (float): The size of the bars to generate.
+        allow_splits (bool): Whether to allow splitting a trade across multiple bars.
+        state (IntoExpr | None): The state of the chunk before this one. If None,
+            the first bar starts empty.
+        close_before_overflow (bool): When splits are not allowed, close the bar
+            before a trade that would take it past `bar_size`, so the trade starts
+            the next bar, rather than keeping the trade in the overflowing bar.
+
+    Returns:
+    -------
+        pl.Expr: A single row struct with the `current_sum` of the open bar, its
+            `group_id` and the next `transaction_id`.
+
+    """
+    args = [expr] if state is None else [expr, state]
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=args,
+        kwargs={
+            "bar_size": bar_size,
+            "allow_splits": allow_splits,
+            "close_before_overflow": close_before_overflow,
+        },
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="bar_group_state",
+    )
+
+
+def dollar_bar_groups(
+    price: IntoExpr,
+    size: IntoExpr,
+    bar_size: float,
+    allow_splits: bool = True,
+    close_before_overflow: bool = False,
+) -> pl.Expr:
+    """Generate bar groups on the dollar volume of each trade.
+
+    This is `_bar_groups_expr` applied to `price * size`, without having to compute
+    the product first. It returns the same list of structs with `bar_group__id` and
+    `bar_group__amount` fields, where the amount is in dollars.
+
+    Args:
+    ----
+        price (IntoExpr): The price of each trade.
+        size (IntoExpr): The size of each trade.
+        bar_size (float): The dollar volume of each bar.
+        allow_splits (bool): Whether to allow splitting a trade across multiple bars.
+        close_before_overflow (bool): When splits are not allowed, close the bar
+            before a trade that would take it past `bar_size`, so the trade starts
+            the next bar, rather than keeping the trade in the overflowing bar.
+
+    Returns:
+    -------
+        pl.Expr: The expression with dollar bar groups.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[price, size],
+        kwargs={
+            "bar_size": bar_size,
+            "allow_splits": allow_splits,
+            "close_before_overflow": close_before_overflow,
+        },
+        is_elementwise=False,
+        function_name="dollar_bars",
+    )
+
+
+def bar_summary(
+    expr: IntoExpr,
+    bar_size: float,
+    allow_splits: bool = True,
+    close_before_overflow: bool = False,
+) -> pl.Expr:
+    """Summarise the bars a bar grouping would produce.
+
+    This groups the values in the same way as `_bar_groups_expr` and returns
+    diagnostics of the resulting bars without a separate `group_by`. The last
+    bar may still be open and smaller than `bar_size`.
+
+    Args:
+    ----
+        expr (IntoExpr): The expression to generate bar groups for.
+        bar_size (float): The size of the bars to generate.
+        allow_splits (bool): Whether to allow splitting a trade across multiple bars.
+        close_before_overflow (bool): When splits are not allowed, close the bar
+            before a trade that would take it past `bar_size`.
+
+    Returns:
+    -------
+        pl.Expr: A single row struct with the `num_bars`, `mean_bar_size` and
+            `max_bar_size`.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={
+            "bar_size": bar_size,
+            "allow_splits": allow_splits,
+            "close_before_overflow": close_before_overflow,
+        },
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="bar_summary",
+    )
+
+
+def bar_boundaries(
+    expr: IntoExpr,
+    bar_size: float,
+    allow_splits: bool = True,
+    close_before_overflow: bool = False,
+) -> pl.Expr:
+    """Get the index of the last transaction in each bar.
+
+    This groups the values in the same way as `_bar_groups_expr`, but returns the
+    row index where each bar ends instead of a group id per row. A trade split
+    across bars ends each bar it closes. The last bar may still be open.
+
+    Args:
+    ----
+        expr (IntoExpr): The expression to generate bar groups for.
+        bar_size (float): The size of the bars to generate.
+        allow_splits (bool): Whether to allow splitting a trade across multiple bars.
+        close_before_overflow (bool): When splits are not allowed, close the bar
+            before a trade that would take it past `bar_size`.
+
+    Returns:
+    -------
+        pl.Expr: A single row list of the Int32 transaction index ending each bar.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={
+            "bar_size": bar_size,
+            "allow_splits": allow_splits,
+            "close_before_overflow": close_before_overflow,
+        },
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="bar_boundaries",
+    )
+
+
+def multi_bar_groups(expr: IntoExpr, bar_sizes: list[float]) -> pl.Expr:
+    """Generate nested bar groups for several bar sizes in a single pass.
+
+    This expression will return a struct column with one `bar_group__id_{i}` field
+    per bar size, in the order given. Values are never split across bars, so a bar
+    may overflow its size. A bar only closes where the bar of the next smaller
+    size also closes, so each coarser bar is made of whole finer bars.
+
+    Args:
+    ----
+        expr (IntoExpr): The expression to generate bar groups for.
+        bar_sizes (list[float]): The ascending sizes of the bars to generate.
+
+    Returns:
+    -------
+        pl.Expr: The expression with the bar group ids for each bar size.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"bar_sizes": bar_sizes},
+        is_elementwise=False,
+        function_name="multi_bar_groups",
+    )
+
+
+def tick_imbalance_bar_groups(
+    signs: IntoExpr, expected_theta: float, alpha: float = 0.1
+) -> pl.Expr:
+    """Generate tick imbalance bar ids from the tick rule sign of each trade.
+
+    A bar closes once the absolute sum of its signs reaches the expected
+    imbalance, the expected number of ticks in a bar times the absolute expected
+    sign of a tick. After each bar, both expectations are updated by an EWMA of
+    the bar's length and mean sign. Trades are never split across bars.
+
+    Reference: Advances in Financial Machine Learning, Chapter 2.3.2.1
+
+    Args:
+    ----
+        signs (IntoExpr): The tick rule sign of each trade, 1 for a buy and -1
+            for a sell.
+        expected_theta (float): The imbalance of the first bar, i.e. the expected
+            number of ticks in it.
+        alpha (float): The EWMA weight of the latest bar, in (0, 1].
+
+    Returns:
+    -------
+        pl.Expr: The bar id of each trade.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[signs],
+        kwargs={"expected_theta": expected_theta, "alpha": alpha},
+        is_elementwise=False,
+        function_name="tick_imbalance_bars",
+    )
+
+
+def volume_profile(
+    price: IntoExpr, volume: IntoExpr, bar_id: IntoExpr, n_bins: int
+) -> pl.Expr:
+    """Generate the intrabar volume profile of each bar.
+
+    The prices traded within each bar are split into `n_bins` equal width bins
+    from the bar's low to its high, and the volume traded in each bin is summed.
+    A bar trading at a single price has a single bin. Rows with a null input are
+    skipped.
+
+    This returns one row per bar in ascending bar id order, so it lines up with
+    `bar_id.unique().sort()` in a `select`.
+
+    Args:
+    ----
+        price (IntoExpr): The price of each trade.
+        volume (IntoExpr): The volume of each trade.
+        bar_id (IntoExpr): The bar group id of each trade, e.g. from
+            `multi_bar_groups`.
+        n_bins (int): The number of price bins in each bar.
+
+    Returns:
+    -------
+        pl.Expr: A list of structs with `price_level` and `volume` fields per bar,
+            where the price level is the midpoint of the bin.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[price, volume, bar_id],
+        kwargs={"n_bins": n_bins},
+        is_elementwise=False,
+        changes_length=True,
+        function_name="volume_profile",
+    )
+
+
+def bar_vwap(
+    price: IntoExpr,
+    volume: IntoExpr,
+    bar_id: IntoExpr,
+    *,
+    weighting: str = "volume",
+    span: float | None = None,
+) -> pl.Expr:
+    """Calculate the volume weighted average price of each bar.
+
+    With `"volume"` weighting this is the plain VWAP. The other weightings further
+    scale each trade's volume so that older fills count for less, e.g. for
+    transaction cost analysis. `"exp"` discounts each trade by `1 - alpha` per
+    later trade in the bar, with `alpha = 2 / (span + 1)`, and `"time"` scales
+    the trades linearly by their order in the bar, a triangular kernel. Trades
+    must be sorted by time and rows with a null input are skipped. A bar with no
+    volume is null.
+
+    This returns one row per bar in ascending bar id order, so it lines up with
+    `bar_id.unique().sort()` in a `select`.
+
+    Args:
+    ----
+        price (IntoExpr): The price of each trade.
+        volume (IntoExpr): The volume of each trade.
+        bar_id (IntoExpr): The bar group id of each trade.
+        weighting (str): One of `"volume"`, `"exp"` or `"time"`.
+        span (float | None): The span of the `"exp"` weighting in trades, at
+            least 1.
+
+    Returns:
+    -------
+        pl.Expr: The weighted average price of each bar.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[price, volume, bar_id],
+        kwargs={"weighting": weighting, "span": span},
+        is_elementwise=False,
+        changes_length=True,
+        function_name="bar_vwap",
+    )
+
+
+def bar_twap(price: IntoExpr, timestamp: IntoExpr, bar_id: IntoExpr) -> pl.Expr:
+    """Calculate the time weighted average price of each bar.
+
+    Unlike the VWAP, each price is weighted by how long it stood, i.e. the time
+    until the next trade in the bar, which is more representative when volume is
+    sparse. The last trade of a bar carries no weight. A bar whose trades all
+    share a timestamp, including a single trade bar, is the mean of its prices.
+    Trades must be sorted by timestamp and rows with a null input are skipped.
+
+    This returns one row per bar in ascending bar id order, so it lines up with
+    `bar_id.unique().sort()` in a `select`.
+
+    Args:
+    ----
+        price (IntoExpr): The price of each trade.
+        timestamp (IntoExpr): The timestamp of each trade.
+        bar_id (IntoExpr): The bar group id of each trade.
+
+    Returns:
+    -------
+        pl.Expr: The time weighted average price of each bar.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[price, timestamp, bar_id],
+        is_elementwise=False,
+        changes_length=True,
+        function_name="bar_twap",
+    )
+
+
 def _ohlcv_expr(
     timestamp_col: IntoExpr, price_col: IntoExpr, size_col: IntoExpr
 ) -> list[pl.Expr]:
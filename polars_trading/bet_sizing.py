@@ -0,0 +1,170 @@
+"""Module containing functions to size bets from model predictions."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def bet_size(prob: IntoExpr, side: IntoExpr, num_classes: int = 2) -> pl.Expr:
+    """Size bets from the predicted probability of a classifier.
+
+    The statistic `z = (p - 1/k) / sqrt(p(1-p))` tests the predicted probability
+    against a uniform guess over `k` classes, and the bet size is
+    `side * (2 * norm_cdf(z) - 1)`. A probability of `1/k` gives no bet and a
+    probability near 1 gives a full bet on the predicted side.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 142
+
+    Args:
+    ----
+        prob: IntoExpr - The predicted probability of the predicted class.
+        side: IntoExpr - The predicted side of the bet, 1 for long and -1 for short.
+        num_classes: int - The number of classes the classifier predicts.
+
+    Returns:
+    -------
+        pl.Expr: The signed bet size in [-1, 1].
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prob, side],
+        kwargs={"num_classes": num_classes},
+        is_elementwise=True,
+        function_name="bet_size",
+    )
+
+
+def discretize_bet_size(size: IntoExpr, step_size: float) -> pl.Expr:
+    """Round bet sizes to the nearest multiple of a step size.
+
+    Discretizing continuous bet sizes prevents overtrading on small changes in the
+    predicted probability. Sizes are capped at a full bet of -1 or 1.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 145
+
+    Args:
+    ----
+        size: IntoExpr - The bet sizes, e.g. from `bet_size`.
+        step_size: float - The grid spacing, in (0, 1].
+
+    Returns:
+    -------
+        pl.Expr: The discretized bet sizes.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[size],
+        kwargs={"step_size": step_size},
+        is_elementwise=True,
+        function_name="discretize_bet_size",
+    )
+
+
+def avg_active_bets(size: IntoExpr, start: IntoExpr, end: IntoExpr) -> pl.Expr:
+    """Average the sizes of the bets active at each bar.
+
+    Each bet is active from its start to its end position, e.g. the row position
+    of the event and the positional `barrier_touch` of `triple_barrier_label`.
+    The output at each bar is the mean size of the bets active there, or null if
+    there are none.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 144
+
+    Args:
+    ----
+        size: IntoExpr - The size of each bet.
+        start: IntoExpr - The bar position at which each bet starts.
+        end: IntoExpr - The bar position at which each bet ends (inclusive).
+
+    Returns:
+    -------
+        pl.Expr: The average active bet size at each bar.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[size, start, end],
+        is_elementwise=False,
+        function_name="avg_active_bets",
+    )
+
+
+def drawdown_throttle(equity: IntoExpr, max_dd: float, floor: float) -> pl.Expr:
+    """Scale exposure down as the drawdown of an equity curve deepens.
+
+    The running drawdown is calculated as in `drawdown_series`, and the multiplier
+    falls linearly from 1 at the peak to `floor` at a drawdown of `max_dd`, staying
+    at `floor` beyond it. Multiply the bet sizes by it to cut risk while losing.
+    Null equity values are null.
+
+    Args:
+    ----
+        equity: IntoExpr - The equity curve, e.g. from `cumulative_returns`.
+        max_dd: float - The drawdown at which exposure reaches the floor, e.g. 0.2
+            for 20%.
+        floor: float - The smallest multiplier, in [0, 1].
+
+    Returns:
+    -------
+        pl.Expr: The exposure multiplier, in [floor, 1].
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[equity],
+        kwargs={"max_dd": max_dd, "floor": floor},
+        is_elementwise=False,
+        function_name="drawdown_throttle",
+    )
+
+
+def kelly_fraction(
+    mu: IntoExpr,
+    variance: IntoExpr,
+    kelly_multiplier: float = 1.0,
+    *,
+    min_fraction: float = -1.0,
+    max_fraction: float = 1.0,
+) -> pl.Expr:
+    """Size positions with the Kelly criterion.
+
+    The Kelly fraction `mu / variance` maximizes the expected log growth of
+    capital. Estimation error in `mu` makes full Kelly aggressive, so it is
+    scaled by `kelly_multiplier`, e.g. 0.5 for half Kelly, and then clamped to
+    `[min_fraction, max_fraction]`. Rows with a non-positive variance are null.
+
+    Args:
+    ----
+        mu: IntoExpr - The expected return.
+        variance: IntoExpr - The variance of the return.
+        kelly_multiplier: float - The fraction of full Kelly to bet, positive.
+        min_fraction: float - The smallest (most short) fraction.
+        max_fraction: float - The largest (most long) fraction.
+
+    Returns:
+    -------
+        pl.Expr: The position fractions.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[mu, variance],
+        kwargs={
+            "kelly_multiplier": kelly_multiplier,
+            "min_fraction": min_fraction,
+            "max_fraction": max_fraction,
+        },
+        is_elementwise=True,
+        function_name="kelly_fraction",
+    )
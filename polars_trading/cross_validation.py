@@ -0,0 +1,50 @@
+"""Module containing cross-validation splits for overlapping labels."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def purged_kfold_indices(
+    start: IntoExpr, end: IntoExpr, n_splits: int, embargo_pct: float = 0.0
+) -> pl.Expr:
+    """Assign labels to purged k-fold cross-validation splits.
+
+    The labels, sorted by start, are split into `n_splits` contiguous test folds.
+    For each fold, training labels whose span overlaps the test labels' horizon
+    are purged, and those starting within an embargo of `embargo_pct` of the bars
+    after it are embargoed, so no training label leaks information from the test
+    set.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 109
+
+    Args:
+    ----
+        start: IntoExpr - The bar position at which each label starts.
+        end: IntoExpr - The bar position at which each label ends (inclusive).
+        n_splits: int - The number of folds.
+        embargo_pct: float - The fraction of bars to embargo after each test fold.
+
+    Returns:
+    -------
+        pl.Expr: A struct with one `fold_{i}` field per split, which is 1 if the
+            label is in the test set, 0 if it is in the training set and null if
+            it is purged or embargoed.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[start, end],
+        kwargs={"n_splits": n_splits, "embargo_pct": embargo_pct},
+        is_elementwise=False,
+        function_name="purged_kfold_indices",
+    )
@@ -0,0 +1,88 @@
+"""Module containing entropy features."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING, Literal
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def entropy(
+    symbols: IntoExpr,
+    window: int,
+    method: Literal["shannon", "plugin", "lempel_ziv"] = "shannon",
+    *,
+    word_length: int = 1,
+) -> pl.Expr:
+    """Generate expression to estimate the entropy of a rolling window of symbols.
+
+    The methods are:
+    - `shannon`: the Shannon entropy of the symbol frequencies, in bits.
+    - `plugin`: the plug-in entropy rate of overlapping words of `word_length`
+        symbols, in bits per symbol.
+    - `lempel_ziv`: the size of the Lempel-Ziv library relative to the window
+        length, which is lower for redundant messages.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 263
+
+    Args:
+    ----
+        symbols: IntoExpr - The encoded symbols, e.g. from `encode_returns`.
+        window: int - The number of symbols in each window.
+        method: str - The entropy estimator to use.
+        word_length: int - The word length of the plug-in estimator.
+
+    Returns:
+    -------
+        pl.Expr: The rolling entropy estimate.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[symbols],
+        kwargs={"window": window, "method": method, "word_length": word_length},
+        is_elementwise=False,
+        function_name="entropy",
+    )
+
+
+def encode_returns(
+    returns: IntoExpr,
+    method: Literal["sign", "quantile"] = "sign",
+    *,
+    n_bins: int | None = None,
+) -> pl.Expr:
+    """Generate expression to encode returns as integer symbols.
+
+    The methods are:
+    - `sign`: -1, 0 or 1 by the sign of the return.
+    - `quantile`: the quantile bin of the return over the whole series, from 0 to
+        `n_bins - 1`. Tied returns always share a bin.
+
+    This is the preprocessing step before `entropy`. Null returns stay null.
+
+    Args:
+    ----
+        returns: IntoExpr - The returns to encode.
+        method: str - The encoding method.
+        n_bins: int | None - The number of quantile bins, required for `quantile`.
+
+    Returns:
+    -------
+        pl.Expr: The integer codes as an Int64 expression.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={"method": method, "n_bins": n_bins},
+        is_elementwise=False,
+        function_name="encode_returns",
+    )
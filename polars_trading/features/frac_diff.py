@@ -7,15 +7,33 @@ from polars_trading._utils import LIB
 from polars_trading.typing import IntoExpr
 
 
-def frac_diff(expr: IntoExpr, d: float, threshold: float) -> pl.Expr:
+def frac_diff(
+    expr: IntoExpr,
+    d: float,
+    threshold: float | None = None,
+    *,
+    window: int | None = None,
+    skip_leading_nulls: bool = False,
+) -> pl.Expr:
     """Generate expression to calculate the fractionally differentiated series.
 
+    The weights are truncated at the first lag whose weight is smaller than
+    `threshold` in magnitude, or at exactly `window` weights if it is set.
+
+    Nulls in the series raise an error. With `skip_leading_nulls`, nulls at the
+    head of the series, e.g. from the warm-up of another transform, are allowed:
+    they stay null and the series is differentiated from the first non-null value.
+
     Args:
     ----
         expr: IntoExpr - The expression to calculate the fractionally differentiated
             series.
         d: float - The fractional difference.
-        threshold: float - The threshold.
+        threshold: float | None - The threshold.
+        window: int | None - The number of weights, including the current value,
+            used instead of `threshold`.
+        skip_leading_nulls: bool - Whether to allow nulls at the head of the
+            series.
 
     Returns:
     -------
@@ -25,7 +43,94 @@ def frac_diff(expr: IntoExpr, d: float, threshold: float) -> pl.Expr:
     return register_plugin_function(
         plugin_path=LIB,
         args=[expr],
-        kwargs={"d": d, "threshold": threshold},
+        kwargs={
+            "d": d,
+            "threshold": threshold,
+            "window": window,
+            "skip_leading_nulls": skip_leading_nulls,
+        },
         is_elementwise=False,
         function_name="frac_diff",
     )
+
+
+def frac_diff_multi(
+    *exprs: IntoExpr,
+    d: float,
+    threshold: float | None = None,
+    window: int | None = None,
+) -> pl.Expr:
+    """Fractionally differentiate several series with the same weights.
+
+    The weights are computed once, as in `frac_diff`, and applied to each input,
+    e.g. the open, high, low and close prices.
+
+    Args:
+    ----
+        *exprs: IntoExpr - The series to fractionally differentiate.
+        d: float - The fractional difference.
+        threshold: float | None - The threshold.
+        window: int | None - The number of weights, including the current value,
+            used instead of `threshold`.
+
+    Returns:
+    -------
+        pl.Expr: A struct with one fractionally differentiated field per input,
+            named after it.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=list(exprs),
+        kwargs={"d": d, "threshold": threshold, "window": window},
+        is_elementwise=False,
+        function_name="frac_diff_multi",
+    )
+
+
+def frac_diff_analysis(
+    expr: IntoExpr,
+    ds: list[float],
+    threshold: float | None = None,
+    *,
+    window: int | None = None,
+    max_lag: int = 1,
+) -> pl.Expr:
+    """Tabulate the stationarity and memory of a series for several `d` values.
+
+    For each `d`, the series is fractionally differentiated as in `frac_diff` and
+    the Augmented Dickey-Fuller statistic of the result, with `max_lag` lagged
+    differences, and its correlation with the original series are measured. The
+    smallest `d` whose ADF statistic passes the critical value keeps the most
+    memory while being stationary.
+
+    Reference: Advances in Financial Machine Learning, Chapter 5.6
+
+    Args:
+    ----
+        expr: IntoExpr - The price series, without nulls.
+        ds: list[float] - The fractional differences to try.
+        threshold: float | None - The threshold.
+        window: int | None - The number of weights, including the current value,
+            used instead of `threshold`.
+        max_lag: int - The number of lagged differences in the ADF regression.
+
+    Returns:
+    -------
+        pl.Expr: A single row list of structs with the fields `d`, `adf_stat` and
+            `corr_with_original`, one per `d`.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={
+            "ds": ds,
+            "threshold": threshold,
+            "window": window,
+            "max_lag": max_lag,
+        },
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="frac_diff_analysis",
+    )
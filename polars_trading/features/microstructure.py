@@ -0,0 +1,269 @@
+"""Module containing market microstructure features."""
+
+import polars as pl
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+from polars_trading.typing import IntoExpr
+
+
+def roll_spread(prices: IntoExpr, window: int) -> pl.Expr:
+    """Generate expression to calculate the Roll effective spread.
+
+    The Roll (1984) model infers the effective bid-ask spread from the negative
+    serial covariance of price changes caused by bid-ask bounce:
+    `2 * sqrt(-cov(Δp_t, Δp_{t-1}))`. The spread is null when the covariance is
+    positive, as the model is undefined there.
+
+    Args:
+    ----
+        prices: IntoExpr - The trade prices.
+        window: int - The number of consecutive price change pairs in each window.
+
+    Returns:
+    -------
+        pl.Expr: The rolling Roll spread.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices],
+        kwargs={"window": window},
+        is_elementwise=False,
+        function_name="roll_spread",
+    )
+
+
+def amihud_illiquidity(
+    returns: IntoExpr, dollar_volume: IntoExpr, window: int
+) -> pl.Expr:
+    """Generate expression to calculate the Amihud illiquidity measure.
+
+    The Amihud (2002) ratio is the mean of `|return| / dollar_volume` over a rolling
+    window, i.e. the price impact per dollar traded. Bars with zero or null dollar
+    volume are skipped rather than producing an infinite ratio.
+
+    Args:
+    ----
+        returns: IntoExpr - The bar returns.
+        dollar_volume: IntoExpr - The dollar volume traded in each bar.
+        window: int - The number of bars in each window.
+
+    Returns:
+    -------
+        pl.Expr: The rolling Amihud illiquidity ratio.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns, dollar_volume],
+        kwargs={"window": window},
+        is_elementwise=False,
+        function_name="amihud_illiquidity",
+    )
+
+
+def tick_rule(prices: IntoExpr) -> pl.Expr:
+    """Generate expression to sign trades with the tick rule.
+
+    A trade at a higher price than the previous trade is signed +1 (buy) and at a
+    lower price -1 (sell). A trade at the same price as the previous trade carries
+    the previous sign forward. Trades before the first price change are null.
+
+    Args:
+    ----
+        prices: IntoExpr - The trade prices.
+
+    Returns:
+    -------
+        pl.Expr: The trade signs as an Int8 expression.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices],
+        is_elementwise=False,
+        function_name="tick_rule",
+    )
+
+
+def trade_aggressor(prices: IntoExpr, bid: IntoExpr, ask: IntoExpr) -> pl.Expr:
+    """Generate expression to flag whether each trade crossed the spread.
+
+    A trade at or above the ask is buyer initiated and signed +1, a trade at or
+    below the bid is seller initiated and signed -1, and a trade strictly inside
+    the spread is passive and 0. Rows with a crossed quote, where the bid is above
+    the ask, are null. Unlike `tick_rule`, this needs the quote at each trade.
+
+    Args:
+    ----
+        prices: IntoExpr - The trade prices.
+        bid: IntoExpr - The bid price at the time of each trade.
+        ask: IntoExpr - The ask price at the time of each trade.
+
+    Returns:
+    -------
+        pl.Expr: The aggressor side of each trade as an Int8 expression.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices, bid, ask],
+        is_elementwise=True,
+        function_name="trade_aggressor",
+    )
+
+
+def snap_to_tick(
+    prices: IntoExpr, tick_size: float | IntoExpr, mode: str = "nearest"
+) -> pl.Expr:
+    """Generate expression to snap prices to the tick grid.
+
+    Each price is rounded to a multiple of the tick size, to the `"nearest"` tick
+    (halves away from zero), `"up"` towards positive infinity or `"down"` towards
+    negative infinity, so negative prices such as spreads round consistently. A
+    price already on the grid, up to floating point error, is unchanged. A zero
+    tick size leaves the price unchanged and a negative one is null.
+
+    Args:
+    ----
+        prices: IntoExpr - The prices to snap.
+        tick_size: float | IntoExpr - The tick size, or a column of tick sizes for
+            a variable grid.
+        mode: str - One of `"nearest"`, `"up"` or `"down"`.
+
+    Returns:
+    -------
+        pl.Expr: The snapped prices.
+
+    """
+    if isinstance(tick_size, (int, float)):
+        args, tick_size_kwarg = [prices], float(tick_size)
+    else:
+        args, tick_size_kwarg = [prices, tick_size], None
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=args,
+        kwargs={"tick_size": tick_size_kwarg, "mode": mode},
+        is_elementwise=True,
+        function_name="snap_to_tick",
+    )
+
+
+def corwin_schultz_spread(high: IntoExpr, low: IntoExpr) -> pl.Expr:
+    """Generate expression to calculate the Corwin-Schultz high-low spread.
+
+    The Corwin-Schultz (2012) estimator infers the proportional bid-ask spread from
+    the high and low prices of each period and the period before it, without quote
+    data. Negative estimates are clamped to zero. The first period is null.
+
+    Args:
+    ----
+        high: IntoExpr - The high price of each period.
+        low: IntoExpr - The low price of each period.
+
+    Returns:
+    -------
+        pl.Expr: The estimated proportional spread.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[high, low],
+        is_elementwise=False,
+        function_name="corwin_schultz_spread",
+    )
+
+
+def micro_price(
+    bid: IntoExpr, ask: IntoExpr, bid_size: IntoExpr, ask_size: IntoExpr
+) -> pl.Expr:
+    """Generate expression to calculate the micro-price.
+
+    The micro-price is the size-weighted mid price
+    `(bid * ask_size + ask * bid_size) / (bid_size + ask_size)`. Depth on the bid
+    pulls it towards the ask and vice versa, making it a better fair value proxy
+    than the simple mid. It is null when the total size is zero or a side is
+    missing.
+
+    Args:
+    ----
+        bid: IntoExpr - The best bid price.
+        ask: IntoExpr - The best ask price.
+        bid_size: IntoExpr - The size at the best bid.
+        ask_size: IntoExpr - The size at the best ask.
+
+    Returns:
+    -------
+        pl.Expr: The micro-price.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[bid, ask, bid_size, ask_size],
+        is_elementwise=True,
+        function_name="micro_price",
+    )
+
+
+def order_flow_imbalance(
+    bid: IntoExpr, bid_size: IntoExpr, ask: IntoExpr, ask_size: IntoExpr
+) -> pl.Expr:
+    """Generate expression to calculate the order flow imbalance of quote updates.
+
+    Each best bid and ask update contributes the change in demand minus the change
+    in supply at the top of the book, following Cont, Kukanov and Stoikov (2014).
+    Positive values indicate buying pressure. Summing the increments over a time
+    interval gives the interval's order flow imbalance. The first row, having no
+    previous quote, is null.
+
+    Args:
+    ----
+        bid: IntoExpr - The best bid price.
+        bid_size: IntoExpr - The size at the best bid.
+        ask: IntoExpr - The best ask price.
+        ask_size: IntoExpr - The size at the best ask.
+
+    Returns:
+    -------
+        pl.Expr: The order flow imbalance of each quote update.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[bid, bid_size, ask, ask_size],
+        is_elementwise=False,
+        function_name="order_flow_imbalance",
+    )
+
+
+def vpin(buy_volume: IntoExpr, sell_volume: IntoExpr, window: int) -> pl.Expr:
+    """Generate expression to calculate the rolling VPIN of volume buckets.
+
+    The volume-synchronized probability of informed trading is a measure of order
+    flow toxicity. Over the last `window` volume buckets it is the total absolute
+    imbalance between buy and sell volume divided by the total volume. The inputs
+    should already be bucketed into equal volume bars, for example by summing
+    trade volume by side within `volume_bars`.
+
+    Reference: Easley, Lopez de Prado and O'Hara (2012), "Flow Toxicity and
+    Liquidity in a High-frequency World"
+
+    Args:
+    ----
+        buy_volume: IntoExpr - The buy volume of each bucket.
+        sell_volume: IntoExpr - The sell volume of each bucket.
+        window: int - The number of buckets in each window.
+
+    Returns:
+    -------
+        pl.Expr: The rolling VPIN.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[buy_volume, sell_volume],
+        kwargs={"window": window},
+        is_elementwise=False,
+        function_name="vpin",
+    )
@@ -0,0 +1,76 @@
+"""Module containing structural break and explosiveness tests."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def cusum_break_test(log_prices: IntoExpr, min_periods: int = 20) -> pl.Expr:
+    """Generate expression to calculate the Chu-Stinchcombe-White CUSUM statistic.
+
+    For each observation `t` the statistic is the supremum over earlier
+    observations `n` of `(y_t - y_n) / (σ_t * sqrt(t - n))`, where `σ_t` is the
+    root mean squared log return up to `t`. Large values flag a departure from a
+    random walk, e.g. a bubble. Unlike `symmetric_cusum_filter`, this is a test
+    statistic rather than an event sampling method.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 251
+
+    Args:
+    ----
+        log_prices: IntoExpr - The log prices of the financial instrument.
+        min_periods: int - The number of observations before the statistic is
+            calculated. Earlier rows are null.
+
+    Returns:
+    -------
+        pl.Expr: The CUSUM statistic series.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[log_prices],
+        kwargs={"min_periods": min_periods},
+        is_elementwise=False,
+        function_name="cusum_break_test",
+    )
+
+
+def sadf(prices: IntoExpr, min_window: int, lag: int = 1) -> pl.Expr:
+    """Generate expression to calculate the supremum ADF (SADF) statistic.
+
+    At each observation the ADF statistic is calculated on every window ending
+    there with at least `min_window` observations, and the supremum is taken. The
+    statistic spikes when prices become explosive, e.g. during a bubble. The cost
+    grows quadratically with the length of the series.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 258
+
+    Args:
+    ----
+        prices: IntoExpr - The prices (or log prices) of the financial instrument.
+        min_window: int - The smallest window to run the ADF regression on, at
+            least `2 * lag + 4`.
+        lag: int - The number of lagged differences in each ADF regression.
+
+    Returns:
+    -------
+        pl.Expr: The SADF statistic series, null for the first `min_window - 1` rows.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices],
+        kwargs={"min_window": min_window, "lag": lag},
+        is_elementwise=False,
+        function_name="sadf",
+    )
@@ -0,0 +1,103 @@
+"""Module containing volatility features."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING, Literal
+
+import polars as pl
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+from polars_trading.config import column_names
+
+if TYPE_CHECKING:
+    from polars_trading.typing import IntoExpr
+
+
+def realized_vol(
+    window: int,
+    estimator: Literal[
+        "close", "parkinson", "garman_klass", "rogers_satchell"
+    ] = "close",
+    *,
+    open_: IntoExpr | None = None,
+    high: IntoExpr | None = None,
+    low: IntoExpr | None = None,
+    close: IntoExpr | None = None,
+) -> pl.Expr:
+    """Generate expression to calculate rolling realized volatility.
+
+    The estimators are:
+    - `close`: the sample standard deviation of close-to-close log returns.
+    - `parkinson`: uses the high-low range of each bar.
+    - `garman_klass`: uses the high-low range and the open-to-close move.
+    - `rogers_satchell`: uses all four prices and allows for drift.
+
+    The volatility is per bar; multiply by the square root of the number of bars per
+    year to annualize it. The OHLC columns default to the configured column names.
+
+    Args:
+    ----
+        window: int - The number of bars in each window.
+        estimator: str - The volatility estimator to use.
+        open_: IntoExpr | None - The open price of each bar.
+        high: IntoExpr | None - The high price of each bar.
+        low: IntoExpr | None - The low price of each bar.
+        close: IntoExpr | None - The close price of each bar.
+
+    Returns:
+    -------
+        pl.Expr: The rolling realized volatility.
+
+    """
+    open_ = open_ if open_ is not None else pl.col(column_names.open)
+    high = high if high is not None else pl.col(column_names.high)
+    low = low if low is not None else pl.col(column_names.low)
+    close = close if close is not None else pl.col(column_names.close)
+    if estimator == "close":
+        args = [close]
+    elif estimator == "parkinson":
+        args = [high, low]
+    elif estimator in ("garman_klass", "rogers_satchell"):
+        args = [open_, high, low, close]
+    else:
+        msg = f"Unknown estimator: {estimator}"
+        raise ValueError(msg)
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=args,
+        kwargs={"window": window, "estimator": estimator},
+        is_elementwise=False,
+        function_name="realized_vol",
+    )
+
+
+def garch11(returns: IntoExpr, omega: float, alpha: float, beta: float) -> pl.Expr:
+    """Generate expression to filter the GARCH(1,1) conditional variance.
+
+    The variance follows `σ²_t = ω + α r²_{t-1} + β σ²_{t-1}`, so each value is the
+    one-step-ahead forecast for its return given the returns before it, starting
+    from the unconditional variance `ω / (1 - α - β)`. A shock raises the variance,
+    which then decays back at a rate set by `α + β`. A null return carries the
+    variance forward. The parameters are fixed, e.g. from an offline fit.
+
+    Args:
+    ----
+        returns: IntoExpr - The (demeaned) returns.
+        omega: float - The constant term, positive.
+        alpha: float - The weight of the last squared return, non-negative.
+        beta: float - The weight of the last variance, non-negative, with
+            `alpha + beta < 1`.
+
+    Returns:
+    -------
+        pl.Expr: The conditional variance of each return.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={"omega": omega, "alpha": alpha, "beta": beta},
+        is_elementwise=False,
+        function_name="garch11",
+    )
@@ -0,0 +1,174 @@
+"""Module containing event filters for sampling bars."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def zscore_filter(expr: IntoExpr, span: int, threshold: float) -> pl.Expr:
+    """Flag events where a value deviates strongly from its recent behaviour.
+
+    Each value is standardized with an exponentially weighted mean and variance of
+    the values before it, estimated online as in RiskMetrics. The event is +1 when
+    the z-score exceeds `threshold`, -1 when it is below `-threshold` and 0
+    otherwise, so the same move fires in a calm regime but not in a volatile one.
+    The first `span` rows are null while the estimates warm up.
+
+    Args:
+    ----
+        expr: IntoExpr - The series to filter, e.g. returns.
+        span: int - The span of the exponential weighting, `alpha = 2 / (span + 1)`.
+        threshold: float - The number of standard deviations that triggers an event.
+
+    Returns:
+    -------
+        pl.Expr: The events as an Int8 expression.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"span": span, "threshold": threshold},
+        is_elementwise=False,
+        function_name="zscore_filter",
+    )
+
+
+def rolling_quantile_threshold(
+    expr: IntoExpr, window: int, quantile: float
+) -> pl.Expr:
+    """Calculate a data-driven event threshold from recent absolute values.
+
+    The threshold is the `quantile` of the absolute values in the window ending at
+    each row, interpolated linearly between the closest ranks, so it widens in a
+    volatile regime and narrows in a calm one. The first `window - 1` rows and
+    windows containing a null are null. The window includes the current row, so
+    `.shift(1)` the threshold to only use past values.
+
+    Args:
+    ----
+        expr: IntoExpr - The series to threshold, e.g. returns.
+        window: int - The number of rows in each window.
+        quantile: float - The quantile of the absolute values, between 0 and 1.
+
+    Returns:
+    -------
+        pl.Expr: The thresholds.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"window": window, "quantile": quantile},
+        is_elementwise=False,
+        function_name="rolling_quantile_threshold",
+    )
+
+
+def symmetric_cusum_filter(
+    expr: IntoExpr,
+    threshold: float | IntoExpr,
+    *,
+    carry_residual: bool = False,
+    min_gap: int = 0,
+    input_: str = "diff",
+    skip_first: bool = False,
+) -> pl.Expr:
+    """Flag events where the cumulative change since the last event is large.
+
+    The positive and negative cumulative sums of the diffs are tracked separately,
+    each floored at zero in its own direction. An event is +1 when the positive
+    sum exceeds `threshold`, -1 when the negative sum falls below `-threshold`
+    and 0 otherwise. The sum that triggered an event is reset to zero, or reduced
+    by `threshold` with `carry_residual` so the overshoot is not discarded. Null
+    diffs are null and leave the sums unchanged. Events fewer than `min_gap` rows
+    after the previous event are suppressed to debounce clustered events.
+
+    With `input_="price"` the filter takes raw prices and runs on their log
+    returns, treating the first price as no change. With `skip_first`, the first
+    non-null diff is 0 and the sums start after it, so a large first diff cannot
+    fire a spurious event.
+
+    The threshold can be a column, e.g. from `rolling_quantile_threshold`, to
+    compare each diff against a threshold that tracks volatility. The column must
+    have one row per diff, or a single row to use for every diff. Rows with a null
+    or non-positive threshold are treated like null diffs.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 39
+
+    Args:
+    ----
+        expr: IntoExpr - The diffs to filter, e.g. log returns, or the prices
+            with `input_="price"`.
+        threshold: float | IntoExpr - The cumulative change that triggers an
+            event, or a column of per-row thresholds.
+        carry_residual: bool - Whether to carry the overshoot past `threshold`
+            into the next event.
+        min_gap: int - The minimum number of rows between events.
+        input_: str - Whether `expr` holds `"diff"`s or `"price"`s.
+        skip_first: bool - Whether to only use the first diff as the starting
+            point.
+
+    Returns:
+    -------
+        pl.Expr: The events as an Int8 expression.
+
+    """
+    if isinstance(threshold, (int, float)):
+        args, threshold_kwarg = [expr], float(threshold)
+    else:
+        args, threshold_kwarg = [expr, threshold], None
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=args,
+        kwargs={
+            "threshold": threshold_kwarg,
+            "carry_residual": carry_residual,
+            "min_gap": min_gap,
+            "input": input_,
+            "skip_first": skip_first,
+        },
+        is_elementwise=False,
+        function_name="symmetric_cusum_filter",
+    )
+
+
+def sadf_filter(
+    prices: IntoExpr, min_window: int, crit_value: float, lag: int = 1
+) -> pl.Expr:
+    """Flag events where the supremum ADF statistic crosses a critical value.
+
+    The SADF statistic is calculated as in `sadf`, and an event is 1 where it
+    rises above `crit_value` and 0 otherwise, so each explosive regime fires once
+    when it starts. Unlike the CUSUM filter, which samples on price changes, this
+    samples on the onset of bubbles. The first `min_window - 1` rows are null.
+
+    Args:
+    ----
+        prices: IntoExpr - The (log) prices, without nulls.
+        min_window: int - The minimum number of observations in an ADF
+            regression, at least `2 * lag + 4`.
+        crit_value: float - The SADF value that triggers an event.
+        lag: int - The number of lagged differences in each ADF regression.
+
+    Returns:
+    -------
+        pl.Expr: The events as an Int8 expression.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices],
+        kwargs={"min_window": min_window, "lag": lag, "crit_value": crit_value},
+        is_elementwise=False,
+        function_name="sadf_filter",
+    )
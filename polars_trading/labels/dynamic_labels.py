@@ -4,11 +4,13 @@ from datetime import timedelta
 from typing import TYPE_CHECKING
 
 import polars as pl
+from polars.plugins import register_plugin_function
 
+from polars_trading._utils import LIB
 from polars_trading.config import column_names
 
 if TYPE_CHECKING:
-    from polars_trading.typing import FrameType
+    from polars_trading.typing import FrameType, IntoExpr
 
 
 def daily_vol(
@@ -170,6 +172,212 @@ def apply_profit_taking_stop_loss(
     # TODO: Polars plugin here
 
 
+def triple_barrier_label(
+    index: IntoExpr,
+    prices: IntoExpr,
+    profit_taking: IntoExpr,
+    stop_loss: IntoExpr,
+    vertical_barrier: IntoExpr | None = None,
+    side: IntoExpr | None = None,
+    *,
+    positional_barrier_touch: bool = False,
+    snap_vertical_barrier: bool = False,
+    tie_break: str = "conservative",
+    return_path: bool = False,
+    return_type: str = "simple",
+    trailing_stop: float | None = None,
+) -> pl.Expr:
+    """Calculate the triple barrier label for every row.
+
+    For each row the price path starts at that row and ends at the row whose index
+    equals the vertical barrier (or the end of the data when there is none). The
+    label is 1 if the profit taking return is touched first, -1 if the stop loss
+    return is touched first and 0 if the vertical barrier is reached first.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 45
+
+    Args:
+    ----
+        index: IntoExpr - The sorted index of the price series, e.g. a timestamp.
+        prices: IntoExpr - The prices of the financial instrument.
+        profit_taking: IntoExpr - The return level of the profit taking barrier.
+            A null value disables the barrier for that row.
+        stop_loss: IntoExpr - The return level of the stop loss barrier. This should
+            be negative. A null value disables the barrier for that row.
+        vertical_barrier: IntoExpr | None - The index value of the vertical barrier.
+            Every non-null value must exist in the index unless
+            `snap_vertical_barrier` is True. If None, the price path runs to the end
+            of the data.
+        side: IntoExpr | None - The side of the bet from a primary model, 1 for long
+            and -1 for short. If given, the output also contains a `meta_label`
+            field that is 1 when `ret * side > 0` and 0 otherwise.
+        positional_barrier_touch: bool - Report `barrier_touch` as the row position
+            of the touch rather than the index value at that row. Defaults to False.
+        snap_vertical_barrier: bool - End the price path at the first index value
+            at or after the vertical barrier, so calendar barriers can fall between
+            observations. A barrier beyond the last index value runs to the end of
+            the data. Defaults to False.
+        tie_break: str - Which barrier wins when both are first touched by the
+            same observation. "conservative" picks the stop loss and "optimistic"
+            picks profit taking. Defaults to "conservative".
+        return_path: bool - Add a `price_path` field containing the prices each
+            label was calculated from. This can use a lot of memory when the
+            paths are long. Defaults to False.
+        return_type: str - The returns compared against the barriers and reported
+            as `ret`, "simple" for `p / p0 - 1` or "log" for `ln(p / p0)`.
+            Defaults to "simple".
+        trailing_stop: float | None - Stop out with a -1 label when the return
+            falls this far below the highest return of the path so far, in
+            addition to the fixed stop loss. Defaults to None.
+
+    Returns:
+    -------
+        pl.Expr: A struct expression with the fields `ret`, `label`,
+            `barrier_touch` and `holding_period`, the number of bars from the
+            event to the barrier touch, plus `meta_label` when `side` is given
+            and `price_path` when `return_path` is True.
+
+    """
+    if vertical_barrier is None:
+        vertical_barrier = pl.lit(None, dtype=pl.Int64)
+    args = [index, prices, profit_taking, stop_loss, vertical_barrier]
+    if side is not None:
+        args.append(side)
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=args,
+        kwargs={
+            "positional_barrier_touch": positional_barrier_touch,
+            "snap_vertical_barrier": snap_vertical_barrier,
+            "tie_break": tie_break,
+            "return_path": return_path,
+            "return_type": return_type,
+            "trailing_stop": trailing_stop,
+        },
+        is_elementwise=False,
+        function_name="triple_barrier_label",
+    )
+
+
+def triple_barrier_vol(
+    index: IntoExpr,
+    prices: IntoExpr,
+    volatility: IntoExpr,
+    vertical_barrier: IntoExpr | None = None,
+    *,
+    pt_mult: float | None = 1.0,
+    sl_mult: float | None = 1.0,
+    positional_barrier_touch: bool = False,
+    snap_vertical_barrier: bool = False,
+    tie_break: str = "conservative",
+    return_path: bool = False,
+    return_type: str = "simple",
+    trailing_stop: float | None = None,
+) -> pl.Expr:
+    """Calculate the triple barrier label with barriers scaled by volatility.
+
+    The profit taking barrier of each row is `volatility * pt_mult` and the stop
+    loss barrier is `-volatility * sl_mult`, so the barriers widen as volatility
+    rises. The volatility is typically the output of `daily_vol`. Labeling then
+    follows `triple_barrier_label`.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 45
+
+    Args:
+    ----
+        index: IntoExpr - The sorted index of the price series, e.g. a timestamp.
+        prices: IntoExpr - The prices of the financial instrument.
+        volatility: IntoExpr - The volatility estimate for each row. A null value
+            disables both horizontal barriers for that row.
+        vertical_barrier: IntoExpr | None - The index value of the vertical barrier.
+            If None, the price path runs to the end of the data.
+        pt_mult: float | None - The profit taking multiplier. If None, there is no
+            profit taking barrier. Defaults to 1.0.
+        sl_mult: float | None - The stop loss multiplier. If None, there is no stop
+            loss barrier. Defaults to 1.0.
+        positional_barrier_touch: bool - Report `barrier_touch` as the row position
+            of the touch rather than the index value at that row. Defaults to False.
+        snap_vertical_barrier: bool - End the price path at the first index value
+            at or after the vertical barrier, so calendar barriers can fall between
+            observations. A barrier beyond the last index value runs to the end of
+            the data. Defaults to False.
+        tie_break: str - Which barrier wins when both are first touched by the
+            same observation. "conservative" picks the stop loss and "optimistic"
+            picks profit taking. Defaults to "conservative".
+        return_path: bool - Add a `price_path` field containing the prices each
+            label was calculated from. This can use a lot of memory when the
+            paths are long. Defaults to False.
+        return_type: str - The returns compared against the barriers and reported
+            as `ret`, "simple" for `p / p0 - 1` or "log" for `ln(p / p0)`.
+            Defaults to "simple".
+        trailing_stop: float | None - Stop out with a -1 label when the return
+            falls this far below the highest return of the path so far, in
+            addition to the fixed stop loss. Defaults to None.
+
+    Returns:
+    -------
+        pl.Expr: A struct expression with the fields `ret`, `label`,
+            `barrier_touch` and `holding_period`, plus `price_path` when
+            `return_path` is True.
+
+    """
+    if vertical_barrier is None:
+        vertical_barrier = pl.lit(None, dtype=pl.Int64)
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[index, prices, volatility, vertical_barrier],
+        kwargs={
+            "pt_mult": pt_mult,
+            "sl_mult": sl_mult,
+            "positional_barrier_touch": positional_barrier_touch,
+            "snap_vertical_barrier": snap_vertical_barrier,
+            "tie_break": tie_break,
+            "return_path": return_path,
+            "return_type": return_type,
+            "trailing_stop": trailing_stop,
+        },
+        is_elementwise=False,
+        function_name="triple_barrier_vol",
+    )
+
+
+def label_validity_mask(
+    index: IntoExpr,
+    vertical_barrier: IntoExpr,
+    *,
+    snap_vertical_barrier: bool = False,
+) -> pl.Expr:
+    """Flag the rows whose vertical barrier can end a full price path.
+
+    A row is valid when its vertical barrier exists in the index at or after the
+    row, so `triple_barrier_label` can find the end of its path. This is typically
+    false for the last rows, whose barriers run past the end of the data. Rows
+    without a vertical barrier are always valid. Nulling the barriers of invalid
+    rows with `pl.when(mask).then(vertical_barrier)` avoids the error raised for
+    a missing barrier.
+
+    Args:
+    ----
+        index: IntoExpr - The sorted index of the price series, e.g. a timestamp.
+        vertical_barrier: IntoExpr - The index value of the vertical barrier.
+        snap_vertical_barrier: bool - Match the `triple_barrier_label` option, so a
+            barrier is valid when it is not beyond the last index value. Defaults
+            to False.
+
+    Returns:
+    -------
+        pl.Expr: A boolean expression that is true for valid rows.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[index, vertical_barrier],
+        kwargs={"snap_vertical_barrier": snap_vertical_barrier},
+        is_elementwise=False,
+        function_name="label_validity_mask",
+    )
+
+
 def get_triple_barrier_label() -> FrameType:
     """Calculate the triple barrier label.
 
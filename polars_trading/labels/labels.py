@@ -5,8 +5,9 @@ from __future__ import annotations
 from typing import TYPE_CHECKING
 
 import polars as pl
+from polars.plugins import register_plugin_function
 
-from polars_trading._utils import parse_into_expr
+from polars_trading._utils import LIB, parse_into_expr
 
 if TYPE_CHECKING:
     from polars_trading.typing import IntoExpr
@@ -102,3 +103,63 @@ def fixed_time_return(
     if symbol is not None:
         return_expr = return_expr.over(parse_into_expr(symbol))
     return return_expr
+
+
+def trend_scan(prices: IntoExpr, min_horizon: int, max_horizon: int) -> pl.Expr:
+    """Calculate trend scanning labels.
+
+    For each observation, a linear time trend is fit to the prices over every
+    horizon from `min_horizon` to `max_horizon` observations ahead (including the
+    observation itself). The horizon with the largest absolute t-value of the slope
+    is chosen and the label is the sign of that t-value. Observations with fewer
+    than `min_horizon` observations remaining are null.
+
+    Reference: Marco Lopez de Prado, Machine Learning for Asset Managers, pg. 68
+
+    Args:
+    ----
+        prices: IntoExpr - The prices of the financial instrument.
+        min_horizon: int - The shortest horizon to fit. Must be at least 3.
+        max_horizon: int - The longest horizon to fit.
+
+    Returns:
+    -------
+        pl.Expr: A struct expression with the fields `t_value`, `label` and
+            `horizon`.
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices],
+        kwargs={"min_horizon": min_horizon, "max_horizon": max_horizon},
+        is_elementwise=False,
+        function_name="trend_scan",
+    )
+
+
+def fixed_horizon_label(
+    prices: IntoExpr, horizon: int, threshold: float = 0.0
+) -> pl.Expr:
+    """Calculate the fixed horizon label.
+
+    The label for time t is based on the return from the price at time t to the
+    price at time t+{horizon}. It is 1 if the return is greater than the threshold,
+    -1 if the return is less than the negative threshold, and 0 otherwise. Rows
+    with fewer than `horizon` observations remaining are null.
+
+    Args:
+    ----
+        prices: IntoExpr - The prices of the financial instrument.
+        horizon: int - The number of periods to look forward.
+        threshold: float - The threshold to classify the return. Defaults to 0.0.
+
+    Returns:
+    -------
+        pl.Expr: The fixed horizon label as an Int8 expression.
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices],
+        kwargs={"horizon": horizon, "threshold": threshold},
+        is_elementwise=False,
+        function_name="fixed_horizon_label",
+    )
@@ -0,0 +1,170 @@
+"""Module containing functions to weight overlapping labels."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def label_concurrency(start: IntoExpr, end: IntoExpr) -> pl.Expr:
+    """Count the number of labels spanning each bar.
+
+    Each label spans the bars from its start to its end position, as in
+    `label_uniqueness`, and the concurrency `c_t` of a bar is the number of labels
+    that span it. Each row is both a bar and the event labeled at it, so the end
+    positions must be within the frame. Null spans are left out.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 60
+
+    Args:
+    ----
+        start: IntoExpr - The bar position at which each label starts.
+        end: IntoExpr - The bar position at which each label ends (inclusive).
+
+    Returns:
+    -------
+        pl.Expr: The number of labels spanning each bar as an Int64 expression.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[start, end],
+        is_elementwise=False,
+        function_name="label_concurrency",
+    )
+
+
+def label_uniqueness(start: IntoExpr, end: IntoExpr) -> pl.Expr:
+    """Calculate the average uniqueness of each label.
+
+    Each label spans the bars from its start to its end position, e.g. the row
+    position of the event and the positional `barrier_touch` of
+    `triple_barrier_label`. The concurrency of a bar is the number of labels that
+    span it, and the uniqueness of a label is the mean of `1 / concurrency` over
+    the bars it spans.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 60
+
+    Args:
+    ----
+        start: IntoExpr - The bar position at which each label starts.
+        end: IntoExpr - The bar position at which each label ends (inclusive).
+
+    Returns:
+    -------
+        pl.Expr: The average uniqueness of each label.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[start, end],
+        is_elementwise=False,
+        function_name="label_uniqueness",
+    )
+
+
+def label_return_weights(prices: IntoExpr, start: IntoExpr, end: IntoExpr) -> pl.Expr:
+    """Calculate the return attribution weight of each label.
+
+    The log return of each bar is divided equally among the labels spanning it.
+    The weight of a label is the absolute sum of its share of the returns from its
+    start to its end position, so labels over large, unshared moves get more weight.
+    The weights are scaled to sum to the number of labels.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 68
+
+    Args:
+    ----
+        prices: IntoExpr - The prices of the financial instrument, one per bar.
+        start: IntoExpr - The bar position at which each label starts.
+        end: IntoExpr - The bar position at which each label ends (inclusive).
+
+    Returns:
+    -------
+        pl.Expr: The return attribution weight of each label.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices, start, end],
+        is_elementwise=False,
+        function_name="label_return_weights",
+    )
+
+
+def time_decay_weights(index: IntoExpr, last_weight: float) -> pl.Expr:
+    """Calculate linear time decay weights.
+
+    The weights decay linearly along the ordered index so that the newest
+    observation has weight 1 and the oldest has weight `last_weight`. A negative
+    `last_weight` gives zero weight to the oldest `-last_weight` fraction of the
+    index. The index is commonly the cumulative sum of `label_uniqueness`, so that
+    decay is not faster when labels overlap.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 70
+
+    Args:
+    ----
+        index: IntoExpr - The ordered index to decay along, e.g. a timestamp or
+            cumulative uniqueness.
+        last_weight: float - The weight of the oldest observation, in (-1, 1].
+            1.0 means no decay and 0.0 decays linearly to zero.
+
+    Returns:
+    -------
+        pl.Expr: The time decay weight of each observation.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[index],
+        kwargs={"last_weight": last_weight},
+        is_elementwise=False,
+        function_name="time_decay_weights",
+    )
+
+
+def sequential_bootstrap(
+    start: IntoExpr,
+    end: IntoExpr,
+    sample_length: int | None = None,
+    seed: int | None = None,
+) -> pl.Expr:
+    """Draw a bootstrap sample of labels with the sequential bootstrap.
+
+    Each draw picks a label with probability proportional to its average
+    uniqueness given the labels already drawn, which reduces the overlap between
+    the labels in each bagged training set.
+
+    Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 63
+
+    Args:
+    ----
+        start: IntoExpr - The bar position at which each label starts.
+        end: IntoExpr - The bar position at which each label ends (inclusive).
+        sample_length: int | None - The number of draws. Defaults to the number of
+            labels.
+        seed: int | None - The seed of the random number generator, for
+            reproducible samples.
+
+    Returns:
+    -------
+        pl.Expr: The row positions of the drawn labels, of length `sample_length`.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[start, end],
+        kwargs={"sample_length": sample_length, "seed": seed},
+        is_elementwise=False,
+        changes_length=True,
+        function_name="sequential_bootstrap",
+    )
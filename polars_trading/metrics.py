@@ -0,0 +1,275 @@
+"""Module containing strategy performance metrics."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def rolling_sharpe(
+    returns: IntoExpr,
+    window: int,
+    periods_per_year: float | None = None,
+    *,
+    bar_interval_ns: int | None = None,
+    trading_days: float = 252.0,
+    hours_per_day: float = 6.5,
+) -> pl.Expr:
+    """Calculate the annualized Sharpe ratio over a rolling window.
+
+    The Sharpe ratio is `mean / std * sqrt(periods_per_year)` using the sample
+    standard deviation of the returns in each window. Windows with zero variance
+    are null rather than infinite.
+
+    The annualization factor is either `periods_per_year` or derived from the
+    `bar_interval_ns` of the returns. Bars of a day or more are counted in
+    `trading_days`, and intraday bars within sessions of `hours_per_day` hours,
+    so hourly bars of a 6.5 hour session give `252 * 6.5` periods a year.
+
+    Args:
+    ----
+        returns: IntoExpr - The periodic returns of the strategy.
+        window: int - The number of returns in each window, at least 2.
+        periods_per_year: float | None - The number of return periods in a year.
+            Defaults to `trading_days`, i.e. daily returns.
+        bar_interval_ns: int | None - The interval of the return bars in
+            nanoseconds, to derive `periods_per_year` from instead.
+        trading_days: float - The number of trading days in a year.
+        hours_per_day: float - The number of trading hours in a day, to count
+            intraday bars in.
+
+    Returns:
+    -------
+        pl.Expr: The rolling annualized Sharpe ratio.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={
+            "window": window,
+            "periods_per_year": periods_per_year,
+            "bar_interval_ns": bar_interval_ns,
+            "trading_days": trading_days,
+            "hours_per_day": hours_per_day,
+        },
+        is_elementwise=False,
+        function_name="rolling_sharpe",
+    )
+
+
+def drawdown_series(equity: IntoExpr) -> pl.Expr:
+    """Calculate the running percentage drawdown of an equity curve.
+
+    The drawdown at each row is `equity / running_peak - 1`, so it is zero at a new
+    peak and negative below it.
+
+    Args:
+    ----
+        equity: IntoExpr - The cumulative equity or price series.
+
+    Returns:
+    -------
+        pl.Expr: The drawdown at each row.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[equity],
+        is_elementwise=False,
+        function_name="drawdown_series",
+    )
+
+
+def max_drawdown(equity: IntoExpr) -> pl.Expr:
+    """Calculate the maximum drawdown of an equity curve.
+
+    Args:
+    ----
+        equity: IntoExpr - The cumulative equity or price series.
+
+    Returns:
+    -------
+        pl.Expr: A single row struct with the worst `drawdown` and the row
+            positions of the peak it `start`s from and the trough it `end`s at.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[equity],
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="max_drawdown",
+    )
+
+
+def probabilistic_sharpe(returns: IntoExpr, benchmark_sharpe: float = 0.0) -> pl.Expr:
+    """Calculate the Probabilistic Sharpe Ratio (PSR).
+
+    The PSR is the probability that the true Sharpe ratio exceeds a benchmark,
+    given the length of the track record and the skewness and kurtosis of its
+    returns. Both Sharpe ratios are per period, i.e. not annualized. Null returns
+    are skipped.
+
+    Reference: Bailey and Lopez de Prado (2012), "The Sharpe Ratio Efficient
+    Frontier"
+
+    Args:
+    ----
+        returns: IntoExpr - The periodic returns of the strategy.
+        benchmark_sharpe: float - The per period Sharpe ratio to test against.
+
+    Returns:
+    -------
+        pl.Expr: The probability in [0, 1] as a single Float64 value.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={"benchmark_sharpe": benchmark_sharpe},
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="probabilistic_sharpe",
+    )
+
+
+def deflated_sharpe(
+    returns: IntoExpr, num_trials: int, variance_of_trial_sharpes: float
+) -> pl.Expr:
+    """Calculate the Deflated Sharpe Ratio (DSR).
+
+    The DSR is the Probabilistic Sharpe Ratio against the expected maximum Sharpe
+    ratio of `num_trials` skill-less strategies. It corrects for selection bias
+    when the strategy is the best of many backtests, and falls as the number of
+    trials rises. Sharpe ratios are per period, i.e. not annualized.
+
+    Reference: Bailey and Lopez de Prado (2014), "The Deflated Sharpe Ratio"
+
+    Args:
+    ----
+        returns: IntoExpr - The periodic returns of the selected strategy.
+        num_trials: int - The number of independent strategies tried.
+        variance_of_trial_sharpes: float - The variance of the per period Sharpe
+            ratios across the trials.
+
+    Returns:
+    -------
+        pl.Expr: The probability in [0, 1] as a single Float64 value.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={
+            "num_trials": num_trials,
+            "variance_of_trial_sharpes": variance_of_trial_sharpes,
+        },
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="deflated_sharpe",
+    )
+
+
+def calmar_ratio(
+    returns: IntoExpr,
+    periods_per_year: float | None = None,
+    *,
+    bar_interval_ns: int | None = None,
+    trading_days: float = 252.0,
+    hours_per_day: float = 6.5,
+) -> pl.Expr:
+    """Calculate the Calmar ratio of a track record.
+
+    The Calmar ratio is the compound annual growth rate of the returns divided by
+    the depth of the maximum drawdown of their equity curve. It is null when the
+    equity never draws down. Null returns are skipped. The annualization factor
+    is resolved as in `rolling_sharpe`.
+
+    Args:
+    ----
+        returns: IntoExpr - The periodic simple returns of the strategy.
+        periods_per_year: float | None - The number of return periods in a year.
+            Defaults to `trading_days`, i.e. daily returns.
+        bar_interval_ns: int | None - The interval of the return bars in
+            nanoseconds, to derive `periods_per_year` from instead.
+        trading_days: float - The number of trading days in a year.
+        hours_per_day: float - The number of trading hours in a day, to count
+            intraday bars in.
+
+    Returns:
+    -------
+        pl.Expr: The Calmar ratio as a single Float64 value.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={
+            "periods_per_year": periods_per_year,
+            "bar_interval_ns": bar_interval_ns,
+            "trading_days": trading_days,
+            "hours_per_day": hours_per_day,
+        },
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="calmar_ratio",
+    )
+
+
+def sortino_ratio(
+    returns: IntoExpr,
+    periods_per_year: float | None = None,
+    target_return: float = 0.0,
+    *,
+    bar_interval_ns: int | None = None,
+    trading_days: float = 252.0,
+    hours_per_day: float = 6.5,
+) -> pl.Expr:
+    """Calculate the annualized Sortino ratio of a track record.
+
+    The Sortino ratio is the mean excess return over `target_return` divided by
+    the downside deviation, the root mean square of the shortfalls below the
+    target, annualized by `sqrt(periods_per_year)`. It is null when no return
+    falls below the target. Null returns are skipped. The annualization factor is
+    resolved as in `rolling_sharpe`.
+
+    Args:
+    ----
+        returns: IntoExpr - The periodic returns of the strategy.
+        periods_per_year: float | None - The number of return periods in a year.
+            Defaults to `trading_days`, i.e. daily returns.
+        target_return: float - The per period minimum acceptable return.
+        bar_interval_ns: int | None - The interval of the return bars in
+            nanoseconds, to derive `periods_per_year` from instead.
+        trading_days: float - The number of trading days in a year.
+        hours_per_day: float - The number of trading hours in a day, to count
+            intraday bars in.
+
+    Returns:
+    -------
+        pl.Expr: The Sortino ratio as a single Float64 value.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={
+            "periods_per_year": periods_per_year,
+            "bar_interval_ns": bar_interval_ns,
+            "trading_days": trading_days,
+            "hours_per_day": hours_per_day,
+            "target_return": target_return,
+        },
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="sortino_ratio",
+    )
@@ -0,0 +1,391 @@
+"""Module containing functions to price options."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def black_scholes(
+    spot: IntoExpr,
+    strike: IntoExpr,
+    r: IntoExpr,
+    sigma: IntoExpr,
+    t: IntoExpr,
+    type_: IntoExpr,
+) -> pl.Expr:
+    """Price European options on a non-dividend paying asset with Black-Scholes.
+
+    Rows with a null input, a non-positive spot, strike, volatility or time to
+    expiry, or a type other than `"call"` or `"put"` are null.
+
+    Any input can be a scalar, e.g. `pl.lit(0.05)` for the rate of a whole strike
+    ladder, which is broadcast to the length of the other inputs. This holds for
+    every pricing function in this module.
+
+    Args:
+    ----
+        spot: IntoExpr - The price of the underlying.
+        strike: IntoExpr - The strike price.
+        r: IntoExpr - The continuously compounded risk-free rate.
+        sigma: IntoExpr - The annualized volatility of the underlying.
+        t: IntoExpr - The time to expiry in years.
+        type_: IntoExpr - The option type, `"call"` or `"put"`.
+
+    Returns:
+    -------
+        pl.Expr: The option prices.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[spot, strike, r, sigma, t, type_],
+        is_elementwise=True,
+        function_name="black_scholes",
+    )
+
+
+def monte_carlo_price(
+    spot: IntoExpr,
+    strike: IntoExpr,
+    r: IntoExpr,
+    sigma: IntoExpr,
+    t: IntoExpr,
+    type_: IntoExpr,
+    *,
+    n_paths: int = 10_000,
+    n_steps: int = 1,
+    antithetic: bool = True,
+    seed: int | None = None,
+) -> pl.Expr:
+    """Price European options by Monte Carlo simulation.
+
+    Each option is priced by simulating `n_paths` risk-neutral geometric Brownian
+    motion paths to expiry and averaging the payoffs, discounted at `r`. With
+    `antithetic`, each path is paired with the path of its negated shocks, which
+    reduces the variance of the estimate for the same number of paths. Invalid
+    rows are null as in `black_scholes`.
+
+    Args:
+    ----
+        spot: IntoExpr - The price of the underlying.
+        strike: IntoExpr - The strike price.
+        r: IntoExpr - The continuously compounded risk-free rate.
+        sigma: IntoExpr - The annualized volatility of the underlying.
+        t: IntoExpr - The time to expiry in years.
+        type_: IntoExpr - The option type, `"call"` or `"put"`.
+        n_paths: int - The number of paths to simulate per option.
+        n_steps: int - The number of time steps per path.
+        antithetic: bool - Whether to use antithetic variates.
+        seed: int | None - The random seed, for reproducible prices.
+
+    Returns:
+    -------
+        pl.Expr: The simulated option prices.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[spot, strike, r, sigma, t, type_],
+        kwargs={
+            "n_paths": n_paths,
+            "n_steps": n_steps,
+            "antithetic": antithetic,
+            "seed": seed,
+        },
+        is_elementwise=False,
+        function_name="monte_carlo_price",
+    )
+
+
+def asian_option(
+    spot: IntoExpr,
+    strike: IntoExpr,
+    r: IntoExpr,
+    sigma: IntoExpr,
+    t: IntoExpr,
+    type_: IntoExpr,
+    *,
+    n_paths: int = 10_000,
+    n_steps: int = 252,
+    antithetic: bool = True,
+    seed: int | None = None,
+) -> pl.Expr:
+    """Price arithmetic average Asian options by Monte Carlo simulation.
+
+    The payoff is that of a call or put on the mean price of the underlying,
+    sampled at the end of each of `n_steps` equal steps to expiry, and is
+    discounted at `r` as in `black_scholes`. Averaging lowers the volatility of
+    the price the option pays on, so an Asian option is cheaper than the
+    equivalent European option. Invalid rows are null as in `black_scholes`.
+
+    Args:
+    ----
+        spot: IntoExpr - The price of the underlying.
+        strike: IntoExpr - The strike price.
+        r: IntoExpr - The continuously compounded risk-free rate.
+        sigma: IntoExpr - The annualized volatility of the underlying.
+        t: IntoExpr - The time to expiry in years.
+        type_: IntoExpr - The option type, `"call"` or `"put"`.
+        n_paths: int - The number of paths to simulate per option.
+        n_steps: int - The number of averaging dates per path.
+        antithetic: bool - Whether to use antithetic variates.
+        seed: int | None - The random seed, for reproducible prices.
+
+    Returns:
+    -------
+        pl.Expr: The simulated option prices.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[spot, strike, r, sigma, t, type_],
+        kwargs={
+            "n_paths": n_paths,
+            "n_steps": n_steps,
+            "antithetic": antithetic,
+            "seed": seed,
+        },
+        is_elementwise=False,
+        function_name="asian_option",
+    )
+
+
+def barrier_option(
+    spot: IntoExpr,
+    strike: IntoExpr,
+    r: IntoExpr,
+    sigma: IntoExpr,
+    t: IntoExpr,
+    type_: IntoExpr,
+    barrier: IntoExpr,
+    barrier_type: str,
+) -> pl.Expr:
+    """Price European barrier options with the Reiner-Rubinstein formulas.
+
+    A knock-in option becomes a vanilla option, and a knock-out option becomes
+    worthless, once the underlying touches the barrier before expiry. The barrier
+    is monitored continuously and there is no rebate, so a knock-in and a
+    knock-out option on the same barrier add up to the vanilla option. If the spot
+    has already breached the barrier, the price is that of the vanilla option for
+    knock-ins and zero for knock-outs. Invalid rows and non-positive barriers are
+    null.
+
+    Reference: Haug, The Complete Guide to Option Pricing Formulas, pg. 152
+
+    Args:
+    ----
+        spot: IntoExpr - The price of the underlying.
+        strike: IntoExpr - The strike price.
+        r: IntoExpr - The continuously compounded risk-free rate.
+        sigma: IntoExpr - The annualized volatility of the underlying.
+        t: IntoExpr - The time to expiry in years.
+        type_: IntoExpr - The option type, `"call"` or `"put"`.
+        barrier: IntoExpr - The barrier level.
+        barrier_type: str - One of `"up_in"`, `"up_out"`, `"down_in"` or
+            `"down_out"`.
+
+    Returns:
+    -------
+        pl.Expr: The option prices.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[spot, strike, r, sigma, t, type_, barrier],
+        kwargs={"barrier_type": barrier_type},
+        is_elementwise=True,
+        function_name="barrier_option",
+    )
+
+
+def vol_smile_fit(
+    strike: IntoExpr, implied_vol: IntoExpr, forward: IntoExpr
+) -> pl.Expr:
+    """Smooth the implied vols of an expiry with a quadratic smile.
+
+    The smile `vol = a + b k + c k^2` in the log-moneyness `k = ln(strike /
+    forward)` is fit by least squares and evaluated at each strike. Use
+    `.over("expiry")` to fit each expiry of a chain separately. Rows with a null
+    implied vol are left out of the fit but still get a fitted vol. The fit is
+    null with fewer than three points or three distinct strikes.
+
+    Args:
+    ----
+        strike: IntoExpr - The strike price.
+        implied_vol: IntoExpr - The implied volatility at the strike.
+        forward: IntoExpr - The forward (or spot) price of the underlying.
+
+    Returns:
+    -------
+        pl.Expr: The fitted implied volatility at each strike.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[strike, implied_vol, forward],
+        is_elementwise=False,
+        function_name="vol_smile_fit",
+    )
+
+
+def atm_iv(strike: IntoExpr, implied_vol: IntoExpr, vega: IntoExpr) -> pl.Expr:
+    """Summarize the implied vols of an expiry as their vega-weighted mean.
+
+    Vega peaks at the money, so the mean is dominated by the near the money
+    strikes and is the headline vol of the expiry. Aggregate each expiry of a
+    chain with `group_by("expiry").agg(...)` or `.over("expiry")`. Rows with a
+    null or non-positive strike, a null implied vol or a null or negative vega
+    are left out. The mean is null if the total vega is zero.
+
+    Args:
+    ----
+        strike: IntoExpr - The strike price.
+        implied_vol: IntoExpr - The implied volatility at the strike.
+        vega: IntoExpr - The vega of the option at the strike.
+
+    Returns:
+    -------
+        pl.Expr: The vega-weighted mean implied volatility.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[strike, implied_vol, vega],
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="atm_iv",
+    )
+
+
+def forward_price(spot: IntoExpr, r: IntoExpr, q: IntoExpr, t: IntoExpr) -> pl.Expr:
+    """Calculate the forward price `F = S e^{(r - q) t}` of the underlying.
+
+    Args:
+    ----
+        spot: IntoExpr - The price of the underlying.
+        r: IntoExpr - The continuously compounded risk-free rate.
+        q: IntoExpr - The continuous dividend yield of the underlying.
+        t: IntoExpr - The time to expiry in years.
+
+    Returns:
+    -------
+        pl.Expr: The forward prices.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[spot, r, q, t],
+        is_elementwise=True,
+        function_name="forward_price",
+    )
+
+
+def log_moneyness(
+    strike: IntoExpr, spot: IntoExpr, r: IntoExpr, q: IntoExpr, t: IntoExpr
+) -> pl.Expr:
+    """Calculate the log-moneyness `ln(K / F)` of each strike.
+
+    The forward `F` is calculated as in `forward_price`, so a strike at the money
+    forward has a log-moneyness of 0. Rows with a non-positive strike or forward
+    are null.
+
+    Args:
+    ----
+        strike: IntoExpr - The strike price.
+        spot: IntoExpr - The price of the underlying.
+        r: IntoExpr - The continuously compounded risk-free rate.
+        q: IntoExpr - The continuous dividend yield of the underlying.
+        t: IntoExpr - The time to expiry in years.
+
+    Returns:
+    -------
+        pl.Expr: The log-moneyness of each strike.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[strike, spot, r, q, t],
+        is_elementwise=True,
+        function_name="log_moneyness",
+    )
+
+
+def put_call_parity_violation(
+    call: IntoExpr,
+    put: IntoExpr,
+    spot: IntoExpr,
+    strike: IntoExpr,
+    r: IntoExpr,
+    t: IntoExpr,
+) -> pl.Expr:
+    """Calculate the signed deviation of option prices from put-call parity.
+
+    European options on a non-dividend paying asset satisfy `C - P = S - K e^{-rt}`,
+    so the deviation `(C - P) - (S - K e^{-rt})` flags bad quotes or arbitrage.
+    A positive deviation means the call is rich relative to the put.
+
+    Args:
+    ----
+        call: IntoExpr - The price of the call.
+        put: IntoExpr - The price of the put with the same strike and expiry.
+        spot: IntoExpr - The price of the underlying.
+        strike: IntoExpr - The strike price.
+        r: IntoExpr - The continuously compounded risk-free rate.
+        t: IntoExpr - The time to expiry in years.
+
+    Returns:
+    -------
+        pl.Expr: The deviation from parity.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[call, put, spot, strike, r, t],
+        is_elementwise=True,
+        function_name="put_call_parity_violation",
+    )
+
+
+def gamma_pnl(
+    gamma: IntoExpr,
+    spot: IntoExpr,
+    realized_var: IntoExpr,
+    implied_var: IntoExpr,
+    dt: float,
+) -> pl.Expr:
+    """Calculate the gamma P&L of a delta-hedged option position per step.
+
+    Over a step of `dt` years, a delta-hedged position earns
+    `0.5 * gamma * spot^2 * (realized_var - implied_var) * dt`, its gamma gains
+    net of the theta it pays. A long gamma position profits when the underlying
+    realizes more variance than implied. Use `cum_sum` for the running P&L.
+
+    Args:
+    ----
+        gamma: IntoExpr - The gamma of the position.
+        spot: IntoExpr - The price of the underlying.
+        realized_var: IntoExpr - The annualized realized variance over the step.
+        implied_var: IntoExpr - The annualized implied variance.
+        dt: float - The length of each step in years, e.g. `1 / 252`.
+
+    Returns:
+    -------
+        pl.Expr: The gamma P&L of each step.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[gamma, spot, realized_var, implied_var],
+        kwargs={"dt": dt},
+        is_elementwise=True,
+        function_name="gamma_pnl",
+    )
@@ -0,0 +1,76 @@
+"""Module containing functions to account for the PnL of trades."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def round_trips(quantity: IntoExpr, price: IntoExpr) -> pl.Expr:
+    """Group a stream of fills into round-trip trades and their PnL.
+
+    A round trip runs from the fill that opens a position from flat to the fill
+    that brings it back to flat. The position is carried at its average entry
+    price, so partial exits realize PnL against it. A fill that flips the
+    position closes the current trip and opens the next one with the remainder.
+    The return is the PnL over the notional of the fills that opened or added to
+    the position. Fills with a null or zero quantity or a null price are skipped,
+    and a trip still open after the last fill is left out.
+
+    Args:
+    ----
+        quantity: IntoExpr - The signed fill quantity, positive for buys.
+        price: IntoExpr - The fill price.
+
+    Returns:
+    -------
+        pl.Expr: A list of structs with the `entry_idx` and `exit_idx` row
+            positions, `pnl` and `return` of each round trip.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[quantity, price],
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="round_trips",
+    )
+
+
+def cost_basis(quantity: IntoExpr, price: IntoExpr, method: str = "fifo") -> pl.Expr:
+    """Account for the realized PnL of each fill by matching it against open lots.
+
+    Each fill that adds to the position opens a lot at its price. A fill against
+    the position closes lots, the oldest first with `"fifo"` or the newest first
+    with `"lifo"`, and realizes the difference between its price and theirs.
+    With `"average"`, the lots are merged into one at their average price. A fill
+    that flips the position closes every lot and opens a new one with the
+    remainder. Fills with a null quantity or price are null.
+
+    Args:
+    ----
+        quantity: IntoExpr - The signed fill quantity, positive for buys.
+        price: IntoExpr - The fill price.
+        method: str - One of `"fifo"`, `"lifo"` or `"average"`.
+
+    Returns:
+    -------
+        pl.Expr: A struct of the `realized_pnl` of each fill and the `avg_cost` of
+            the lots left open after it, null when flat.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[quantity, price],
+        kwargs={"method": method},
+        is_elementwise=False,
+        function_name="cost_basis",
+    )
@@ -0,0 +1,115 @@
+"""Module containing functions to calculate returns from prices."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def log_returns(
+    prices: IntoExpr,
+    *,
+    base: float | None = None,
+    fill_first: float | None = None,
+) -> pl.Expr:
+    """Calculate the log return of each price from the previous price.
+
+    The return is `log(p_t / p_{t-1})`, in the natural log unless a `base` is
+    given. The first price has no previous price and is filled with `fill_first`.
+    Steps from or to a null or non-positive price are null.
+
+    Args:
+    ----
+        prices: IntoExpr - The prices.
+        base: float | None - The base of the logarithm. Defaults to e.
+        fill_first: float | None - The return of the first price, e.g. 0.0.
+            Defaults to null.
+
+    Returns:
+    -------
+        pl.Expr: The log returns.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[prices],
+        kwargs={"base": base, "fill_first": fill_first},
+        is_elementwise=False,
+        function_name="log_returns",
+    )
+
+
+def cumulative_returns(
+    returns: IntoExpr,
+    *,
+    method: str = "simple",
+    starting_value: float = 1.0,
+) -> pl.Expr:
+    """Build the equity curve of a series of per-period returns.
+
+    The equity starts at `starting_value` and compounds each return, as
+    `∏(1 + r)` for `"simple"` returns or `exp(Σr)` for `"log"` returns. A null
+    return carries the previous equity forward.
+
+    Args:
+    ----
+        returns: IntoExpr - The per-period returns.
+        method: str - Whether the returns are `"simple"` or `"log"` returns.
+        starting_value: float - The equity before the first return.
+
+    Returns:
+    -------
+        pl.Expr: The equity after each return.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns],
+        kwargs={"method": method, "starting_value": starting_value},
+        is_elementwise=False,
+        function_name="cumulative_returns",
+    )
+
+
+def apply_costs(
+    returns: IntoExpr,
+    turnover: IntoExpr,
+    *,
+    commission_bps: float = 0.0,
+    spread_bps: float = 0.0,
+) -> pl.Expr:
+    """Haircut returns by the cost of the trading that produced them.
+
+    Each return is reduced by `|turnover| * (commission_bps + spread_bps / 2)`, in
+    basis points, as every unit traded pays the commission and crosses half of
+    the bid-ask spread. Periods without turnover are unaffected. Rows with a null
+    return or turnover are null.
+
+    Args:
+    ----
+        returns: IntoExpr - The gross returns.
+        turnover: IntoExpr - The signed change in position in each period, as a
+            fraction of capital, e.g. `position.diff()`.
+        commission_bps: float - The commission per unit traded in basis points.
+        spread_bps: float - The full bid-ask spread in basis points.
+
+    Returns:
+    -------
+        pl.Expr: The net returns.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[returns, turnover],
+        kwargs={"commission_bps": commission_bps, "spread_bps": spread_bps},
+        is_elementwise=True,
+        function_name="apply_costs",
+    )
@@ -0,0 +1,393 @@
+"""Module containing statistical tests and estimators for financial time series."""
+
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from polars.plugins import register_plugin_function
+
+from polars_trading._utils import LIB
+
+if TYPE_CHECKING:
+    import polars as pl
+
+    from polars_trading.typing import IntoExpr
+
+
+def adf_test(expr: IntoExpr, max_lag: int) -> pl.Expr:
+    """Run the Augmented Dickey-Fuller test for a unit root.
+
+    The regression `Δy_t = a + b y_{t-1} + sum_i c_i Δy_{t-i}` is fit with up to
+    `max_lag` lagged differences, choosing the lag that minimises the AIC. The
+    statistic is the t-value of `b` and can be compared against the standard
+    critical values with a constant (about -3.43, -2.86 and -2.57 at the 1%, 5%
+    and 10% levels). Null values are skipped.
+
+    Args:
+    ----
+        expr: IntoExpr - The series to test, e.g. prices or fractionally
+            differentiated prices.
+        max_lag: int - The maximum number of lagged differences to include.
+
+    Returns:
+    -------
+        pl.Expr: A single row struct with the `statistic` and the `used_lag`.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"max_lag": max_lag},
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="adf_test",
+    )
+
+
+def hurst_exponent(expr: IntoExpr, max_lag: int) -> pl.Expr:
+    """Estimate the Hurst exponent of a series.
+
+    The standard deviation of the differences `y_{t+lag} - y_t` scales like
+    `lag^H`, so the exponent is the slope of a log-log fit over lags 1 to
+    `max_lag`. Values above 0.5 indicate a trending series, 0.5 a random walk and
+    below 0.5 a mean-reverting series. Null values are skipped.
+
+    Args:
+    ----
+        expr: IntoExpr - The series to analyse, e.g. log prices or cumulative
+            returns.
+        max_lag: int - The largest lag in the fit, at least 2.
+
+    Returns:
+    -------
+        pl.Expr: The Hurst exponent as a single Float64 value.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"max_lag": max_lag},
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="hurst_exponent",
+    )
+
+
+def ewm_mean(
+    expr: IntoExpr,
+    span: float | None = None,
+    alpha: float | None = None,
+    *,
+    min_periods: int = 0,
+) -> pl.Expr:
+    """Calculate the exponentially weighted moving average of a series.
+
+    The weight of the value `i` steps back is `(1 - alpha)^i`, normalised by the
+    sum of the weights, so the first values are not biased towards zero during
+    warm-up. Null values stay null but still decay the weights of the values
+    before them.
+
+    Args:
+    ----
+        expr: IntoExpr - The series to average.
+        span: float | None - The span of the average, giving
+            `alpha = 2 / (span + 1)`. Exactly one of `span` and `alpha` must be
+            given.
+        alpha: float | None - The smoothing factor in (0, 1].
+        min_periods: int - The number of non-null values required before a value
+            is produced. Defaults to 0.
+
+    Returns:
+    -------
+        pl.Expr: The exponentially weighted moving average.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"span": span, "alpha": alpha, "min_periods": min_periods},
+        is_elementwise=False,
+        function_name="ewm_mean",
+    )
+
+
+def ewm_std(
+    expr: IntoExpr,
+    span: float | None = None,
+    alpha: float | None = None,
+    *,
+    bias: bool = False,
+    min_periods: int = 0,
+) -> pl.Expr:
+    """Calculate the exponentially weighted moving standard deviation of a series.
+
+    The weights follow `ewm_mean`. Unless `bias` is True, the weighted variance is
+    corrected by `(sum w)^2 / ((sum w)^2 - sum w^2)`, which reduces to the usual
+    `n / (n - 1)` correction for equal weights. The first value has no bias
+    corrected standard deviation and is null.
+
+    Args:
+    ----
+        expr: IntoExpr - The series, e.g. returns.
+        span: float | None - The span of the average, giving
+            `alpha = 2 / (span + 1)`. Exactly one of `span` and `alpha` must be
+            given.
+        alpha: float | None - The smoothing factor in (0, 1].
+        bias: bool - Return the biased standard deviation. Defaults to False.
+        min_periods: int - The number of non-null values required before a value
+            is produced. Defaults to 0.
+
+    Returns:
+    -------
+        pl.Expr: The exponentially weighted moving standard deviation.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={
+            "span": span,
+            "alpha": alpha,
+            "bias": bias,
+            "min_periods": min_periods,
+        },
+        is_elementwise=False,
+        function_name="ewm_std",
+    )
+
+
+def ewm_corr(
+    x: IntoExpr,
+    y: IntoExpr,
+    span: float | None = None,
+    alpha: float | None = None,
+    *,
+    min_periods: int = 0,
+) -> pl.Expr:
+    """Calculate the exponentially weighted moving correlation of two series.
+
+    The means, variances and covariance of the pairs are weighted as in
+    `ewm_mean` and updated online, so the correlation tracks a changing
+    relationship, e.g. between the legs of a pair. Pairs with a null are null but
+    still decay the weights of the pairs before them. The correlation is null
+    during warm-up, before `min_periods` pairs or while either series is constant.
+
+    Args:
+    ----
+        x: IntoExpr - The first series, e.g. returns.
+        y: IntoExpr - The second series.
+        span: float | None - The span of the average, giving
+            `alpha = 2 / (span + 1)`. Exactly one of `span` and `alpha` must be
+            given.
+        alpha: float | None - The smoothing factor in (0, 1].
+        min_periods: int - The number of non-null pairs required before a value
+            is produced. Defaults to 0.
+
+    Returns:
+    -------
+        pl.Expr: The exponentially weighted moving correlation.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[x, y],
+        kwargs={"span": span, "alpha": alpha, "min_periods": min_periods},
+        is_elementwise=False,
+        function_name="ewm_corr",
+    )
+
+
+def rolling_ols(y: IntoExpr, x: IntoExpr, window: int, lags: int = 0) -> pl.Expr:
+    """Fit a rolling ordinary least squares regression of `y` on `x`.
+
+    Each window ending at a row is fit with `y = alpha + beta x`, e.g. to estimate
+    the hedge ratio of a pair. Windows containing a null, or in which `x` is
+    constant, are null, as are the first `window - 1` rows.
+
+    The standard error of `beta` is the Newey-West HAC standard error with `lags`
+    lags, which corrects for autocorrelated residuals, e.g. from overlapping
+    returns. With no lags it is the usual OLS standard error. It is null for
+    windows of two observations.
+
+    Args:
+    ----
+        y: IntoExpr - The dependent series.
+        x: IntoExpr - The independent series.
+        window: int - The number of observations in each regression, at least 2.
+        lags: int - The number of residual autocovariances in the standard error.
+
+    Returns:
+    -------
+        pl.Expr: A struct expression with the fields `alpha`, `beta`,
+            `r_squared` and `beta_se`.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[y, x],
+        kwargs={"window": window, "lags": lags},
+        is_elementwise=False,
+        function_name="rolling_ols",
+    )
+
+
+def engle_granger(y: IntoExpr, x: IntoExpr, max_lag: int) -> pl.Expr:
+    """Run the Engle-Granger cointegration test of two series.
+
+    `y` is regressed on `x` over the whole sample and the Augmented Dickey-Fuller
+    test is run on the residual spread, choosing up to `max_lag` lagged
+    differences by AIC. Because the spread is estimated, the statistic must be
+    compared with Engle-Granger critical values rather than the ADF ones (about
+    -3.90, -3.34 and -3.04 at the 1%, 5% and 10% levels for two series). Rows
+    where either series is null are skipped.
+
+    Args:
+    ----
+        y: IntoExpr - The first price series, e.g. log prices.
+        x: IntoExpr - The second price series.
+        max_lag: int - The maximum number of lagged differences to include.
+
+    Returns:
+    -------
+        pl.Expr: The ADF statistic of the spread as a single Float64 value.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[y, x],
+        kwargs={"max_lag": max_lag},
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="engle_granger",
+    )
+
+
+def mean_reversion_half_life(spread: IntoExpr) -> pl.Expr:
+    """Estimate the half-life of mean reversion of a spread.
+
+    The change in the spread is regressed on its previous value,
+    `Δs_t = a + λ s_{t-1}`. For an Ornstein-Uhlenbeck process `λ` is negative and
+    a deviation from the mean halves in `-ln(2) / λ` observations, which is a
+    natural holding horizon for a pairs trade. The result is null when the spread
+    is not mean reverting. Null values are skipped.
+
+    Args:
+    ----
+        spread: IntoExpr - The spread series, e.g. the residual of `rolling_ols`.
+
+    Returns:
+    -------
+        pl.Expr: The half-life in observations as a single Float64 value.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[spread],
+        is_elementwise=False,
+        returns_scalar=True,
+        function_name="mean_reversion_half_life",
+    )
+
+
+def rolling_zscore(expr: IntoExpr, window: int) -> pl.Expr:
+    """Calculate the rolling z-score of a series.
+
+    Each value is standardized by the mean and sample standard deviation of the
+    window ending at it, `(x - rolling_mean) / rolling_std`. Applied to a pairs
+    spread it gives entry and exit signals when it crosses a band. The first
+    `window - 1` values, windows containing a null and windows with no variance
+    are null.
+
+    Args:
+    ----
+        expr: IntoExpr - The series to standardize, e.g. a spread.
+        window: int - The number of observations in each window, at least 2.
+
+    Returns:
+    -------
+        pl.Expr: The rolling z-score.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"window": window},
+        is_elementwise=False,
+        function_name="rolling_zscore",
+    )
+
+
+def rolling_skew(expr: IntoExpr, window: int) -> pl.Expr:
+    """Calculate the rolling bias-corrected sample skewness of a series.
+
+    The skewness is the adjusted Fisher-Pearson coefficient `G1`, as in pandas.
+    The first `window - 1` values, windows containing a null and windows with no
+    variance are null.
+
+    Args:
+    ----
+        expr: IntoExpr - The series, e.g. returns.
+        window: int - The number of observations in each window, at least 3.
+
+    Returns:
+    -------
+        pl.Expr: The rolling skewness.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"window": window},
+        is_elementwise=False,
+        function_name="rolling_skew",
+    )
+
+
+def rolling_kurtosis(expr: IntoExpr, window: int) -> pl.Expr:
+    """Calculate the rolling bias-corrected sample excess kurtosis of a series.
+
+    The kurtosis is the excess kurtosis `G2`, as in pandas, so a normal
+    distribution has a kurtosis of 0. The first `window - 1` values, windows
+    containing a null and windows with no variance are null.
+
+    Args:
+    ----
+        expr: IntoExpr - The series, e.g. returns.
+        window: int - The number of observations in each window, at least 4.
+
+    Returns:
+    -------
+        pl.Expr: The rolling excess kurtosis.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        kwargs={"window": window},
+        is_elementwise=False,
+        function_name="rolling_kurtosis",
+    )
+
+
+def norm_ppf(expr: IntoExpr) -> pl.Expr:
+    """Calculate the standard normal quantile of each probability.
+
+    This is the inverse of the standard normal cumulative distribution function,
+    e.g. `norm_ppf(0.975)` is about 1.96, for building confidence intervals or
+    significance thresholds. Probabilities outside `(0, 1)` are null.
+
+    Args:
+    ----
+        expr: IntoExpr - The probabilities.
+
+    Returns:
+    -------
+        pl.Expr: The standard normal quantiles.
+
+    """
+    return register_plugin_function(
+        plugin_path=LIB,
+        args=[expr],
+        is_elementwise=True,
+        function_name="norm_ppf",
+    )
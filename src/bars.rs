@@ -1,5 +1,6 @@
 #![allow(clippy::unused_unit)]
 use std::cmp::PartialOrd;
+use std::collections::BTreeMap;
 
 use num::traits::{Signed, Zero};
 use polars::lazy::prelude::*;
@@ -7,11 +8,52 @@ use polars::prelude::*;
 use pyo3_polars::derive::polars_expr;
 use serde::Deserialize;
 
-fn compute_bar_groups<T>(
+/// The state of the bar grouping after the last value, so that a later chunk of
+/// values can continue the same bars.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BarAccumulator<T> {
+    current_sum: T,
+    group_id: i32,
+    transaction_id: i32,
+}
+
+impl<T: Zero> Default for BarAccumulator<T> {
+    fn default() -> Self {
+        Self {
+            current_sum: T::zero(),
+            group_id: 0,
+            transaction_id: 0,
+        }
+    }
+}
+
+impl<T> BarAccumulator<T> {
+    fn map_sum<U>(self, f: impl FnOnce(T) -> U) -> BarAccumulator<U> {
+        BarAccumulator {
+            current_sum: f(self.current_sum),
+            group_id: self.group_id,
+            transaction_id: self.transaction_id,
+        }
+    }
+}
+
+/// Compute the bar groups of `values` continuing from the bars in `state`, and
+/// return the state after the last value along with the groups.
+///
+/// Without splits, a value that would take an open bar past `bar_size` is kept
+/// in that bar, unless `close_before_overflow` is true, in which case the bar is
+/// closed and the value starts the next bar.
+///
+/// With splits, only positive values are split. Zero and negative values, e.g.
+/// sell volume in a net signed flow, go whole to the open bar and reduce its
+/// sum, as they do without splits.
+fn compute_bar_groups_from<T>(
     values: impl Iterator<Item = T>,
     bar_size: T,
     allow_splits: bool,
-) -> (Vec<i32>, Vec<i32>, Vec<T>)
+    close_before_overflow: bool,
+    state: BarAccumulator<T>,
+) -> (Vec<i32>, Vec<i32>, Vec<T>, BarAccumulator<T>)
 where
     T: Signed
         + Zero
@@ -24,15 +66,23 @@ where
     let mut transaction_ids: Vec<i32> = Vec::new();
     let mut group_ids: Vec<i32> = Vec::new();
     let mut amounts: Vec<T> = Vec::new();
-    let mut current_sum = T::zero();
-    let mut group_id = 0;
-    let mut transaction_id = 0;
+    let BarAccumulator {
+        mut current_sum,
+        mut group_id,
+        mut transaction_id,
+    } = state;
 
     for val in values {
         if allow_splits {
             // Allow splitting a single value across multiple bars
             let mut remaining_val = val;
 
+            if val <= T::zero() {
+                transaction_ids.push(transaction_id);
+                group_ids.push(group_id);
+                amounts.push(val);
+                current_sum += val;
+            }
             while remaining_val > T::zero() {
                 if current_sum + remaining_val >= bar_size {
                     let amount_to_add = bar_size - current_sum;
@@ -52,6 +102,10 @@ where
             }
         } else {
             // Don't allow splitting - entire value goes to one bar, allow overflow
+            if close_before_overflow && current_sum > T::zero() && current_sum + val > bar_size {
+                group_id += 1;
+                current_sum = T::zero();
+            }
             transaction_ids.push(transaction_id);
             group_ids.push(group_id);
             amounts.push(val);
@@ -67,21 +121,255 @@ where
         transaction_id += 1;
     }
 
-    (transaction_ids, group_ids, amounts)
+    let state = BarAccumulator {
+        current_sum,
+        group_id,
+        transaction_id,
+    };
+    (transaction_ids, group_ids, amounts, state)
+}
+
+/// Get the transaction id of the last value in each bar from the transaction and
+/// group id of each part of a value.
+///
+/// A value split across bars ends each bar it closes, and the last bar may still
+/// be open.
+fn bar_end_transactions(transaction_ids: &[i32], group_ids: &[i32]) -> Vec<i32> {
+    let mut boundaries: Vec<i32> = group_ids
+        .windows(2)
+        .zip(transaction_ids)
+        .filter(|(groups, _)| groups[0] != groups[1])
+        .map(|(_, &transaction_id)| transaction_id)
+        .collect();
+    boundaries.extend(transaction_ids.last());
+    boundaries
+}
+
+/// Diagnostics of the bars produced by a bar grouping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BarSummary {
+    num_bars: usize,
+    mean_bar_size: f64,
+    max_bar_size: f64,
+}
+
+/// Summarise the bars from the group id and amount of each part of a value.
+///
+/// Returns `None` if there are no bars.
+fn summarize_bars(group_ids: &[i32], amounts: &[f64]) -> Option<BarSummary> {
+    let mut bar_sizes: BTreeMap<i32, f64> = BTreeMap::new();
+    for (group_id, amount) in group_ids.iter().zip(amounts) {
+        *bar_sizes.entry(*group_id).or_default() += amount;
+    }
+    let num_bars = bar_sizes.len();
+    let max_bar_size = bar_sizes.values().copied().reduce(f64::max)?;
+    Some(BarSummary {
+        num_bars,
+        mean_bar_size: bar_sizes.values().sum::<f64>() / num_bars as f64,
+        max_bar_size,
+    })
+}
+
+/// Assign each value a bar id for every bar size in a single pass, without
+/// splitting values across bars.
+///
+/// The bar sizes must be ascending. A bar closes once its sum meets the bar size,
+/// but only where the bar of the next smaller size also closes, so that every bar
+/// is made of whole bars of each smaller size.
+fn compute_nested_bar_groups(
+    values: impl Iterator<Item = f64>,
+    bar_sizes: &[f64],
+) -> Vec<Vec<i32>> {
+    let mut group_ids: Vec<Vec<i32>> = vec![Vec::new(); bar_sizes.len()];
+    let mut current_ids = vec![0; bar_sizes.len()];
+    let mut current_sums = vec![0.0; bar_sizes.len()];
+
+    for val in values {
+        // The smallest bar size has no finer bar to wait for.
+        let mut finer_closed = true;
+        for (level, &bar_size) in bar_sizes.iter().enumerate() {
+            group_ids[level].push(current_ids[level]);
+            current_sums[level] += val;
+            finer_closed = finer_closed && current_sums[level] >= bar_size;
+            if finer_closed {
+                current_ids[level] += 1;
+                current_sums[level] = 0.0;
+            }
+        }
+    }
+
+    group_ids
 }
 
-fn create_row_groups<T>(
+/// Assign each trade a tick imbalance bar id from its tick rule sign.
+///
+/// A bar closes once the absolute sum of its signs reaches the expected
+/// imbalance `E[T] * |E[b]|`, where `E[T]` is the expected number of ticks in a
+/// bar and `E[b]` the expected sign of a tick. After each bar, both expectations
+/// are updated by an EWMA with weight `alpha` on the bar's length and mean sign.
+/// The first bar expects `expected_theta` ticks that all share a sign.
+///
+/// Returns the transaction id and group id of each trade. Trades are never
+/// split, so the transaction ids are the positions of the trades.
+///
+/// Reference: Advances in Financial Machine Learning, Chapter 2.3.2.1
+fn compute_tick_imbalance_bars(
+    signs: impl Iterator<Item = i8>,
+    expected_theta: f64,
+    alpha: f64,
+) -> (Vec<i32>, Vec<i32>) {
+    let mut transaction_ids = Vec::new();
+    let mut group_ids = Vec::new();
+    let (mut expected_ticks, mut expected_sign) = (expected_theta, 1.0);
+    let (mut group_id, mut ticks, mut theta) = (0, 0, 0.0);
+
+    for (transaction_id, sign) in signs.enumerate() {
+        transaction_ids.push(transaction_id as i32);
+        group_ids.push(group_id);
+        ticks += 1;
+        theta += f64::from(sign);
+        if theta.abs() >= expected_ticks * expected_sign.abs() {
+            expected_ticks += alpha * (ticks as f64 - expected_ticks);
+            expected_sign += alpha * (theta / ticks as f64 - expected_sign);
+            group_id += 1;
+            ticks = 0;
+            theta = 0.0;
+        }
+    }
+
+    (transaction_ids, group_ids)
+}
+
+/// Bucket the volume traded within a bar into `n_bins` equal width price bins
+/// spanning the bar's low to high price.
+///
+/// Returns the midpoint price and total volume of each bin. A bar trading at a
+/// single price has a single bin at that price.
+fn volume_profile_bins(prices: &[f64], volumes: &[f64], n_bins: usize) -> Vec<(f64, f64)> {
+    let low = prices.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if high <= low {
+        return vec![(low, volumes.iter().sum())];
+    }
+    let width = (high - low) / n_bins as f64;
+    let mut bins: Vec<(f64, f64)> = (0..n_bins)
+        .map(|i| (low + (i as f64 + 0.5) * width, 0.0))
+        .collect();
+    for (price, volume) in prices.iter().zip(volumes) {
+        // The high price falls in the last bin rather than one past it.
+        let bin = (((price - low) / width) as usize).min(n_bins - 1);
+        bins[bin].1 += volume;
+    }
+    bins
+}
+
+/// Calculate the time weighted average price of a bar's trades.
+///
+/// Each price is weighted by the time until the next trade in the bar, so the
+/// last trade carries no weight. A bar whose trades all share a timestamp,
+/// including a single trade bar, is the simple mean of its prices.
+fn time_weighted_average(prices: &[f64], timestamps: &[i64]) -> f64 {
+    let (weighted, duration) = prices.windows(2).zip(timestamps.windows(2)).fold(
+        (0.0, 0.0),
+        |(weighted, duration), (p, t)| {
+            let dt = (t[1] - t[0]) as f64;
+            (weighted + p[0] * dt, duration + dt)
+        },
+    );
+    if duration > 0.0 {
+        weighted / duration
+    } else {
+        prices.iter().sum::<f64>() / prices.len() as f64
+    }
+}
+
+/// How the volume of each trade in a bar is further weighted by its position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VwapWeighting {
+    /// Volume only, the plain VWAP.
+    Volume,
+    /// Exponentially decaying with the number of later trades, with a span.
+    Exp,
+    /// Linearly increasing in trade order, i.e. a triangular kernel.
+    Time,
+}
+
+impl VwapWeighting {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "volume" => Some(Self::Volume),
+            "exp" => Some(Self::Exp),
+            "time" => Some(Self::Time),
+            _ => None,
+        }
+    }
+}
+
+/// Calculate the volume weighted average price of a bar's trades, with the
+/// volumes scaled by `weighting` so that later trades can count for more.
+///
+/// With exponential weighting, each trade is discounted by `1 - alpha` per later
+/// trade in the bar, with `alpha = 2 / (span + 1)`. With time weighting, the
+/// `i`th trade is scaled by `i + 1`. Returns `None` if the weights sum to zero.
+fn weighted_vwap(
+    prices: &[f64],
+    volumes: &[f64],
+    weighting: VwapWeighting,
+    span: f64,
+) -> Option<f64> {
+    let n = prices.len();
+    let decay = 1.0 - 2.0 / (span + 1.0);
+    let (weighted, total) = prices.iter().zip(volumes).enumerate().fold(
+        (0.0, 0.0),
+        |(weighted, total), (i, (price, volume))| {
+            let scale = match weighting {
+                VwapWeighting::Volume => 1.0,
+                VwapWeighting::Exp => decay.powi((n - 1 - i) as i32),
+                VwapWeighting::Time => (i + 1) as f64,
+            };
+            let weight = volume * scale;
+            (weighted + price * weight, total + weight)
+        },
+    );
+    (total != 0.0).then(|| weighted / total)
+}
+
+/// Group the rows of each bar by bar id, in ascending id order.
+fn rows_by_bar<T: Copy>(
+    bar_ids: &[Option<i64>],
+    prices: &[Option<f64>],
+    values: &[Option<T>],
+) -> BTreeMap<i64, (Vec<f64>, Vec<T>)> {
+    let mut bars: BTreeMap<i64, (Vec<f64>, Vec<T>)> = BTreeMap::new();
+    for ((bar_id, price), value) in bar_ids.iter().zip(prices).zip(values) {
+        if let (Some(bar_id), Some(price), Some(value)) = (bar_id, price, value) {
+            let bar = bars.entry(*bar_id).or_default();
+            bar.0.push(*price);
+            bar.1.push(*value);
+        }
+    }
+    bars
+}
+
+fn create_row_groups_from<T>(
     ca: &ChunkedArray<T>,
     bar_size: T::Native,
     allow_splits: bool,
+    close_before_overflow: bool,
+    state: BarAccumulator<T::Native>,
 ) -> PolarsResult<Series>
 where
     T: PolarsNumericType,
     T::Native: Signed + Zero + PartialOrd,
     ChunkedArray<T>: IntoSeries,
 {
-    let (transaction_ids, group_ids, amounts) =
-        compute_bar_groups(ca.into_no_null_iter(), bar_size, allow_splits);
+    let (transaction_ids, group_ids, amounts, _) = compute_bar_groups_from(
+        ca.into_no_null_iter(),
+        bar_size,
+        allow_splits,
+        close_before_overflow,
+        state,
+    );
 
     let transaction_id_ca = Int32Chunked::new("transaction_id".into(), &transaction_ids);
     let id_ca = Int32Chunked::new("bar_group__id".into(), &group_ids);
@@ -115,6 +403,8 @@ struct BarGroupKwargs {
     bar_size: f64,
     #[serde(default = "default_allow_splits")]
     allow_splits: bool,
+    #[serde(default)]
+    close_before_overflow: bool,
 }
 
 fn default_allow_splits() -> bool {
@@ -131,37 +421,399 @@ fn bar_group_struct(input_fields: &[Field]) -> PolarsResult<Field> {
     ))
 }
 
+/// Read the bar grouping state from the first row of a struct series with
+/// `current_sum`, `group_id` and `transaction_id` fields.
+fn bar_accumulator_from_series(s: &Series) -> PolarsResult<BarAccumulator<f64>> {
+    let state = s.struct_()?;
+    let current_sum = state
+        .field_by_name("current_sum")?
+        .cast(&DataType::Float64)?
+        .f64()?
+        .get(0);
+    let group_id = state
+        .field_by_name("group_id")?
+        .cast(&DataType::Int32)?
+        .i32()?
+        .get(0);
+    let transaction_id = state
+        .field_by_name("transaction_id")?
+        .cast(&DataType::Int32)?
+        .i32()?
+        .get(0);
+    match (current_sum, group_id, transaction_id) {
+        (Some(current_sum), Some(group_id), Some(transaction_id)) => Ok(BarAccumulator {
+            current_sum,
+            group_id,
+            transaction_id,
+        }),
+        _ => Err(PolarsError::ComputeError(
+            "Bar group state must not be null".into(),
+        )),
+    }
+}
+
+/// Read the optional prior bar grouping state input, defaulting to a fresh
+/// state.
+fn prior_bar_accumulator(inputs: &[Series]) -> PolarsResult<BarAccumulator<f64>> {
+    inputs.get(1).map_or_else(
+        || Ok(BarAccumulator::default()),
+        bar_accumulator_from_series,
+    )
+}
+
 #[polars_expr(output_type_func=bar_group_struct)]
 fn bar_groups(inputs: &[Series], kwargs: BarGroupKwargs) -> PolarsResult<Series> {
+    let state = prior_bar_accumulator(inputs)?;
     match inputs[0].dtype() {
-        DataType::Float64 => create_row_groups(
+        DataType::Float64 => create_row_groups_from(
             inputs[0].f64().unwrap(),
             kwargs.bar_size,
             kwargs.allow_splits,
+            kwargs.close_before_overflow,
+            state,
         ),
-        DataType::Float32 => create_row_groups(
+        DataType::Float32 => create_row_groups_from(
             inputs[0].f32().unwrap(),
             kwargs.bar_size as f32,
             kwargs.allow_splits,
+            kwargs.close_before_overflow,
+            state.map_sum(|sum| sum as f32),
         ),
-        DataType::Int64 => create_row_groups(
+        DataType::Int64 => create_row_groups_from(
             inputs[0].i64().unwrap(),
             kwargs.bar_size as i64,
             kwargs.allow_splits,
+            kwargs.close_before_overflow,
+            state.map_sum(|sum| sum as i64),
         ),
-        DataType::Int32 => create_row_groups(
+        DataType::Int32 => create_row_groups_from(
             inputs[0].i32().unwrap(),
             kwargs.bar_size as i32,
             kwargs.allow_splits,
+            kwargs.close_before_overflow,
+            state.map_sum(|sum| sum as i32),
         ),
         _ => Err(PolarsError::ComputeError("Unsupported type".into())),
     }
 }
 
+fn bar_group_state_struct(_input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        "bar_group_state".into(),
+        DataType::Struct(vec![
+            Field::new("current_sum".into(), DataType::Float64),
+            Field::new("group_id".into(), DataType::Int32),
+            Field::new("transaction_id".into(), DataType::Int32),
+        ]),
+    ))
+}
+
+#[polars_expr(output_type_func=bar_group_state_struct)]
+fn bar_group_state(inputs: &[Series], kwargs: BarGroupKwargs) -> PolarsResult<Series> {
+    let state = prior_bar_accumulator(inputs)?;
+    let values = inputs[0].cast(&DataType::Float64)?;
+    let (_, _, _, state) = compute_bar_groups_from(
+        values.f64()?.into_no_null_iter(),
+        kwargs.bar_size,
+        kwargs.allow_splits,
+        kwargs.close_before_overflow,
+        state,
+    );
+    let fields = [
+        Float64Chunked::from_vec("current_sum".into(), vec![state.current_sum]).into_series(),
+        Int32Chunked::from_vec("group_id".into(), vec![state.group_id]).into_series(),
+        Int32Chunked::from_vec("transaction_id".into(), vec![state.transaction_id]).into_series(),
+    ];
+    Ok(StructChunked::from_series("bar_group_state".into(), 1, fields.iter())?.into_series())
+}
+
+/// Multiply prices by volumes to get the dollar volume of each trade.
+fn dollar_volume(prices: &Series, volumes: &Series) -> PolarsResult<Float64Chunked> {
+    let product = (&prices.cast(&DataType::Float64)? * &volumes.cast(&DataType::Float64)?)?;
+    Ok(product.f64()?.clone())
+}
+
+fn dollar_bar_group_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::List(Box::new(DataType::Struct(vec![
+            Field::new("bar_group__id".into(), DataType::Int32),
+            Field::new("bar_group__amount".into(), DataType::Float64),
+        ]))),
+    ))
+}
+
+#[polars_expr(output_type_func=dollar_bar_group_struct)]
+fn dollar_bars(inputs: &[Series], kwargs: BarGroupKwargs) -> PolarsResult<Series> {
+    let dollar_volume = dollar_volume(&inputs[0], &inputs[1])?;
+    create_row_groups_from(
+        &dollar_volume,
+        kwargs.bar_size,
+        kwargs.allow_splits,
+        kwargs.close_before_overflow,
+        BarAccumulator::default(),
+    )
+}
+
+fn bar_summary_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(vec![
+            Field::new("num_bars".into(), DataType::Int64),
+            Field::new("mean_bar_size".into(), DataType::Float64),
+            Field::new("max_bar_size".into(), DataType::Float64),
+        ]),
+    ))
+}
+
+#[polars_expr(output_type_func=bar_summary_struct)]
+fn bar_summary(inputs: &[Series], kwargs: BarGroupKwargs) -> PolarsResult<Series> {
+    let values = inputs[0].cast(&DataType::Float64)?;
+    let (_, group_ids, amounts, _) = compute_bar_groups_from(
+        values.f64()?.into_no_null_iter(),
+        kwargs.bar_size,
+        kwargs.allow_splits,
+        kwargs.close_before_overflow,
+        BarAccumulator::default(),
+    );
+    let summary = summarize_bars(&group_ids, &amounts);
+    let fields = [
+        Int64Chunked::new(
+            "num_bars".into(),
+            &[Some(summary.map_or(0, |s| s.num_bars as i64))],
+        )
+        .into_series(),
+        Float64Chunked::new("mean_bar_size".into(), &[summary.map(|s| s.mean_bar_size)])
+            .into_series(),
+        Float64Chunked::new("max_bar_size".into(), &[summary.map(|s| s.max_bar_size)])
+            .into_series(),
+    ];
+    Ok(StructChunked::from_series(inputs[0].name().clone(), 1, fields.iter())?.into_series())
+}
+
+fn bar_boundaries_list(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::List(Box::new(DataType::Int32)),
+    ))
+}
+
+#[polars_expr(output_type_func=bar_boundaries_list)]
+fn bar_boundaries(inputs: &[Series], kwargs: BarGroupKwargs) -> PolarsResult<Series> {
+    let values = inputs[0].cast(&DataType::Float64)?;
+    let (transaction_ids, group_ids, _, _) = compute_bar_groups_from(
+        values.f64()?.into_no_null_iter(),
+        kwargs.bar_size,
+        kwargs.allow_splits,
+        kwargs.close_before_overflow,
+        BarAccumulator::default(),
+    );
+    let boundaries = Int32Chunked::from_vec(
+        inputs[0].name().clone(),
+        bar_end_transactions(&transaction_ids, &group_ids),
+    );
+    let mut boundaries: ListChunked = std::iter::once(Some(boundaries.into_series())).collect();
+    boundaries.rename(inputs[0].name().clone());
+    Ok(boundaries.into_series())
+}
+
+#[derive(Deserialize)]
+struct MultiBarGroupKwargs {
+    bar_sizes: Vec<f64>,
+}
+
+fn multi_bar_group_struct(
+    input_fields: &[Field],
+    kwargs: MultiBarGroupKwargs,
+) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(
+            (0..kwargs.bar_sizes.len())
+                .map(|i| Field::new(format!("bar_group__id_{i}").into(), DataType::Int32))
+                .collect(),
+        ),
+    ))
+}
+
+#[polars_expr(output_type_func_with_kwargs=multi_bar_group_struct)]
+fn multi_bar_groups(inputs: &[Series], kwargs: MultiBarGroupKwargs) -> PolarsResult<Series> {
+    if kwargs.bar_sizes.is_empty() || kwargs.bar_sizes.windows(2).any(|w| w[1] < w[0]) {
+        return Err(PolarsError::ComputeError(
+            "bar_sizes must be non-empty and ascending".into(),
+        ));
+    }
+    let values = inputs[0].cast(&DataType::Float64)?;
+    let group_ids = compute_nested_bar_groups(values.f64()?.into_no_null_iter(), &kwargs.bar_sizes);
+    let fields: Vec<Series> = group_ids
+        .iter()
+        .enumerate()
+        .map(|(i, ids)| Int32Chunked::new(format!("bar_group__id_{i}").into(), ids).into_series())
+        .collect();
+    Ok(
+        StructChunked::from_series(inputs[0].name().clone(), values.len(), fields.iter())?
+            .into_series(),
+    )
+}
+
+#[derive(Deserialize)]
+struct TickImbalanceBarKwargs {
+    expected_theta: f64,
+    alpha: f64,
+}
+
+#[polars_expr(output_type=Int32)]
+fn tick_imbalance_bars(inputs: &[Series], kwargs: TickImbalanceBarKwargs) -> PolarsResult<Series> {
+    if kwargs.expected_theta <= 0.0 || !(kwargs.alpha > 0.0 && kwargs.alpha <= 1.0) {
+        return Err(PolarsError::ComputeError(
+            "expected_theta must be positive and alpha in (0, 1]".into(),
+        ));
+    }
+    let signs = inputs[0].cast(&DataType::Int8)?;
+    let (_, group_ids) = compute_tick_imbalance_bars(
+        signs.i8()?.into_no_null_iter(),
+        kwargs.expected_theta,
+        kwargs.alpha,
+    );
+    Ok(Int32Chunked::from_vec(inputs[0].name().clone(), group_ids).into_series())
+}
+
+#[derive(Deserialize)]
+struct VolumeProfileKwargs {
+    n_bins: usize,
+}
+
+fn volume_profile_struct(_input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        "volume_profile".into(),
+        DataType::List(Box::new(DataType::Struct(vec![
+            Field::new("price_level".into(), DataType::Float64),
+            Field::new("volume".into(), DataType::Float64),
+        ]))),
+    ))
+}
+
+#[polars_expr(output_type_func=volume_profile_struct)]
+fn volume_profile(inputs: &[Series], kwargs: VolumeProfileKwargs) -> PolarsResult<Series> {
+    if kwargs.n_bins == 0 {
+        return Err(PolarsError::ComputeError(
+            "n_bins must be at least 1".into(),
+        ));
+    }
+    let prices: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let volumes: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let bar_ids: Vec<Option<i64>> = inputs[2].cast(&DataType::Int64)?.i64()?.iter().collect();
+
+    let profiles = rows_by_bar(&bar_ids, &prices, &volumes)
+        .values()
+        .map(|(prices, volumes)| {
+            let bins = volume_profile_bins(prices, volumes, kwargs.n_bins);
+            let levels: Vec<f64> = bins.iter().map(|b| b.0).collect();
+            let volumes: Vec<f64> = bins.iter().map(|b| b.1).collect();
+            let fields = [
+                Float64Chunked::from_vec("price_level".into(), levels).into_series(),
+                Float64Chunked::from_vec("volume".into(), volumes).into_series(),
+            ];
+            Ok(Some(
+                StructChunked::from_series("".into(), bins.len(), fields.iter())?.into_series(),
+            ))
+        })
+        .collect::<PolarsResult<Vec<Option<Series>>>>()?;
+    let mut profiles: ListChunked = profiles.into_iter().collect();
+    profiles.rename("volume_profile".into());
+    Ok(profiles.into_series())
+}
+
+#[derive(Deserialize)]
+struct BarVwapKwargs {
+    weighting: String,
+    span: Option<f64>,
+}
+
+#[polars_expr(output_type=Float64)]
+fn bar_vwap(inputs: &[Series], kwargs: BarVwapKwargs) -> PolarsResult<Series> {
+    let weighting = VwapWeighting::from_name(&kwargs.weighting).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown weighting: {}", kwargs.weighting).into())
+    })?;
+    let span = match (weighting, kwargs.span) {
+        (VwapWeighting::Exp, Some(span)) if span >= 1.0 => span,
+        (VwapWeighting::Exp, _) => {
+            return Err(PolarsError::ComputeError(
+                "exp weighting needs a span of at least 1".into(),
+            ))
+        },
+        _ => 1.0,
+    };
+    let prices: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let volumes: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let bar_ids: Vec<Option<i64>> = inputs[2].cast(&DataType::Int64)?.i64()?.iter().collect();
+
+    let vwaps: Vec<Option<f64>> = rows_by_bar(&bar_ids, &prices, &volumes)
+        .values()
+        .map(|(prices, volumes)| weighted_vwap(prices, volumes, weighting, span))
+        .collect();
+    Ok(Float64Chunked::new("bar_vwap".into(), &vwaps).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn bar_twap(inputs: &[Series]) -> PolarsResult<Series> {
+    let prices: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let timestamps: Vec<Option<i64>> = inputs[1]
+        .to_physical_repr()
+        .cast(&DataType::Int64)?
+        .i64()?
+        .iter()
+        .collect();
+    let bar_ids: Vec<Option<i64>> = inputs[2].cast(&DataType::Int64)?.i64()?.iter().collect();
+
+    let twaps: Vec<f64> = rows_by_bar(&bar_ids, &prices, &timestamps)
+        .values()
+        .map(|(prices, timestamps)| time_weighted_average(prices, timestamps))
+        .collect();
+    Ok(Float64Chunked::from_vec("bar_twap".into(), twaps).into_series())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn compute_bar_groups<T>(
+        values: impl Iterator<Item = T>,
+        bar_size: T,
+        allow_splits: bool,
+    ) -> (Vec<i32>, Vec<i32>, Vec<T>)
+    where
+        T: Signed
+            + Zero
+            + PartialOrd
+            + Copy
+            + std::ops::Add<Output = T>
+            + std::ops::Sub<Output = T>
+            + std::ops::AddAssign,
+    {
+        let (transaction_ids, group_ids, amounts, _) = compute_bar_groups_from(
+            values,
+            bar_size,
+            allow_splits,
+            false,
+            BarAccumulator::default(),
+        );
+        (transaction_ids, group_ids, amounts)
+    }
+
+    fn create_row_groups<T>(
+        ca: &ChunkedArray<T>,
+        bar_size: T::Native,
+        allow_splits: bool,
+    ) -> PolarsResult<Series>
+    where
+        T: PolarsNumericType,
+        T::Native: Signed + Zero + PartialOrd,
+        ChunkedArray<T>: IntoSeries,
+    {
+        create_row_groups_from(ca, bar_size, allow_splits, false, BarAccumulator::default())
+    }
+
     #[test]
     fn test_compute_bar_groups_simple() {
         let values = vec![1, 2, 3, 4, 5];
@@ -197,6 +849,39 @@ mod tests {
         assert_eq!(result_amounts, expected_amounts);
     }
 
+    #[test]
+    fn test_compute_bar_groups_splits_signed_values() {
+        // The -3 pulls the open bar back below zero, so the following 9 needs 5 to
+        // close it.
+        let values = vec![2, -3, 0, 9];
+
+        let (transaction_ids, group_ids, amounts) = compute_bar_groups(values.into_iter(), 4, true);
+
+        assert_eq!(transaction_ids, vec![0, 1, 2, 3, 3]);
+        assert_eq!(group_ids, vec![0, 0, 0, 0, 1]);
+        assert_eq!(amounts, vec![2, -3, 0, 5, 4]);
+    }
+
+    #[test]
+    fn test_bar_end_transactions() {
+        let values = vec![1, 2, 3, 4, 5];
+
+        let (transaction_ids, group_ids, _) =
+            compute_bar_groups(values.clone().into_iter(), 4, false);
+        assert_eq!(
+            bar_end_transactions(&transaction_ids, &group_ids),
+            vec![2, 3, 4]
+        );
+
+        // Values 3 and 4 are split, so they end one bar each and start the next.
+        let (transaction_ids, group_ids, _) = compute_bar_groups(values.into_iter(), 4, true);
+        assert_eq!(
+            bar_end_transactions(&transaction_ids, &group_ids),
+            vec![2, 3, 4, 4]
+        );
+        assert!(bar_end_transactions(&[], &[]).is_empty());
+    }
+
     #[test]
     fn test_compute_bar_groups_no_splits() {
         let values = vec![1, 2, 3, 4, 5];
@@ -310,6 +995,97 @@ mod tests {
         assert_eq!(overflow_amounts, vec![3, 3, 3, 3]);
     }
 
+    #[test]
+    fn test_compute_bar_groups_from_continues_chunks() {
+        for allow_splits in [true, false] {
+            let (all_transaction_ids, all_group_ids, all_amounts) =
+                compute_bar_groups(vec![1, 2, 3, 4, 5].into_iter(), 4, allow_splits);
+
+            let (mut transaction_ids, mut group_ids, mut amounts, state) = compute_bar_groups_from(
+                vec![1, 2, 3].into_iter(),
+                4,
+                allow_splits,
+                false,
+                BarAccumulator::default(),
+            );
+            let (next_transaction_ids, next_group_ids, next_amounts, _) =
+                compute_bar_groups_from(vec![4, 5].into_iter(), 4, allow_splits, false, state);
+            transaction_ids.extend(next_transaction_ids);
+            group_ids.extend(next_group_ids);
+            amounts.extend(next_amounts);
+
+            assert_eq!(transaction_ids, all_transaction_ids);
+            assert_eq!(group_ids, all_group_ids);
+            assert_eq!(amounts, all_amounts);
+        }
+    }
+
+    #[test]
+    fn test_close_before_overflow() {
+        let values = vec![3, 3, 3, 3];
+        let bar_size = 4;
+
+        // Keeping the overflowing value: bars of 3 + 3 = 6
+        let (_, overflow_group_ids, _, _) = compute_bar_groups_from(
+            values.clone().into_iter(),
+            bar_size,
+            false,
+            false,
+            BarAccumulator::default(),
+        );
+        assert_eq!(overflow_group_ids, vec![0, 0, 1, 1]);
+
+        // Closing before the overflowing value: every 3 + 3 would pass 4, so each
+        // value starts a new bar
+        let (transaction_ids, close_group_ids, amounts, _) = compute_bar_groups_from(
+            values.into_iter(),
+            bar_size,
+            false,
+            true,
+            BarAccumulator::default(),
+        );
+        assert_eq!(transaction_ids, vec![0, 1, 2, 3]);
+        assert_eq!(close_group_ids, vec![0, 1, 2, 3]);
+        assert_eq!(amounts, vec![3, 3, 3, 3]);
+
+        // A value exactly filling the bar stays in it, and a value larger than the
+        // bar size still forms its own bar
+        let (_, group_ids, _, _) = compute_bar_groups_from(
+            vec![1, 3, 5, 2, 2].into_iter(),
+            bar_size,
+            false,
+            true,
+            BarAccumulator::default(),
+        );
+        assert_eq!(group_ids, vec![0, 0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_summarize_bars() {
+        let values = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+
+        let (_, group_ids, amounts) = compute_bar_groups(values.clone().into_iter(), 4.0, true);
+        assert_eq!(
+            summarize_bars(&group_ids, &amounts),
+            Some(BarSummary {
+                num_bars: 4,
+                mean_bar_size: 3.75,
+                max_bar_size: 4.0
+            })
+        );
+
+        let (_, group_ids, amounts) = compute_bar_groups(values.into_iter(), 4.0, false);
+        assert_eq!(
+            summarize_bars(&group_ids, &amounts),
+            Some(BarSummary {
+                num_bars: 3,
+                mean_bar_size: 5.0,
+                max_bar_size: 6.0
+            })
+        );
+        assert_eq!(summarize_bars(&[], &[]), None);
+    }
+
     #[test]
     fn test_create_row_groups() {
         let values = vec![Some(1.0), Some(2.0), Some(3.0), Some(4.0), Some(5.0)];
@@ -345,4 +1121,148 @@ mod tests {
         let fifth_transaction = list_ca.get_as_series(4).unwrap();
         assert_eq!(fifth_transaction.len(), 2);
     }
+
+    #[test]
+    fn test_dollar_bars_matches_manual_product() {
+        let prices = Series::new("price".into(), &[10.0, 10.5, 11.0, 9.5]);
+        let volumes = Series::new("size".into(), &[100i64, 50, 200, 80]);
+        let manual = Float64Chunked::new("dollar_volume".into(), &[1000.0, 525.0, 2200.0, 760.0]);
+
+        let dollar_volume = dollar_volume(&prices, &volumes).unwrap();
+        let result = create_row_groups(&dollar_volume, 2000.0, true).unwrap();
+
+        let expected = create_row_groups(&manual, 2000.0, true).unwrap();
+        assert!(result.equals(&expected));
+    }
+
+    #[test]
+    fn test_compute_nested_bar_groups() {
+        let values = vec![1.0, 2.0, 3.0, 4.0, 5.0, 1.0, 1.0];
+
+        let group_ids = compute_nested_bar_groups(values.into_iter(), &[4.0, 8.0]);
+
+        // Bar size 4: 1 + 2 + 3 = 6, then 4, then 5 each close a bar.
+        assert_eq!(group_ids[0], vec![0, 0, 0, 1, 2, 3, 3]);
+        // Bar size 8: fine bar 0 closes at a sum of 6, short of 8, so the bar
+        // closes with fine bar 1 at a sum of 10.
+        assert_eq!(group_ids[1], vec![0, 0, 0, 0, 1, 1, 1]);
+
+        // Each fine bar lies within a single coarse bar.
+        let mut coarse_of_fine = std::collections::HashMap::new();
+        for (fine, coarse) in group_ids[0].iter().zip(&group_ids[1]) {
+            assert_eq!(*coarse_of_fine.entry(fine).or_insert(coarse), coarse);
+        }
+    }
+
+    #[test]
+    fn test_compute_nested_bar_groups_waits_for_fine_bar() {
+        let values = vec![2.0, 2.0, 2.0, 2.0, 2.0];
+
+        let group_ids = compute_nested_bar_groups(values.into_iter(), &[4.0, 5.0]);
+
+        assert_eq!(group_ids[0], vec![0, 0, 1, 1, 2]);
+        // Bar size 5 is reached at the third value, mid fine bar 1, so the coarse
+        // bar stays open until fine bar 1 closes at the fourth value.
+        assert_eq!(group_ids[1], vec![0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_compute_tick_imbalance_bars_one_sided() {
+        // All buys keep the expectations at 3 ticks of sign 1, so every 3 ticks
+        // close a bar.
+        let (transaction_ids, group_ids) =
+            compute_tick_imbalance_bars([1; 7].into_iter(), 3.0, 0.5);
+
+        assert_eq!(transaction_ids, vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(group_ids, vec![0, 0, 0, 1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn test_compute_tick_imbalance_bars_updates_expectations() {
+        let signs = vec![1, -1, 1, 1, -1, -1, -1, 1, 1];
+
+        let (_, group_ids) = compute_tick_imbalance_bars(signs.into_iter(), 2.0, 0.5);
+
+        // Bar 0 reaches an imbalance of 2 over 4 ticks, so the next bar expects
+        // 3 ticks of mean sign 0.75 and needs an imbalance of 2.25. Bar 1 is 3
+        // sells, which pulls the expected sign to -0.125 and the expected
+        // imbalance to 0.375, so each of the next ticks closes a bar.
+        assert_eq!(group_ids, vec![0, 0, 0, 0, 1, 1, 1, 2, 3]);
+        assert_eq!(
+            compute_tick_imbalance_bars(std::iter::empty(), 2.0, 0.5),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn test_volume_profile_bins_two_price_levels() {
+        let prices = vec![10.0, 12.0, 10.0, 12.0, 12.0];
+        let volumes = vec![100.0, 50.0, 25.0, 50.0, 75.0];
+
+        assert_eq!(
+            volume_profile_bins(&prices, &volumes, 2),
+            vec![(10.5, 125.0), (11.5, 175.0)]
+        );
+        // The middle of four bins is empty.
+        assert_eq!(
+            volume_profile_bins(&prices, &volumes, 4),
+            vec![(10.25, 125.0), (10.75, 0.0), (11.25, 0.0), (11.75, 175.0)]
+        );
+        assert_eq!(
+            volume_profile_bins(&[10.0, 10.0], &[1.0, 2.0], 4),
+            vec![(10.0, 3.0)]
+        );
+    }
+
+    #[test]
+    fn test_rows_by_bar_skips_nulls() {
+        let bars = rows_by_bar(
+            &[Some(1), Some(0), Some(1), None],
+            &[Some(10.0), Some(11.0), None, Some(12.0)],
+            &[Some(1.0), Some(2.0), Some(3.0), Some(4.0)],
+        );
+
+        assert_eq!(bars.keys().collect::<Vec<_>>(), vec![&0, &1]);
+        assert_eq!(bars[&1], (vec![10.0], vec![1.0]));
+    }
+
+    #[test]
+    fn test_time_weighted_average_unequal_gaps() {
+        // 10 is held for 9 seconds and 20 for 1 second before the final trade.
+        let prices = vec![10.0, 20.0, 30.0];
+        let timestamps = vec![0, 9, 10];
+
+        let twap = time_weighted_average(&prices, &timestamps);
+
+        assert!((twap - 11.0).abs() < 1e-12);
+        assert!(twap < prices.iter().sum::<f64>() / 3.0);
+        assert_eq!(time_weighted_average(&[12.5], &[100]), 12.5);
+        assert_eq!(time_weighted_average(&[10.0, 12.0], &[5, 5]), 11.0);
+    }
+
+    #[test]
+    fn test_weighted_vwap_tilts_toward_later_prints() {
+        let prices = vec![10.0, 20.0, 30.0];
+        let volumes = vec![2.0, 1.0, 1.0];
+
+        let plain = weighted_vwap(&prices, &volumes, VwapWeighting::Volume, 1.0).unwrap();
+        // A span of 3 halves the weight per later trade: 0.5, 0.5 and 1.
+        let exp = weighted_vwap(&prices, &volumes, VwapWeighting::Exp, 3.0).unwrap();
+        // Scaled by 1, 2 and 3: 2, 2 and 3.
+        let time = weighted_vwap(&prices, &volumes, VwapWeighting::Time, 1.0).unwrap();
+
+        assert!((plain - 17.5).abs() < 1e-12);
+        assert!((exp - 22.5).abs() < 1e-12);
+        assert!((time - 150.0 / 7.0).abs() < 1e-12);
+        assert!(plain < time && plain < exp);
+        // A span of 1 keeps only the last trade.
+        assert_eq!(
+            weighted_vwap(&prices, &volumes, VwapWeighting::Exp, 1.0),
+            Some(30.0)
+        );
+        assert_eq!(
+            weighted_vwap(&[10.0], &[0.0], VwapWeighting::Volume, 1.0),
+            None
+        );
+    }
 }
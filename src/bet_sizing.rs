@@ -0,0 +1,287 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::math::norm_cdf;
+use crate::metrics::drawdowns;
+use crate::sample_weights::to_spans;
+
+/// Calculate the unsigned bet size from the predicted probability of the
+/// predicted class.
+///
+/// The statistic `z = (p - 1/k) / sqrt(p(1-p))` tests the prediction against a
+/// uniform guess over `k` classes, and the size is `2 * Φ(z) - 1`. Probabilities
+/// outside `[0, 1]` have no size.
+///
+/// Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 142
+fn bet_size_from_probability(prob: f64, num_classes: usize) -> Option<f64> {
+    if !(0.0..=1.0).contains(&prob) {
+        return None;
+    }
+    let z = (prob - 1.0 / num_classes as f64) / (prob * (1.0 - prob)).sqrt();
+    Some(2.0 * norm_cdf(z) - 1.0)
+}
+
+/// Round a bet size to the nearest multiple of `step_size`, capped at a full bet.
+fn discretize(size: f64, step_size: f64) -> f64 {
+    ((size / step_size).round() * step_size).clamp(-1.0, 1.0)
+}
+
+/// Average the sizes of the bets active at each bar.
+///
+/// Each span is the inclusive `(start, end)` bar position of a bet. Bars without
+/// an active bet are `None`, and spans past the last bar are truncated.
+///
+/// Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 144
+fn average_active_sizes(
+    sizes: &[Option<f64>],
+    spans: &[Option<(usize, usize)>],
+    n_bars: usize,
+) -> Vec<Option<f64>> {
+    let mut size_deltas = vec![0.0; n_bars + 1];
+    let mut count_deltas = vec![0i64; n_bars + 1];
+    for (size, span) in sizes.iter().zip(spans) {
+        let (Some(size), Some((start, end))) = (size, span) else {
+            continue;
+        };
+        if *start >= n_bars {
+            continue;
+        }
+        let end = (*end).min(n_bars - 1);
+        size_deltas[*start] += size;
+        size_deltas[end + 1] -= size;
+        count_deltas[*start] += 1;
+        count_deltas[end + 1] -= 1;
+    }
+    let (mut total, mut count) = (0.0, 0);
+    size_deltas
+        .iter()
+        .zip(&count_deltas)
+        .take(n_bars)
+        .map(|(size_delta, count_delta)| {
+            total += size_delta;
+            count += count_delta;
+            (count > 0).then(|| total / count as f64)
+        })
+        .collect()
+}
+
+/// Calculate the exposure multiplier at a running `drawdown`, e.g. -0.1 for 10%
+/// below the peak.
+///
+/// The multiplier falls linearly from 1 with no drawdown to `floor` at a drawdown
+/// of `max_dd`, and stays at `floor` beyond it.
+fn drawdown_multiplier(drawdown: f64, max_dd: f64, floor: f64) -> f64 {
+    let depth = (-drawdown / max_dd).clamp(0.0, 1.0);
+    1.0 - (1.0 - floor) * depth
+}
+
+/// Calculate the Kelly fraction `multiplier * mu / variance`, clamped to
+/// `[min_fraction, max_fraction]`. A non-positive variance has no fraction.
+fn kelly(
+    mu: f64,
+    variance: f64,
+    multiplier: f64,
+    min_fraction: f64,
+    max_fraction: f64,
+) -> Option<f64> {
+    (variance > 0.0).then(|| (multiplier * mu / variance).clamp(min_fraction, max_fraction))
+}
+
+#[derive(Deserialize)]
+struct BetSizeKwargs {
+    num_classes: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn bet_size(inputs: &[Series], kwargs: BetSizeKwargs) -> PolarsResult<Series> {
+    if kwargs.num_classes < 2 {
+        return Err(PolarsError::ComputeError(
+            "num_classes must be at least 2".into(),
+        ));
+    }
+    let probs = inputs[0].cast(&DataType::Float64)?;
+    let sides = inputs[1].cast(&DataType::Float64)?;
+    let sizes: Vec<Option<f64>> = probs
+        .f64()?
+        .iter()
+        .zip(sides.f64()?.iter())
+        .map(|(prob, side)| Some(bet_size_from_probability(prob?, kwargs.num_classes)? * side?))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &sizes).into_series())
+}
+
+#[derive(Deserialize)]
+struct DiscretizeBetSizeKwargs {
+    step_size: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn discretize_bet_size(inputs: &[Series], kwargs: DiscretizeBetSizeKwargs) -> PolarsResult<Series> {
+    if kwargs.step_size <= 0.0 || kwargs.step_size > 1.0 {
+        return Err(PolarsError::ComputeError(
+            "step_size must be in (0, 1]".into(),
+        ));
+    }
+    let sizes = inputs[0].cast(&DataType::Float64)?;
+    let discretized: Vec<Option<f64>> = sizes
+        .f64()?
+        .iter()
+        .map(|size| Some(discretize(size?, kwargs.step_size)))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &discretized).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn avg_active_bets(inputs: &[Series]) -> PolarsResult<Series> {
+    let sizes: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let spans = to_spans(&inputs[1], &inputs[2])?;
+    let averages = average_active_sizes(&sizes, &spans, sizes.len());
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &averages).into_series())
+}
+
+#[derive(Deserialize)]
+struct DrawdownThrottleKwargs {
+    max_dd: f64,
+    floor: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn drawdown_throttle(inputs: &[Series], kwargs: DrawdownThrottleKwargs) -> PolarsResult<Series> {
+    if kwargs.max_dd <= 0.0 || !(0.0..=1.0).contains(&kwargs.floor) {
+        return Err(PolarsError::ComputeError(
+            "max_dd must be positive and floor in [0, 1]".into(),
+        ));
+    }
+    let equity: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let multipliers: Vec<Option<f64>> = drawdowns(&equity)
+        .iter()
+        .map(|drawdown| {
+            Some(drawdown_multiplier(
+                (*drawdown)?,
+                kwargs.max_dd,
+                kwargs.floor,
+            ))
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &multipliers).into_series())
+}
+
+#[derive(Deserialize)]
+struct KellyFractionKwargs {
+    kelly_multiplier: f64,
+    min_fraction: f64,
+    max_fraction: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn kelly_fraction(inputs: &[Series], kwargs: KellyFractionKwargs) -> PolarsResult<Series> {
+    if kwargs.kelly_multiplier <= 0.0 || kwargs.min_fraction > kwargs.max_fraction {
+        return Err(PolarsError::ComputeError(
+            "kelly_multiplier must be positive and min_fraction at most max_fraction".into(),
+        ));
+    }
+    let mus = inputs[0].cast(&DataType::Float64)?;
+    let variances = inputs[1].cast(&DataType::Float64)?;
+    let fractions: Vec<Option<f64>> = mus
+        .f64()?
+        .iter()
+        .zip(variances.f64()?.iter())
+        .map(|(mu, variance)| {
+            kelly(
+                mu?,
+                variance?,
+                kwargs.kelly_multiplier,
+                kwargs.min_fraction,
+                kwargs.max_fraction,
+            )
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &fractions).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bet_size_from_probability() {
+        assert!(bet_size_from_probability(0.5, 2).unwrap().abs() < 1e-12);
+        assert!(bet_size_from_probability(0.999, 2).unwrap() > 0.99);
+        assert_eq!(bet_size_from_probability(1.0, 2), Some(1.0));
+        // A probability of 0.5 is better than a uniform guess over three classes.
+        assert!(bet_size_from_probability(0.5, 3).unwrap() > 0.0);
+        assert_eq!(bet_size_from_probability(1.5, 2), None);
+    }
+
+    #[test]
+    fn test_discretize() {
+        assert_eq!(discretize(0.3, 0.25), 0.25);
+        assert_eq!(discretize(0.4, 0.25), 0.5);
+        assert_eq!(discretize(-0.6, 0.25), -0.5);
+        assert_eq!(discretize(0.9, 0.25), 1.0);
+        // A grid that does not divide 1 is capped at a full bet.
+        assert_eq!(discretize(1.0, 0.4), 1.0);
+    }
+
+    #[test]
+    fn test_average_active_sizes_overlapping_bets() {
+        let sizes = vec![Some(0.5), Some(1.0), None, Some(-1.0), None];
+        let spans = vec![Some((0, 2)), Some((1, 3)), None, Some((3, 9)), None];
+
+        let averages = average_active_sizes(&sizes, &spans, 6);
+
+        assert_eq!(
+            averages,
+            vec![
+                Some(0.5),
+                Some(0.75),
+                Some(0.75),
+                Some(0.0),
+                Some(-1.0),
+                Some(-1.0)
+            ]
+        );
+        assert_eq!(
+            average_active_sizes(&[Some(1.0)], &[Some((2, 3))], 2),
+            vec![None, None]
+        );
+    }
+
+    #[test]
+    fn test_drawdown_multiplier_hits_floor_at_max_dd() {
+        let equity = [
+            Some(100.0),
+            Some(110.0),
+            Some(99.0),
+            Some(88.0),
+            Some(80.0),
+            Some(121.0),
+        ];
+
+        let multipliers: Vec<f64> = drawdowns(&equity)
+            .iter()
+            .map(|drawdown| drawdown_multiplier(drawdown.unwrap(), 0.2, 0.25))
+            .collect();
+
+        // Drawdowns of 0, 0, 10%, 20% and 27% from the peak of 110, then 0 at the
+        // new peak of 121, where the multiplier recovers to 1.
+        let expected = [1.0, 1.0, 0.625, 0.25, 0.25, 1.0];
+        for (m, e) in multipliers.iter().zip(expected) {
+            assert!((m - e).abs() < 1e-12, "{m} != {e}");
+        }
+    }
+
+    #[test]
+    fn test_kelly_half_is_half_of_full() {
+        let full = kelly(0.08, 0.04, 1.0, -10.0, 10.0).unwrap();
+        let half = kelly(0.08, 0.04, 0.5, -10.0, 10.0).unwrap();
+
+        assert_eq!(full, 2.0);
+        assert_eq!(half, full / 2.0);
+        assert_eq!(kelly(-0.08, 0.04, 0.5, -10.0, 10.0), Some(-1.0));
+        assert_eq!(kelly(0.08, 0.04, 1.0, -1.0, 1.5), Some(1.5));
+        assert_eq!(kelly(0.08, 0.0, 1.0, -1.0, 1.0), None);
+    }
+}
@@ -0,0 +1,515 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::math::norm_cdf;
+use crate::utils::broadcast_inputs;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OptionType {
+    Call,
+    Put,
+}
+
+impl OptionType {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "call" => Some(Self::Call),
+            "put" => Some(Self::Put),
+            _ => None,
+        }
+    }
+
+    /// The payoff of the option at exercise.
+    pub(crate) fn payoff(self, spot: f64, strike: f64) -> f64 {
+        match self {
+            Self::Call => (spot - strike).max(0.0),
+            Self::Put => (strike - spot).max(0.0),
+        }
+    }
+}
+
+/// A European option on a non-dividend paying asset.
+///
+/// `rate` is the continuously compounded risk-free rate, `sigma` the annualized
+/// volatility and `t` the time to expiry in years.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OptionContract {
+    pub(crate) spot: f64,
+    pub(crate) strike: f64,
+    pub(crate) rate: f64,
+    pub(crate) sigma: f64,
+    pub(crate) t: f64,
+    pub(crate) option_type: OptionType,
+}
+
+impl OptionContract {
+    /// Build a contract, or `None` if the spot, strike, volatility or time to
+    /// expiry are not positive.
+    pub(crate) fn new(
+        spot: f64,
+        strike: f64,
+        rate: f64,
+        sigma: f64,
+        t: f64,
+        option_type: OptionType,
+    ) -> Option<Self> {
+        (spot > 0.0 && strike > 0.0 && sigma > 0.0 && t > 0.0).then_some(Self {
+            spot,
+            strike,
+            rate,
+            sigma,
+            t,
+            option_type,
+        })
+    }
+
+    /// The factor discounting a payoff at expiry to today.
+    pub(crate) fn discount(&self) -> f64 {
+        discount_factor(self.rate, self.t)
+    }
+}
+
+/// The factor `e^{-rt}` discounting a payoff in `t` years to today.
+fn discount_factor(rate: f64, t: f64) -> f64 {
+    (-rate * t).exp()
+}
+
+/// Calculate the signed deviation of call and put prices from put-call parity,
+/// `(C - P) - (S - K e^{-rt})`.
+///
+/// A positive deviation means the call is rich relative to the put.
+fn parity_deviation(call: f64, put: f64, spot: f64, strike: f64, rate: f64, t: f64) -> f64 {
+    (call - put) - (spot - strike * discount_factor(rate, t))
+}
+
+/// Calculate the Black-Scholes price of a European option.
+pub(crate) fn black_scholes_price(contract: &OptionContract) -> f64 {
+    let OptionContract {
+        spot,
+        strike,
+        rate,
+        sigma,
+        t,
+        option_type,
+    } = *contract;
+    let vol = sigma * t.sqrt();
+    let d1 = ((spot / strike).ln() + (rate + sigma * sigma / 2.0) * t) / vol;
+    let d2 = d1 - vol;
+    let discounted_strike = strike * contract.discount();
+    match option_type {
+        OptionType::Call => spot * norm_cdf(d1) - discounted_strike * norm_cdf(d2),
+        OptionType::Put => discounted_strike * norm_cdf(-d2) - spot * norm_cdf(-d1),
+    }
+}
+
+/// Calculate the forward price `S e^{(r - q) t}` of an asset with a continuous
+/// dividend yield `q`.
+pub(crate) fn compute_forward_price(spot: f64, rate: f64, dividend_yield: f64, t: f64) -> f64 {
+    spot * ((rate - dividend_yield) * t).exp()
+}
+
+/// Calculate the log-moneyness `ln(K / F)` of a strike, or `None` if the strike
+/// or forward are not positive.
+pub(crate) fn compute_log_moneyness(strike: f64, forward: f64) -> Option<f64> {
+    (strike > 0.0 && forward > 0.0).then(|| (strike / forward).ln())
+}
+
+/// Calculate the P&L of a delta-hedged option over a step of `dt` years from the
+/// gap between the realized and implied variance, `0.5 Γ S² (σ_r² - σ_i²) dt`.
+///
+/// A long gamma position earns from its gamma when the underlying moves more
+/// than implied, and pays the theta when it moves less.
+fn gamma_pnl_step(gamma: f64, spot: f64, realized_var: f64, implied_var: f64, dt: f64) -> f64 {
+    0.5 * gamma * spot * spot * (realized_var - implied_var) * dt
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BarrierType {
+    UpIn,
+    UpOut,
+    DownIn,
+    DownOut,
+}
+
+impl BarrierType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "up_in" => Some(Self::UpIn),
+            "up_out" => Some(Self::UpOut),
+            "down_in" => Some(Self::DownIn),
+            "down_out" => Some(Self::DownOut),
+            _ => None,
+        }
+    }
+}
+
+/// Calculate the price of a European barrier option without rebate.
+///
+/// Knock-in options become a vanilla option, and knock-out options worthless, if
+/// the price of the underlying touches `barrier` before expiry. A barrier the spot
+/// has already breached has knocked in or out, so the price is that of the
+/// vanilla option or zero.
+///
+/// Reference: Reiner and Rubinstein (1991), "Breaking down the barriers", as
+/// given by Haug, The Complete Guide to Option Pricing Formulas, pg. 152
+fn barrier_option_price(contract: &OptionContract, barrier: f64, barrier_type: BarrierType) -> f64 {
+    let OptionContract {
+        spot,
+        strike,
+        rate,
+        sigma,
+        t,
+        option_type,
+    } = *contract;
+    let is_up = matches!(barrier_type, BarrierType::UpIn | BarrierType::UpOut);
+    let is_in = matches!(barrier_type, BarrierType::UpIn | BarrierType::DownIn);
+    let breached = if is_up {
+        spot >= barrier
+    } else {
+        spot <= barrier
+    };
+    if breached {
+        return if is_in {
+            black_scholes_price(contract)
+        } else {
+            0.0
+        };
+    }
+
+    let phi = match option_type {
+        OptionType::Call => 1.0,
+        OptionType::Put => -1.0,
+    };
+    let eta = if is_up { -1.0 } else { 1.0 };
+    let vol = sigma * t.sqrt();
+    let mu = (rate - sigma * sigma / 2.0) / (sigma * sigma);
+    let drift = (1.0 + mu) * vol;
+    let discounted_strike = strike * contract.discount();
+    let x1 = (spot / strike).ln() / vol + drift;
+    let x2 = (spot / barrier).ln() / vol + drift;
+    let y1 = (barrier * barrier / (spot * strike)).ln() / vol + drift;
+    let y2 = (barrier / spot).ln() / vol + drift;
+    let spot_weight = (barrier / spot).powf(2.0 * (mu + 1.0));
+    let strike_weight = (barrier / spot).powf(2.0 * mu);
+    let a = phi * spot * norm_cdf(phi * x1) - phi * discounted_strike * norm_cdf(phi * (x1 - vol));
+    let b = phi * spot * norm_cdf(phi * x2) - phi * discounted_strike * norm_cdf(phi * (x2 - vol));
+    let c = phi * spot * spot_weight * norm_cdf(eta * y1)
+        - phi * discounted_strike * strike_weight * norm_cdf(eta * (y1 - vol));
+    let d = phi * spot * spot_weight * norm_cdf(eta * y2)
+        - phi * discounted_strike * strike_weight * norm_cdf(eta * (y2 - vol));
+
+    let strike_above = strike > barrier;
+    match (barrier_type, option_type, strike_above) {
+        (BarrierType::DownIn, OptionType::Call, true) => c,
+        (BarrierType::DownIn, OptionType::Call, false) => a - b + d,
+        (BarrierType::UpIn, OptionType::Call, true) => a,
+        (BarrierType::UpIn, OptionType::Call, false) => b - c + d,
+        (BarrierType::DownIn, OptionType::Put, true) => b - c + d,
+        (BarrierType::DownIn, OptionType::Put, false) => a,
+        (BarrierType::UpIn, OptionType::Put, true) => a - b + d,
+        (BarrierType::UpIn, OptionType::Put, false) => c,
+        (BarrierType::DownOut, OptionType::Call, true) => a - c,
+        (BarrierType::DownOut, OptionType::Call, false) => b - d,
+        (BarrierType::UpOut, OptionType::Call, true) => 0.0,
+        (BarrierType::UpOut, OptionType::Call, false) => a - b + c - d,
+        (BarrierType::DownOut, OptionType::Put, true) => a - b + c - d,
+        (BarrierType::DownOut, OptionType::Put, false) => 0.0,
+        (BarrierType::UpOut, OptionType::Put, true) => b - d,
+        (BarrierType::UpOut, OptionType::Put, false) => a - c,
+    }
+}
+
+/// Read the spot, strike, rate, sigma, t and type columns of an option
+/// expression into contracts. Rows with nulls, an unknown type or invalid
+/// parameters are `None`.
+pub(crate) fn option_contracts(inputs: &[Series]) -> PolarsResult<Vec<Option<OptionContract>>> {
+    let columns = inputs[..5]
+        .iter()
+        .map(|s| s.cast(&DataType::Float64))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let (spot, strike, rate) = (columns[0].f64()?, columns[1].f64()?, columns[2].f64()?);
+    let (sigma, t) = (columns[3].f64()?, columns[4].f64()?);
+    let option_type = inputs[5].str()?;
+    Ok(spot
+        .iter()
+        .zip(strike.iter())
+        .zip(rate.iter())
+        .zip(sigma.iter())
+        .zip(t.iter())
+        .zip(option_type.iter())
+        .map(|(((((spot, strike), rate), sigma), t), option_type)| {
+            OptionContract::new(
+                spot?,
+                strike?,
+                rate?,
+                sigma?,
+                t?,
+                OptionType::from_name(option_type?)?,
+            )
+        })
+        .collect())
+}
+
+#[polars_expr(output_type=Float64)]
+fn black_scholes(inputs: &[Series]) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let prices: Vec<Option<f64>> = option_contracts(inputs)?
+        .iter()
+        .map(|contract| contract.as_ref().map(black_scholes_price))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &prices).into_series())
+}
+
+#[derive(Deserialize)]
+struct BarrierOptionKwargs {
+    barrier_type: String,
+}
+
+#[polars_expr(output_type=Float64)]
+fn barrier_option(inputs: &[Series], kwargs: BarrierOptionKwargs) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let barrier_type = BarrierType::from_name(&kwargs.barrier_type).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown barrier_type: {}", kwargs.barrier_type).into())
+    })?;
+    let barriers = inputs[6].cast(&DataType::Float64)?;
+    let prices: Vec<Option<f64>> = option_contracts(inputs)?
+        .iter()
+        .zip(barriers.f64()?.iter())
+        .map(|(contract, barrier)| {
+            let barrier = barrier.filter(|&barrier| barrier > 0.0)?;
+            Some(barrier_option_price(
+                contract.as_ref()?,
+                barrier,
+                barrier_type,
+            ))
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &prices).into_series())
+}
+
+/// Read the spot, rate, dividend yield and time columns of a forward price
+/// expression, starting at `inputs[0]`, into forward prices.
+fn forward_prices(inputs: &[Series]) -> PolarsResult<Vec<Option<f64>>> {
+    let columns = inputs[..4]
+        .iter()
+        .map(|s| s.cast(&DataType::Float64))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let (spot, rate) = (columns[0].f64()?, columns[1].f64()?);
+    let (dividend_yield, t) = (columns[2].f64()?, columns[3].f64()?);
+    Ok(spot
+        .iter()
+        .zip(rate.iter())
+        .zip(dividend_yield.iter())
+        .zip(t.iter())
+        .map(|(((spot, rate), dividend_yield), t)| {
+            Some(compute_forward_price(spot?, rate?, dividend_yield?, t?))
+        })
+        .collect())
+}
+
+#[polars_expr(output_type=Float64)]
+fn forward_price(inputs: &[Series]) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let forwards = forward_prices(inputs)?;
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &forwards).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn log_moneyness(inputs: &[Series]) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let strikes = inputs[0].cast(&DataType::Float64)?;
+    let moneyness: Vec<Option<f64>> = strikes
+        .f64()?
+        .iter()
+        .zip(forward_prices(&inputs[1..])?)
+        .map(|(strike, forward)| compute_log_moneyness(strike?, forward?))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &moneyness).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn put_call_parity_violation(inputs: &[Series]) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let columns = inputs[..6]
+        .iter()
+        .map(|s| s.cast(&DataType::Float64))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let (call, put, spot) = (columns[0].f64()?, columns[1].f64()?, columns[2].f64()?);
+    let (strike, rate, t) = (columns[3].f64()?, columns[4].f64()?, columns[5].f64()?);
+    let deviations: Vec<Option<f64>> = call
+        .iter()
+        .zip(put.iter())
+        .zip(spot.iter())
+        .zip(strike.iter())
+        .zip(rate.iter())
+        .zip(t.iter())
+        .map(|(((((call, put), spot), strike), rate), t)| {
+            Some(parity_deviation(call?, put?, spot?, strike?, rate?, t?))
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &deviations).into_series())
+}
+
+#[derive(Deserialize)]
+struct GammaPnlKwargs {
+    dt: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn gamma_pnl(inputs: &[Series], kwargs: GammaPnlKwargs) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let columns = inputs[..4]
+        .iter()
+        .map(|s| s.cast(&DataType::Float64))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let (gamma, spot) = (columns[0].f64()?, columns[1].f64()?);
+    let (realized_var, implied_var) = (columns[2].f64()?, columns[3].f64()?);
+    let pnl: Vec<Option<f64>> = gamma
+        .iter()
+        .zip(spot.iter())
+        .zip(realized_var.iter())
+        .zip(implied_var.iter())
+        .map(|(((gamma, spot), realized_var), implied_var)| {
+            Some(gamma_pnl_step(
+                gamma?,
+                spot?,
+                realized_var?,
+                implied_var?,
+                kwargs.dt,
+            ))
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &pnl).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_black_scholes_price() {
+        let call = OptionContract::new(100.0, 100.0, 0.05, 0.2, 1.0, OptionType::Call).unwrap();
+        let put = OptionContract {
+            option_type: OptionType::Put,
+            ..call
+        };
+
+        assert!((black_scholes_price(&call) - 10.450_583_572_185_565).abs() < 1e-9);
+        assert!((black_scholes_price(&put) - 5.573_526_022_256_971).abs() < 1e-9);
+        // Put-call parity: C - P = S - K e^{-rT}.
+        let parity = 100.0 - 100.0 * call.discount();
+        assert!((black_scholes_price(&call) - black_scholes_price(&put) - parity).abs() < 1e-12);
+        assert_eq!(
+            OptionContract::new(100.0, 100.0, 0.05, 0.0, 1.0, OptionType::Call),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parity_deviation() {
+        for strike in [80.0, 100.0, 125.0] {
+            let call =
+                OptionContract::new(100.0, strike, 0.05, 0.3, 0.75, OptionType::Call).unwrap();
+            let put = OptionContract {
+                option_type: OptionType::Put,
+                ..call
+            };
+
+            let deviation = parity_deviation(
+                black_scholes_price(&call),
+                black_scholes_price(&put),
+                100.0,
+                strike,
+                0.05,
+                0.75,
+            );
+            assert!(deviation.abs() < 1e-9);
+        }
+        // At the money with no rates, the call and put should be equal.
+        let deviation = parity_deviation(5.5, 5.0, 100.0, 100.0, 0.0, 1.0);
+        assert!((deviation - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_gamma_pnl_step() {
+        let dt = 1.0 / 252.0;
+
+        // Realized vol of 30% against 20% implied on a long gamma position.
+        let pnl = gamma_pnl_step(0.02, 100.0, 0.09, 0.04, dt);
+        assert!(pnl > 0.0);
+        assert!((pnl - 0.5 * 0.02 * 10_000.0 * 0.05 * dt).abs() < 1e-15);
+        // The same move loses when short gamma, or when realized is below implied.
+        assert!(gamma_pnl_step(-0.02, 100.0, 0.09, 0.04, dt) < 0.0);
+        assert!(gamma_pnl_step(0.02, 100.0, 0.01, 0.04, dt) < 0.0);
+        assert_eq!(gamma_pnl_step(0.02, 100.0, 0.04, 0.04, dt), 0.0);
+    }
+
+    #[test]
+    fn test_forward_price_and_log_moneyness() {
+        let forward = compute_forward_price(100.0, 0.05, 0.02, 2.0);
+
+        assert!((forward - 100.0 * 0.06_f64.exp()).abs() < 1e-12);
+        assert_eq!(compute_forward_price(100.0, 0.03, 0.03, 1.0), 100.0);
+        // A strike at the money forward has zero log-moneyness.
+        assert_eq!(compute_log_moneyness(forward, forward), Some(0.0));
+        assert!(compute_log_moneyness(100.0, forward).unwrap() < 0.0);
+        assert!((compute_log_moneyness(110.0, 100.0).unwrap() - 1.1_f64.ln()).abs() < 1e-15);
+        assert_eq!(compute_log_moneyness(0.0, forward), None);
+    }
+
+    #[test]
+    fn test_barrier_option_in_out_parity() {
+        let call = OptionContract::new(100.0, 100.0, 0.05, 0.25, 0.5, OptionType::Call).unwrap();
+        for option_type in [OptionType::Call, OptionType::Put] {
+            for strike in [90.0, 100.0, 110.0] {
+                let contract = OptionContract {
+                    strike,
+                    option_type,
+                    ..call
+                };
+                let vanilla = black_scholes_price(&contract);
+                for (barrier, knock_in, knock_out) in [
+                    (105.0, BarrierType::UpIn, BarrierType::UpOut),
+                    (95.0, BarrierType::DownIn, BarrierType::DownOut),
+                ] {
+                    let price_in = barrier_option_price(&contract, barrier, knock_in);
+                    let price_out = barrier_option_price(&contract, barrier, knock_out);
+                    assert!(price_in >= -1e-12 && price_out >= -1e-12);
+                    assert!((price_in + price_out - vanilla).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_barrier_option_price() {
+        let call = OptionContract::new(100.0, 100.0, 0.05, 0.25, 0.5, OptionType::Call).unwrap();
+        let vanilla = black_scholes_price(&call);
+
+        let down_out = barrier_option_price(&call, 95.0, BarrierType::DownOut);
+        assert!((down_out - 4.771_773_147_229_027).abs() < 1e-9);
+        assert_eq!(barrier_option_price(&call, 95.0, BarrierType::UpOut), 0.0);
+        assert_eq!(
+            barrier_option_price(&call, 95.0, BarrierType::UpIn),
+            vanilla
+        );
+        assert_eq!(
+            barrier_option_price(&call, 100.0, BarrierType::DownOut),
+            0.0
+        );
+        // A call that knocks out below its strike can't pay off.
+        let otm_call = OptionContract {
+            strike: 110.0,
+            ..call
+        };
+        assert_eq!(
+            barrier_option_price(&otm_call, 105.0, BarrierType::UpOut),
+            0.0
+        );
+        // A distant barrier is almost never touched.
+        assert!((barrier_option_price(&call, 1.0, BarrierType::DownOut) - vanilla).abs() < 1e-9);
+        assert!(barrier_option_price(&call, 1.0, BarrierType::DownIn).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,158 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::sample_weights::to_spans;
+
+/// The role of a label in one fold of a purged k-fold split. Labels that are
+/// neither are purged or embargoed.
+const TEST: i8 = 1;
+const TRAIN: i8 = 0;
+
+/// Assign each label a role in each of `n_splits` purged k-fold splits.
+///
+/// The labels, ordered by start, are split into contiguous test folds. A label is
+/// in the training set of a fold if it ends before the first test label starts,
+/// or starts more than `embargo` bars after the last test label ends. Labels
+/// overlapping the test labels' horizon are purged, and those starting within
+/// the embargo after it are embargoed, both as `None`.
+///
+/// Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 109
+fn purged_kfold_roles(
+    spans: &[Option<(usize, usize)>],
+    n_splits: usize,
+    embargo: usize,
+) -> Vec<Vec<Option<i8>>> {
+    let n = spans.len();
+    (0..n_splits)
+        .map(|fold| {
+            // The first n % n_splits folds take one extra label.
+            let fold_start = fold * (n / n_splits) + fold.min(n % n_splits);
+            let fold_end = fold_start + n / n_splits + usize::from(fold < n % n_splits);
+            let test_spans = spans[fold_start..fold_end].iter().flatten();
+            let test_start = test_spans.clone().map(|&(start, _)| start).min();
+            let test_end = test_spans.map(|&(_, end)| end).max();
+            spans
+                .iter()
+                .enumerate()
+                .map(|(i, span)| {
+                    let (start, end) = (*span)?;
+                    if (fold_start..fold_end).contains(&i) {
+                        return Some(TEST);
+                    }
+                    match (test_start, test_end) {
+                        (Some(test_start), Some(test_end))
+                            if end >= test_start && start <= test_end + embargo =>
+                        {
+                            None
+                        },
+                        _ => Some(TRAIN),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct PurgedKFoldKwargs {
+    n_splits: usize,
+    embargo_pct: f64,
+}
+
+fn purged_kfold_struct(input_fields: &[Field], kwargs: PurgedKFoldKwargs) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(
+            (0..kwargs.n_splits)
+                .map(|fold| Field::new(format!("fold_{fold}").into(), DataType::Int8))
+                .collect(),
+        ),
+    ))
+}
+
+#[polars_expr(output_type_func_with_kwargs=purged_kfold_struct)]
+fn purged_kfold_indices(inputs: &[Series], kwargs: PurgedKFoldKwargs) -> PolarsResult<Series> {
+    let spans = to_spans(&inputs[0], &inputs[1])?;
+    if kwargs.n_splits < 2 || kwargs.n_splits > spans.len() {
+        return Err(PolarsError::ComputeError(
+            "n_splits must be at least 2 and at most the number of labels".into(),
+        ));
+    }
+    if !(0.0..1.0).contains(&kwargs.embargo_pct) {
+        return Err(PolarsError::ComputeError(
+            "embargo_pct must be in [0, 1)".into(),
+        ));
+    }
+    let starts: Vec<usize> = spans.iter().flatten().map(|&(start, _)| start).collect();
+    if starts.windows(2).any(|w| w[1] < w[0]) {
+        return Err(PolarsError::ComputeError(
+            "Labels must be sorted by start".into(),
+        ));
+    }
+    let n_bars = spans
+        .iter()
+        .flatten()
+        .map(|&(_, end)| end + 1)
+        .max()
+        .unwrap_or(0);
+    let embargo = (n_bars as f64 * kwargs.embargo_pct) as usize;
+    let fields: Vec<Series> = purged_kfold_roles(&spans, kwargs.n_splits, embargo)
+        .iter()
+        .enumerate()
+        .map(|(fold, roles)| Int8Chunked::new(format!("fold_{fold}").into(), roles).into_series())
+        .collect();
+    Ok(
+        StructChunked::from_series(inputs[0].name().clone(), spans.len(), fields.iter())?
+            .into_series(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_purged_kfold_roles_no_train_test_overlap() {
+        // Labels start at each bar and span the next two bars.
+        let spans: Vec<Option<(usize, usize)>> = (0..10).map(|i| Some((i, i + 2))).collect();
+
+        let folds = purged_kfold_roles(&spans, 3, 1);
+
+        assert_eq!(folds.len(), 3);
+        // The first fold takes the extra label. Its labels end by bar 5, so labels
+        // starting up to bar 6 are purged or embargoed.
+        assert_eq!(
+            folds[0],
+            vec![
+                Some(TEST),
+                Some(TEST),
+                Some(TEST),
+                Some(TEST),
+                None,
+                None,
+                None,
+                Some(TRAIN),
+                Some(TRAIN),
+                Some(TRAIN)
+            ]
+        );
+        for roles in &folds {
+            let test: Vec<(usize, usize)> = spans
+                .iter()
+                .zip(roles)
+                .filter(|(_, role)| **role == Some(TEST))
+                .map(|(span, _)| span.unwrap())
+                .collect();
+            let test_start = test.iter().map(|s| s.0).min().unwrap();
+            let test_end = test.iter().map(|s| s.1).max().unwrap();
+            for (span, role) in spans.iter().zip(roles) {
+                let (start, end) = span.unwrap();
+                if *role == Some(TRAIN) {
+                    assert!(end < test_start || start > test_end + 1);
+                }
+            }
+        }
+    }
+}
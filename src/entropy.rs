@@ -0,0 +1,231 @@
+#![allow(clippy::unused_unit)]
+use std::collections::{HashMap, HashSet};
+
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::stats::rolling_statistic;
+
+/// Estimate the entropy rate of a message with the plug-in (maximum likelihood)
+/// estimator, in bits per symbol.
+///
+/// The entropy of the empirical distribution of overlapping words of
+/// `word_length` symbols is divided by the word length. With a word length of 1
+/// this is the Shannon entropy of the symbol frequencies.
+fn plugin_entropy(message: &[i64], word_length: usize) -> f64 {
+    let mut counts: HashMap<&[i64], usize> = HashMap::new();
+    for word in message.windows(word_length) {
+        *counts.entry(word).or_default() += 1;
+    }
+    let n_words = (message.len() + 1).saturating_sub(word_length) as f64;
+    -counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / n_words;
+            p * p.log2()
+        })
+        .sum::<f64>()
+        / word_length as f64
+}
+
+/// Estimate the complexity of a message from the size of its Lempel-Ziv library,
+/// relative to the length of the message.
+///
+/// The message is parsed into the shortest substrings not already in the library.
+/// Redundant messages are built from few, long substrings and score lower than
+/// random ones.
+fn lempel_ziv_entropy(message: &[i64]) -> f64 {
+    let Some(first) = message.get(..1) else {
+        return 0.0;
+    };
+    let mut library: HashSet<&[i64]> = HashSet::from([first]);
+    let mut i = 1;
+    while i < message.len() {
+        let mut j = i;
+        while j < message.len() {
+            if library.insert(&message[i..=j]) {
+                break;
+            }
+            j += 1;
+        }
+        i = j + 1;
+    }
+    library.len() as f64 / message.len() as f64
+}
+
+/// Calculate an entropy estimate over a rolling window of symbols.
+///
+/// The first `window - 1` rows, and windows containing a null symbol, are `None`.
+fn rolling_entropy(
+    symbols: &[Option<i64>],
+    window: usize,
+    estimator: impl Fn(&[i64]) -> f64,
+) -> Vec<Option<f64>> {
+    rolling_statistic(symbols, window, |message| Some(estimator(message)))
+}
+
+/// Encode returns by their sign as -1, 0 or 1.
+fn sign_codes(returns: &[Option<f64>]) -> Vec<Option<i64>> {
+    returns
+        .iter()
+        .map(|r| {
+            r.map(|r| {
+                if r > 0.0 {
+                    1
+                } else if r < 0.0 {
+                    -1
+                } else {
+                    0
+                }
+            })
+        })
+        .collect()
+}
+
+/// Encode returns by their quantile bin, from 0 to `n_bins - 1`.
+///
+/// The bin of a return is `rank * n_bins / n` where `rank` is the number of
+/// returns strictly below it, so tied returns always share a bin and the result
+/// does not depend on the order of the input.
+fn quantile_codes(returns: &[Option<f64>], n_bins: usize) -> Vec<Option<i64>> {
+    let mut sorted: Vec<f64> = returns.iter().flatten().copied().collect();
+    sorted.sort_by(f64::total_cmp);
+    returns
+        .iter()
+        .map(|r| {
+            r.map(|r| {
+                let rank = sorted.partition_point(|v| v.total_cmp(&r).is_lt());
+                (rank * n_bins / sorted.len()) as i64
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct EntropyKwargs {
+    window: usize,
+    method: String,
+    word_length: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn entropy(inputs: &[Series], kwargs: EntropyKwargs) -> PolarsResult<Series> {
+    if kwargs.word_length == 0 || kwargs.window < kwargs.word_length {
+        return Err(PolarsError::ComputeError(
+            "Window and word length must satisfy 1 <= word_length <= window".into(),
+        ));
+    }
+    let symbols: Vec<Option<i64>> = inputs[0].cast(&DataType::Int64)?.i64()?.iter().collect();
+    let values = match kwargs.method.as_str() {
+        "shannon" => rolling_entropy(&symbols, kwargs.window, |m| plugin_entropy(m, 1)),
+        "plugin" => rolling_entropy(&symbols, kwargs.window, |m| {
+            plugin_entropy(m, kwargs.word_length)
+        }),
+        "lempel_ziv" => rolling_entropy(&symbols, kwargs.window, lempel_ziv_entropy),
+        method => {
+            return Err(PolarsError::ComputeError(
+                format!("Unknown entropy method: {method}").into(),
+            ))
+        },
+    };
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &values).into_series())
+}
+
+#[derive(Deserialize)]
+struct EncodeReturnsKwargs {
+    method: String,
+    n_bins: Option<usize>,
+}
+
+#[polars_expr(output_type=Int64)]
+fn encode_returns(inputs: &[Series], kwargs: EncodeReturnsKwargs) -> PolarsResult<Series> {
+    let returns: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let codes = match (kwargs.method.as_str(), kwargs.n_bins) {
+        ("sign", _) => sign_codes(&returns),
+        ("quantile", Some(n_bins)) if n_bins > 0 => quantile_codes(&returns, n_bins),
+        ("quantile", _) => {
+            return Err(PolarsError::ComputeError(
+                "Quantile encoding requires n_bins >= 1".into(),
+            ))
+        },
+        (method, _) => {
+            return Err(PolarsError::ComputeError(
+                format!("Unknown encoding method: {method}").into(),
+            ))
+        },
+    };
+    Ok(Int64Chunked::new(inputs[0].name().clone(), &codes).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A binary de Bruijn sequence of order 3 contains every word of up to three
+    // symbols equally often when wrapped.
+    const DE_BRUIJN: [i64; 8] = [0, 0, 0, 1, 0, 1, 1, 1];
+
+    #[test]
+    fn test_plugin_entropy_constant_and_uniform() {
+        assert_eq!(plugin_entropy(&[3; 16], 1), 0.0);
+        assert_eq!(plugin_entropy(&[3; 16], 2), 0.0);
+        assert_eq!(plugin_entropy(&[0, 1, 2, 3, 3, 2, 1, 0], 1), 2.0);
+
+        let message: Vec<i64> = DE_BRUIJN.iter().cycle().take(8 * 16 + 1).copied().collect();
+        assert!((plugin_entropy(&message, 2) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_lempel_ziv_entropy_constant_vs_random() {
+        // The library is {a, aa, aaa}.
+        assert_eq!(lempel_ziv_entropy(&[1; 8]), 3.0 / 8.0);
+
+        // A pseudo-random binary message from a xorshift generator.
+        let mut seed: u64 = 42;
+        let random: Vec<i64> = (0..256)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                (seed >> 63) as i64
+            })
+            .collect();
+        assert!(lempel_ziv_entropy(&random) > 2.0 * lempel_ziv_entropy(&[1; 256]));
+    }
+
+    #[test]
+    fn test_rolling_entropy() {
+        let symbols = vec![Some(1), Some(1), Some(2), None, Some(2), Some(2)];
+
+        let values = rolling_entropy(&symbols, 2, |m| plugin_entropy(m, 1));
+
+        assert_eq!(
+            values,
+            vec![None, Some(0.0), Some(1.0), None, None, Some(0.0)]
+        );
+    }
+
+    #[test]
+    fn test_sign_codes() {
+        let returns = vec![Some(0.02), Some(-0.01), Some(0.0), None];
+
+        assert_eq!(sign_codes(&returns), vec![Some(1), Some(-1), Some(0), None]);
+    }
+
+    #[test]
+    fn test_quantile_codes() {
+        let returns = vec![Some(0.4), Some(0.1), None, Some(0.3), Some(0.2)];
+        assert_eq!(
+            quantile_codes(&returns, 2),
+            vec![Some(1), Some(0), None, Some(1), Some(0)]
+        );
+
+        // Tied returns share the lowest bin they would occupy.
+        let ties = vec![Some(0.1), Some(0.2), Some(0.1), Some(0.1)];
+        assert_eq!(
+            quantile_codes(&ties, 2),
+            vec![Some(0), Some(1), Some(0), Some(0)]
+        );
+    }
+}
@@ -0,0 +1,513 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::stats::rolling_statistic;
+use crate::structural_breaks::supremum_adf;
+
+/// Flag values that deviate from their exponentially weighted mean by more than
+/// `threshold` exponentially weighted standard deviations.
+///
+/// Each value is standardized with the mean and variance of the values before
+/// it, which are then updated online with `alpha = 2 / (span + 1)`. Values above
+/// the band are +1, values below it -1 and values within it 0. The first `span`
+/// values, while the estimates warm up, and nulls are `None`.
+fn ewma_zscore_events(values: &[Option<f64>], span: usize, threshold: f64) -> Vec<Option<i8>> {
+    let alpha = 2.0 / (span as f64 + 1.0);
+    let mut state: Option<(f64, f64)> = None;
+    let mut seen = 0;
+    values
+        .iter()
+        .map(|value| {
+            let value = (*value)?;
+            seen += 1;
+            let Some((mean, variance)) = state else {
+                state = Some((value, 0.0));
+                return None;
+            };
+            let diff = value - mean;
+            state = Some((
+                mean + alpha * diff,
+                (1.0 - alpha) * (variance + alpha * diff.powi(2)),
+            ));
+            if seen <= span {
+                return None;
+            }
+            let z = diff / variance.sqrt();
+            Some(if z > threshold {
+                1
+            } else if z < -threshold {
+                -1
+            } else {
+                0
+            })
+        })
+        .collect()
+}
+
+/// Flag the values at which the cumulative sum of diffs since the last event
+/// moves more than its threshold up or down.
+///
+/// The positive sum is floored at 0 and the negative sum capped at 0, so each
+/// only accumulates moves in its own direction. An upward event is +1, a
+/// downward event -1 and no event 0. After an event the sum that triggered it is
+/// reset to 0, or with `carry_residual` reduced by `threshold` so the overshoot
+/// counts towards the next event. Nulls are `None` and leave the sums unchanged.
+///
+/// Each diff is compared against the threshold in the same row of `thresholds`,
+/// so the threshold can track the volatility of the diffs. A diff with a null or
+/// non-positive threshold is treated like a null diff.
+///
+/// An event fewer than `min_gap` values after the previous event is suppressed
+/// as 0, though its sum is still reset. With `skip_first`, the first non-null
+/// diff only marks the starting point: it is 0 and the sums start after it, so a
+/// large first diff, e.g. from an arbitrary initial value, cannot fire an event.
+///
+/// Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 39
+fn symmetric_cusum_events(
+    diffs: &[Option<f64>],
+    thresholds: &[Option<f64>],
+    carry_residual: bool,
+    min_gap: usize,
+    skip_first: bool,
+) -> Vec<Option<i8>> {
+    let (mut s_pos, mut s_neg) = (0.0_f64, 0.0_f64);
+    let mut last_event: Option<usize> = None;
+    let mut skip = skip_first;
+    diffs
+        .iter()
+        .zip(thresholds)
+        .enumerate()
+        .map(|(i, (diff, threshold))| {
+            let diff = (*diff)?;
+            let threshold = threshold.filter(|threshold| *threshold > 0.0)?;
+            if std::mem::take(&mut skip) {
+                return Some(0);
+            }
+            s_pos = (s_pos + diff).max(0.0);
+            s_neg = (s_neg + diff).min(0.0);
+            let event = if s_neg < -threshold {
+                s_neg = if carry_residual {
+                    s_neg + threshold
+                } else {
+                    0.0
+                };
+                -1
+            } else if s_pos > threshold {
+                s_pos = if carry_residual {
+                    s_pos - threshold
+                } else {
+                    0.0
+                };
+                1
+            } else {
+                return Some(0);
+            };
+            if last_event.is_some_and(|last| i - last < min_gap) {
+                return Some(0);
+            }
+            last_event = Some(i);
+            Some(event)
+        })
+        .collect()
+}
+
+/// Flag the observations at which a statistic crosses above `crit_value`.
+///
+/// An event is 1 where the statistic is above `crit_value` but was not at the
+/// previous observation, and 0 otherwise, so a regime above the critical value
+/// fires once when it starts. Missing statistics are `None` and count as below
+/// the critical value.
+fn threshold_crossings(statistic: &[Option<f64>], crit_value: f64) -> Vec<Option<i8>> {
+    let mut was_above = false;
+    statistic
+        .iter()
+        .map(|value| {
+            let above = value.is_some_and(|value| value > crit_value);
+            let event = above && !was_above;
+            was_above = above;
+            value.map(|_| i8::from(event))
+        })
+        .collect()
+}
+
+/// Calculate the `quantile` of the absolute values with linear interpolation
+/// between the closest ranks.
+fn abs_quantile(values: &[f64], quantile: f64) -> Option<f64> {
+    let mut sorted: Vec<f64> = values.iter().map(|value| value.abs()).collect();
+    sorted.sort_by(f64::total_cmp);
+    let rank = quantile * (sorted.len().checked_sub(1)? as f64);
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    Some(sorted[lower] + (rank - lower as f64) * (sorted[upper] - sorted[lower]))
+}
+
+/// Calculate the log difference of each price from the previous one. The first
+/// price has no change, and differences involving a null are `None`.
+fn log_diffs(prices: &[Option<f64>]) -> Vec<Option<f64>> {
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, price)| match i {
+            0 => price.map(|_| 0.0),
+            _ => Some((price.as_ref()? / prices[i - 1]?).ln()),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CusumInput {
+    Price,
+    Diff,
+}
+
+impl CusumInput {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "price" => Some(Self::Price),
+            "diff" => Some(Self::Diff),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ZScoreFilterKwargs {
+    span: usize,
+    threshold: f64,
+}
+
+#[polars_expr(output_type=Int8)]
+fn zscore_filter(inputs: &[Series], kwargs: ZScoreFilterKwargs) -> PolarsResult<Series> {
+    if kwargs.span == 0 || kwargs.threshold <= 0.0 {
+        return Err(PolarsError::ComputeError(
+            "span and threshold must be positive".into(),
+        ));
+    }
+    let values: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let events = ewma_zscore_events(&values, kwargs.span, kwargs.threshold);
+    Ok(Int8Chunked::new(inputs[0].name().clone(), &events).into_series())
+}
+
+#[derive(Deserialize)]
+struct RollingQuantileThresholdKwargs {
+    window: usize,
+    quantile: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn rolling_quantile_threshold(
+    inputs: &[Series],
+    kwargs: RollingQuantileThresholdKwargs,
+) -> PolarsResult<Series> {
+    if kwargs.window == 0 || !(0.0..=1.0).contains(&kwargs.quantile) {
+        return Err(PolarsError::ComputeError(
+            "window must be positive and quantile between 0 and 1".into(),
+        ));
+    }
+    let values: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let thresholds = rolling_statistic(&values, kwargs.window, |window| {
+        abs_quantile(window, kwargs.quantile)
+    });
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &thresholds).into_series())
+}
+
+#[derive(Deserialize)]
+struct CusumKwargs {
+    threshold: Option<f64>,
+    #[serde(default)]
+    carry_residual: bool,
+    #[serde(default)]
+    min_gap: usize,
+    #[serde(default)]
+    skip_first: bool,
+    #[serde(default = "default_cusum_input")]
+    input: String,
+}
+
+fn default_cusum_input() -> String {
+    "diff".to_string()
+}
+
+#[polars_expr(output_type=Int8)]
+fn symmetric_cusum_filter(inputs: &[Series], kwargs: CusumKwargs) -> PolarsResult<Series> {
+    let input = CusumInput::from_name(&kwargs.input).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown input: {}", kwargs.input).into())
+    })?;
+    let values: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let diffs = match input {
+        CusumInput::Price => log_diffs(&values),
+        CusumInput::Diff => values,
+    };
+    let thresholds = match (kwargs.threshold, inputs.get(1)) {
+        (Some(threshold), None) if threshold > 0.0 => vec![Some(threshold); diffs.len()],
+        (None, Some(thresholds)) if thresholds.len() == 1 => {
+            vec![thresholds.cast(&DataType::Float64)?.f64()?.get(0); diffs.len()]
+        },
+        (None, Some(thresholds)) if thresholds.len() == diffs.len() => {
+            thresholds.cast(&DataType::Float64)?.f64()?.iter().collect()
+        },
+        (None, Some(_)) => {
+            return Err(PolarsError::ComputeError(
+                "threshold column must have the same length as the input".into(),
+            ))
+        },
+        _ => {
+            return Err(PolarsError::ComputeError(
+                "threshold must be a positive number or a column".into(),
+            ))
+        },
+    };
+    let events = symmetric_cusum_events(
+        &diffs,
+        &thresholds,
+        kwargs.carry_residual,
+        kwargs.min_gap,
+        kwargs.skip_first,
+    );
+    Ok(Int8Chunked::new(inputs[0].name().clone(), &events).into_series())
+}
+
+#[derive(Deserialize)]
+struct SadfFilterKwargs {
+    min_window: usize,
+    lag: usize,
+    crit_value: f64,
+}
+
+#[polars_expr(output_type=Int8)]
+fn sadf_filter(inputs: &[Series], kwargs: SadfFilterKwargs) -> PolarsResult<Series> {
+    if kwargs.min_window < 2 * kwargs.lag + 4 {
+        return Err(PolarsError::ComputeError(
+            "min_window must be at least 2 * lag + 4".into(),
+        ));
+    }
+    let values = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let statistic = supremum_adf(&values, kwargs.min_window, kwargs.lag);
+    let events = threshold_crossings(&statistic, kwargs.crit_value);
+    Ok(Int8Chunked::new(inputs[0].name().clone(), &events).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ewma_zscore_events_volatility_adjusted() {
+        // The same 1% move follows a calm regime and a volatile regime.
+        let mut returns: Vec<Option<f64>> = (0..40)
+            .map(|i| Some(if i % 2 == 0 { 0.001 } else { -0.001 }))
+            .collect();
+        returns.push(Some(0.01));
+        returns.extend((0..40).map(|i| Some(if i % 2 == 0 { 0.02 } else { -0.02 })));
+        returns.push(Some(0.01));
+
+        let events = ewma_zscore_events(&returns, 10, 3.0);
+
+        assert!(events[..10].iter().all(Option::is_none));
+        assert!(events[10..40].iter().all(|e| *e == Some(0)));
+        assert_eq!(events[40], Some(1));
+        assert_eq!(events[81], Some(0));
+    }
+
+    #[test]
+    fn test_threshold_crossings_fire_in_explosive_regime() {
+        // A random-looking walk followed by a segment growing 5% per step.
+        let steps = [0.3, -0.5, 0.2, 0.4, -0.1, -0.6, 0.5, -0.2, 0.1, -0.3];
+        let mut values: Vec<f64> = steps
+            .iter()
+            .cycle()
+            .take(60)
+            .scan(10.0, |level, step| {
+                *level += step;
+                Some(*level)
+            })
+            .collect();
+        let last = *values.last().unwrap();
+        values.extend((1..=20).map(|i| last * 1.05f64.powi(i) + steps[i as usize % 10]));
+
+        let events = threshold_crossings(&supremum_adf(&values, 20, 1), 1.0);
+
+        assert!(events[..19].iter().all(Option::is_none));
+        let fired: Vec<usize> = (0..events.len())
+            .filter(|&i| events[i] == Some(1))
+            .collect();
+        assert_eq!(fired, vec![65]);
+
+        // Dropping back below the critical value re-arms the filter.
+        let statistic = [None, Some(0.5), Some(1.5), Some(2.0), Some(0.5), Some(1.2)];
+        assert_eq!(
+            threshold_crossings(&statistic, 1.0),
+            vec![None, Some(0), Some(1), Some(0), Some(0), Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_symmetric_cusum_events_carry_residual() {
+        let diffs = vec![Some(0.7); 10];
+
+        let reset = symmetric_cusum_events(&diffs, &[Some(1.0); 10], false, 0, false);
+        let carried = symmetric_cusum_events(&diffs, &[Some(1.0); 10], true, 0, false);
+
+        // Resetting to 0 fires every second diff; carrying the 0.4 overshoot
+        // fires earlier once it has built up.
+        let ups = |events: &[Option<i8>]| events.iter().filter(|e| **e == Some(1)).count();
+        assert_eq!(ups(&reset), 5);
+        assert_eq!(ups(&carried), 6);
+        assert_eq!(reset[..4], [Some(0), Some(1), Some(0), Some(1)]);
+        assert_ne!(reset, carried);
+
+        let events = symmetric_cusum_events(
+            &[Some(-0.7), None, Some(-0.7), Some(0.2)],
+            &[Some(1.0); 4],
+            false,
+            0,
+            false,
+        );
+        assert_eq!(events, vec![Some(0), None, Some(-1), Some(0)]);
+    }
+
+    #[test]
+    fn test_symmetric_cusum_events_min_gap() {
+        // A jump up is followed by a jump down a bar later.
+        let diffs = [Some(1.5), Some(-1.5), Some(0.1), Some(0.1), Some(1.5)];
+
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &[Some(1.0); 5], false, 0, false),
+            vec![Some(1), Some(-1), Some(0), Some(0), Some(1)]
+        );
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &[Some(1.0); 5], false, 3, false),
+            vec![Some(1), Some(0), Some(0), Some(0), Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_symmetric_cusum_events_skip_first() {
+        // The first diff is a jump from an arbitrary initial value.
+        let diffs = [None, Some(5.0), Some(0.6), Some(0.6), Some(-0.3)];
+
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &[Some(1.0); 5], false, 0, false),
+            vec![None, Some(1), Some(0), Some(1), Some(0)]
+        );
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &[Some(1.0); 5], false, 0, true),
+            vec![None, Some(0), Some(0), Some(1), Some(0)]
+        );
+    }
+
+    #[test]
+    fn test_symmetric_cusum_events_rising_threshold() {
+        let diffs = [Some(0.6); 8];
+        let mut thresholds = vec![Some(1.0); 4];
+        thresholds.extend([Some(5.0); 4]);
+
+        // Small moves fire every second diff until the threshold rises.
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &thresholds, false, 0, false),
+            vec![
+                Some(0),
+                Some(1),
+                Some(0),
+                Some(1),
+                Some(0),
+                Some(0),
+                Some(0),
+                Some(0)
+            ]
+        );
+        assert_eq!(
+            symmetric_cusum_events(&diffs[..3], &[None, Some(0.0), Some(1.0)], false, 0, false),
+            vec![None, None, Some(0)]
+        );
+    }
+
+    #[test]
+    fn test_symmetric_cusum_events_time_varying_threshold() {
+        let diffs = [
+            Some(0.6),
+            Some(0.6),
+            Some(0.6),
+            Some(0.6),
+            Some(-2.0),
+            Some(0.6),
+            Some(0.6),
+        ];
+        let mut thresholds = vec![Some(0.5); 2];
+        thresholds.extend([Some(3.0); 5]);
+
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &[Some(1.0); 7], false, 0, false),
+            vec![
+                Some(0),
+                Some(1),
+                Some(0),
+                Some(1),
+                Some(-1),
+                Some(0),
+                Some(1)
+            ]
+        );
+        // A tight threshold fires on every early move, then a wide one absorbs
+        // both the jump down and the drift back up.
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &thresholds, false, 0, false),
+            vec![
+                Some(1),
+                Some(1),
+                Some(0),
+                Some(0),
+                Some(0),
+                Some(0),
+                Some(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rolling_abs_quantile_threshold() {
+        let values = [
+            Some(-1.0),
+            Some(2.0),
+            Some(-3.0),
+            Some(4.0),
+            None,
+            Some(1.0),
+        ];
+
+        let thresholds = rolling_statistic(&values, 3, |window| abs_quantile(window, 0.5));
+
+        assert_eq!(
+            thresholds,
+            vec![None, None, Some(2.0), Some(3.0), None, None]
+        );
+        assert_eq!(abs_quantile(&[1.0, -3.0], 0.25), Some(1.5));
+        assert_eq!(abs_quantile(&[], 0.5), None);
+    }
+
+    #[test]
+    fn test_symmetric_cusum_events_from_prices() {
+        let prices: [f64; 6] = [100.0, 101.0, 103.0, 102.0, 99.0, 100.5];
+        let manual: Vec<Option<f64>> = std::iter::once(Some(0.0))
+            .chain(prices.windows(2).map(|w| Some((w[1] / w[0]).ln())))
+            .collect();
+
+        let diffs = log_diffs(&prices.map(Some));
+
+        assert_eq!(diffs, manual);
+        assert_eq!(
+            symmetric_cusum_events(&diffs, &[Some(0.02); 6], false, 0, false),
+            vec![Some(0), Some(0), Some(1), Some(0), Some(-1), Some(0)]
+        );
+        assert_eq!(
+            log_diffs(&[Some(1.0), None, Some(2.0)]),
+            vec![Some(0.0), None, None]
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use polars::prelude::*;
 use polars_arrow::bitmap::MutableBitmap;
 use pyo3_polars::derive::polars_expr;
-
 use serde::Deserialize;
 
+use crate::stats::{adf_statistic, linear_fit};
+
 pub fn get_weights_ffd(d: f64, threshold: f64) -> Vec<f64> {
     let mut w = vec![1.];
     let mut k = 1.0;
@@ -19,41 +20,290 @@ pub fn get_weights_ffd(d: f64, threshold: f64) -> Vec<f64> {
     w
 }
 
+/// Get the fractional differentiation weights of the `n` most recent lags,
+/// ordered from the oldest lag to the current value's weight of 1.
+pub fn get_weights_ffd_n(d: f64, n: usize) -> Vec<f64> {
+    let mut w = vec![1.];
+    for k in 1..n {
+        let k = k as f64;
+        w.push(-w.last().unwrap() / k * (d - k + 1.0));
+    }
+    w.reverse();
+    w
+}
+
 fn dot_product(a: &[f64], b: &[f64]) -> f64 {
     a.iter().zip(b.iter()).map(|(a, b)| a * b).sum()
 }
 
-#[derive(Deserialize)]
-struct FracDiffKwargs {
+/// The stationarity and memory of a series fractionally differentiated with a
+/// given `d`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FracDiffDiagnostics {
     d: f64,
-    threshold: f64,
+    adf_stat: Option<f64>,
+    corr_with_original: Option<f64>,
 }
 
-#[polars_expr(output_type=Float64)]
-fn frac_diff(inputs: &[Series], kwargs: FracDiffKwargs) -> PolarsResult<Series> {
-    let prices = inputs[0].f64().unwrap().to_vec_null_aware();
-    let prices = if prices.is_left() {
-        prices.left().unwrap()
+/// Fractionally differentiate `prices` with `weights` and measure the ADF
+/// statistic of the result, with `max_lag` lagged differences, and its
+/// correlation with the prices over the same observations.
+///
+/// Reference: Advances in Financial Machine Learning, Chapter 5.6
+fn frac_diff_diagnostics(
+    prices: &[f64],
+    d: f64,
+    weights: &[f64],
+    max_lag: usize,
+) -> FracDiffDiagnostics {
+    let diffed: Vec<f64> = prices
+        .windows(weights.len())
+        .map(|window| dot_product(window, weights))
+        .collect();
+    let original = &prices[prices.len().min(weights.len() - 1)..];
+    let corr_with_original = linear_fit(original, &diffed)
+        .filter(|_| diffed.len() > 1)
+        .map(|fit| fit.beta.signum() * fit.r_squared.sqrt());
+    FracDiffDiagnostics {
+        d,
+        adf_stat: adf_statistic(&diffed, max_lag),
+        corr_with_original,
+    }
+}
+
+/// Count the nulls at the head of `values`, which must be the only nulls.
+///
+/// Without `skip_leading_nulls` there must be no nulls at all. Returns `None` if
+/// a null is not allowed.
+fn null_head_len(values: &[Option<f64>], skip_leading_nulls: bool) -> Option<usize> {
+    let head = if skip_leading_nulls {
+        values.iter().take_while(|value| value.is_none()).count()
     } else {
-        return Err(PolarsError::InvalidOperation("Null price found".into()));
+        0
     };
-    let weights = get_weights_ffd(kwargs.d, kwargs.threshold);
+    values[head..].iter().all(Option::is_some).then_some(head)
+}
+
+#[derive(Deserialize)]
+struct FracDiffKwargs {
+    d: f64,
+    threshold: Option<f64>,
+    window: Option<usize>,
+    #[serde(default)]
+    skip_leading_nulls: bool,
+}
+
+/// Get the weights of `d` truncated at a fixed `window`, or else at `threshold`.
+fn truncated_weights(
+    d: f64,
+    threshold: Option<f64>,
+    window: Option<usize>,
+) -> PolarsResult<Vec<f64>> {
+    match (window, threshold) {
+        (Some(0), _) => Err(PolarsError::ComputeError("window must be positive".into())),
+        (Some(window), _) => Ok(get_weights_ffd_n(d, window)),
+        (None, Some(threshold)) => Ok(get_weights_ffd(d, threshold)),
+        (None, None) => Err(PolarsError::ComputeError(
+            "Either threshold or window must be set".into(),
+        )),
+    }
+}
+
+impl FracDiffKwargs {
+    fn weights(&self) -> PolarsResult<Vec<f64>> {
+        truncated_weights(self.d, self.threshold, self.window)
+    }
+}
+
+/// Apply the weights to each window of prices. The first `weights.len() - 1`
+/// values, which don't have a full window, are null.
+///
+/// With `skip_leading_nulls`, nulls at the head of the prices, e.g. from the
+/// warm-up of another transform, are skipped and the weights are applied to the
+/// tail after them, so the head and the first incomplete windows of the tail are
+/// null.
+fn apply_weights(
+    name: PlSmallStr,
+    prices: &Series,
+    weights: &[f64],
+    skip_leading_nulls: bool,
+) -> PolarsResult<Series> {
+    let values: Vec<Option<f64>> = prices.f64()?.iter().collect();
+    let head = null_head_len(&values, skip_leading_nulls)
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let prices: Vec<f64> = values[head..].iter().flatten().copied().collect();
     let n_weights = weights.len();
-    let mut outputs: Vec<f64> = Vec::with_capacity(prices.len());
-    let mut validity_mask = MutableBitmap::with_capacity(prices.len());
-    validity_mask.extend_constant(prices.len(), true);
-    for i in 0..prices.len() {
-        if i < (n_weights - 1) {
+    let mut outputs: Vec<f64> = Vec::with_capacity(values.len());
+    let mut validity_mask = MutableBitmap::with_capacity(values.len());
+    validity_mask.extend_constant(values.len(), true);
+    for i in 0..values.len() {
+        if i < head + n_weights - 1 {
             outputs.push(0.0);
             validity_mask.set(i, false);
         } else {
-            let window = &prices[i + 1 - n_weights..i + 1];
-            let output = dot_product(window, &weights);
+            let end = i + 1 - head;
+            let window = &prices[end - n_weights..end];
+            let output = dot_product(window, weights);
             outputs.push(output);
         }
     }
+    Ok(Float64Chunked::from_vec_validity(name, outputs, validity_mask.into()).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn frac_diff(inputs: &[Series], kwargs: FracDiffKwargs) -> PolarsResult<Series> {
+    let weights = kwargs.weights()?;
+    apply_weights(
+        "frac_diff".into(),
+        &inputs[0],
+        &weights,
+        kwargs.skip_leading_nulls,
+    )
+}
+
+fn frac_diff_multi_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(
+            input_fields
+                .iter()
+                .map(|field| Field::new(field.name().clone(), DataType::Float64))
+                .collect(),
+        ),
+    ))
+}
+
+#[polars_expr(output_type_func=frac_diff_multi_struct)]
+fn frac_diff_multi(inputs: &[Series], kwargs: FracDiffKwargs) -> PolarsResult<Series> {
+    let weights = kwargs.weights()?;
+    let fields = inputs
+        .iter()
+        .map(|prices| {
+            apply_weights(
+                prices.name().clone(),
+                prices,
+                &weights,
+                kwargs.skip_leading_nulls,
+            )
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
     Ok(
-        Float64Chunked::from_vec_validity("frac_diff".into(), outputs, validity_mask.into())
+        StructChunked::from_series(inputs[0].name().clone(), inputs[0].len(), fields.iter())?
             .into_series(),
     )
 }
+
+#[derive(Deserialize)]
+struct FracDiffAnalysisKwargs {
+    ds: Vec<f64>,
+    threshold: Option<f64>,
+    window: Option<usize>,
+    max_lag: usize,
+}
+
+fn frac_diff_analysis_list(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::List(Box::new(DataType::Struct(vec![
+            Field::new("d".into(), DataType::Float64),
+            Field::new("adf_stat".into(), DataType::Float64),
+            Field::new("corr_with_original".into(), DataType::Float64),
+        ]))),
+    ))
+}
+
+#[polars_expr(output_type_func=frac_diff_analysis_list)]
+fn frac_diff_analysis(inputs: &[Series], kwargs: FracDiffAnalysisKwargs) -> PolarsResult<Series> {
+    let prices = inputs[0].cast(&DataType::Float64)?;
+    let prices: Vec<f64> = prices
+        .f64()?
+        .iter()
+        .collect::<Option<_>>()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let diagnostics = kwargs
+        .ds
+        .iter()
+        .map(|&d| {
+            let weights = truncated_weights(d, kwargs.threshold, kwargs.window)?;
+            Ok(frac_diff_diagnostics(&prices, d, &weights, kwargs.max_lag))
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let ds: Vec<f64> = diagnostics.iter().map(|diag| diag.d).collect();
+    let adf_stats: Vec<Option<f64>> = diagnostics.iter().map(|diag| diag.adf_stat).collect();
+    let corrs: Vec<Option<f64>> = diagnostics
+        .iter()
+        .map(|diag| diag.corr_with_original)
+        .collect();
+    let fields = [
+        Float64Chunked::from_vec("d".into(), ds).into_series(),
+        Float64Chunked::new("adf_stat".into(), &adf_stats).into_series(),
+        Float64Chunked::new("corr_with_original".into(), &corrs).into_series(),
+    ];
+    let table =
+        StructChunked::from_series("".into(), diagnostics.len(), fields.iter())?.into_series();
+    Ok(std::iter::once(Some(table))
+        .collect::<ListChunked>()
+        .with_name(inputs[0].name().clone())
+        .into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_weights_ffd_n() {
+        let weights = get_weights_ffd_n(0.5, 5);
+
+        assert_eq!(weights.len(), 5);
+        assert_eq!(weights[4], 1.0);
+        let expected = [-0.039_062_5, -0.062_5, -0.125, -0.5, 1.0];
+        for (w, e) in weights.iter().zip(expected) {
+            assert!((w - e).abs() < 1e-12);
+        }
+        // The fixed window matches the threshold window of the same length.
+        let by_threshold = get_weights_ffd(0.5, 0.05);
+        assert_eq!(get_weights_ffd_n(0.5, by_threshold.len()), by_threshold);
+    }
+
+    #[test]
+    fn test_null_head_len_three_leading_nulls() {
+        let values = [None, None, None, Some(1.0), Some(2.0)];
+
+        assert_eq!(null_head_len(&values, true), Some(3));
+        assert_eq!(null_head_len(&values, false), None);
+        assert_eq!(null_head_len(&values[3..], false), Some(0));
+        // Nulls after the head are still rejected.
+        assert_eq!(null_head_len(&[None, Some(1.0), None], true), None);
+        assert_eq!(null_head_len(&[None, None], true), Some(2));
+    }
+
+    #[test]
+    fn test_frac_diff_diagnostics_trade_memory_for_stationarity() {
+        // A random walk of xorshift uniform steps.
+        let mut seed: u64 = 42;
+        let mut price = 100.0;
+        let prices: Vec<f64> = (0..1000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                price += (seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+                price
+            })
+            .collect();
+
+        let diagnostics: Vec<FracDiffDiagnostics> = [0.0, 0.3, 0.6, 1.0]
+            .iter()
+            .map(|&d| frac_diff_diagnostics(&prices, d, &get_weights_ffd_n(d, 20), 1))
+            .collect();
+
+        // With d = 0 the series is the prices themselves.
+        assert!((diagnostics[0].corr_with_original.unwrap() - 1.0).abs() < 1e-12);
+        for pair in diagnostics.windows(2) {
+            assert!(pair[1].corr_with_original < pair[0].corr_with_original);
+            assert!(pair[1].adf_stat.unwrap() < pair[0].adf_stat.unwrap());
+        }
+        assert!(diagnostics[3].adf_stat.unwrap() < -10.0);
+    }
+}
@@ -1,64 +1,1544 @@
-// #![allow(clippy::unused_unit)]
-// use std::cmp::PartialOrd;
-
-// use polars::prelude::*;
-// use pyo3_polars::derive::polars_expr;
-// use serde::Deserialize;
-
-// fn apply_profit_taking_stop_loss<T>(
-//     index: &ChunkedArray<T>,
-//     prices: &Float64Chunked,
-//     profit_taking: &Float64Chunked,
-//     stop_loss: &Float64Chunked,
-// ) -> (Option<T>, Option<T>)
-// where
-//     T: PartialOrd + Clone,
-// {
-//     let returns: Vec<f64> = prices
-//         .iter()
-//         .map(|x| x.unwrap() / prices.get(0).unwrap() - 1.0)
-//         .collect();
-//     // Get the minimum index where profit take is greater than returns
-//     let profit_taking_index = returns
-//         .iter()
-//         .zip(profit_taking.iter())
-//         .position(|(&ret, &pt)| ret >= pt);
-//     let stop_loss_index = returns
-//         .iter()
-//         .zip(stop_loss.iter())
-//         .position(|(&ret, &sl)| ret <= sl);
-
-//     match (profit_taking_index, stop_loss_index) {
-//         (Some(pt), Some(sl)) => {
-//             return (
-//                 Some(index.get(pt).unwrap().clone()),
-//                 Some(index.get(sl).unwrap().clone()),
-//             )
-//         },
-//         (Some(pt), None) => return (Some(index.get(pt).unwrap().clone()), None),
-//         (None, Some(sl)) => return (None, Some(index.get(sl).unwrap().clone())),
-//         (None, None) => return (None, None),
-//     }
-// }
-
-// fn barrier_touch_struct(input_fields: &[Field]) -> PolarsResult<Field> {
-//     let dtype = input_fields[0].data_type();
-//     Ok(Field::new(
-//         input_fields[0].name(),
-//         DataType::Struct(vec![
-//             Field::new("barrier_touch_start", dtype.clone()),
-//             Field::new("barrier_touch_profit_take", dtype.clone()),
-//             Field::new("barrier_touch_stop_loss", dtype.clone()),
-//             Field::new("barrier_touch_vertical_barrier", dtype.clone()),
-//         ]),
-//     ))
-// }
-
-// #[polars_expr(output_type_func=barrier_touch_struct)]
-// fn get_barrier_touches(inputs: &[Series]) -> PolarsResult<Series> {
-//     let targets = inputs[0].datetime()?; // Not sure what to do with this type yet.
-//     let prices = inputs[1].f64()?;
-//     let profit_taking = inputs[2].f64()?;
-//     let stop_loss = inputs[3].f64()?;
-//     let (pt, sl) = apply_profit_taking_stop_loss(targets, prices, profit_taking, stop_loss);
-// }
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use rayon::prelude::*;
+use serde::Deserialize;
+
+/// The outcome of labeling a single event from its price path.
+///
+/// `barrier_touch` is the position within the price path (0 being the event
+/// itself) at which the first barrier was touched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Label {
+    ret: f64,
+    label: i32,
+    barrier_touch: usize,
+}
+
+/// The `(ret, label, barrier_touch, holding_period, path_range)` of a labeled
+/// row.
+type LabeledRow = (f64, i32, i64, i64, (usize, usize));
+
+/// Column-wise outputs of `calculate_labels`, one entry per input row.
+#[derive(Debug, Default, PartialEq)]
+struct TripleBarrierLabels {
+    rets: Vec<Option<f64>>,
+    labels: Vec<Option<i32>>,
+    barrier_touches: Vec<Option<i64>>,
+    /// The number of bars from the event to its barrier touch.
+    holding_periods: Vec<Option<i64>>,
+    /// The inclusive `(start, end)` positions of each row's price path.
+    path_ranges: Vec<(usize, usize)>,
+}
+
+impl TripleBarrierLabels {
+    /// Collect the outputs of each row.
+    fn from_rows(rows: &[LabeledRow]) -> Self {
+        Self {
+            rets: rows.iter().map(|row| Some(row.0)).collect(),
+            labels: rows.iter().map(|row| Some(row.1)).collect(),
+            barrier_touches: rows.iter().map(|row| Some(row.2)).collect(),
+            holding_periods: rows.iter().map(|row| Some(row.3)).collect(),
+            path_ranges: rows.iter().map(|row| row.4).collect(),
+        }
+    }
+}
+
+/// Which barrier wins when profit taking and stop loss are first touched by the
+/// same observation, as the true order within the bar is unknown.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum TieBreak {
+    /// Assume the stop loss was touched first.
+    #[default]
+    Conservative,
+    /// Assume the profit taking barrier was touched first.
+    Optimistic,
+}
+
+impl TieBreak {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "conservative" => Some(Self::Conservative),
+            "optimistic" => Some(Self::Optimistic),
+            _ => None,
+        }
+    }
+}
+
+/// How the returns of a price path relative to its first price are measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum ReturnType {
+    /// `p / p0 - 1`
+    #[default]
+    Simple,
+    /// `ln(p / p0)`
+    Log,
+}
+
+impl ReturnType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "simple" => Some(Self::Simple),
+            "log" => Some(Self::Log),
+            _ => None,
+        }
+    }
+
+    fn path_return(self, price: f64, start: f64) -> f64 {
+        match self {
+            Self::Simple => price / start - 1.0,
+            Self::Log => (price / start).ln(),
+        }
+    }
+}
+
+/// Options controlling how `calculate_labels` finds the end of each price path
+/// and reports barrier touches.
+#[derive(Debug, Clone, Copy, Default)]
+struct LabelOptions {
+    /// Report the barrier touch as a row position rather than an index value.
+    positional_barrier_touch: bool,
+    /// End the price path at the first index value at or after the vertical
+    /// barrier, rather than requiring the barrier to exist in the index.
+    snap_vertical_barrier: bool,
+    tie_break: TieBreak,
+    /// Add each row's price path to the output.
+    return_path: bool,
+    /// The returns compared against the horizontal barriers.
+    return_type: ReturnType,
+    /// The drop from the path's highest return that is a stop loss.
+    trailing_stop: Option<f64>,
+}
+
+/// Calculate the return of every price in a path relative to the first price in
+/// the path.
+///
+/// These are cumulative returns since the event, which is what the horizontal
+/// barriers are compared against, rather than step over step returns. For
+/// example a path of `[1.0, 2.0, 3.0]` returns `[0.0, 1.0, 2.0]`, not
+/// `[0.0, 1.0, 0.5]` as simple returns. An empty path has no returns.
+fn calculate_price_path_return(prices: &[f64], return_type: ReturnType) -> Vec<f64> {
+    let Some(&start) = prices.first() else {
+        return Vec::new();
+    };
+    prices
+        .iter()
+        .map(|&p| return_type.path_return(p, start))
+        .collect()
+}
+
+/// Get the inclusive `(start, end)` positions of the price path for the event at
+/// `start_idx`.
+///
+/// The path ends at the position of the vertical barrier within the index, or at
+/// the end of the data when there is no vertical barrier. When `snap` is true the
+/// path instead ends at the first index value at or after the vertical barrier,
+/// or at the end of the data when the barrier is beyond the last index value.
+///
+/// The index must be sorted, as the barrier is found by binary search.
+fn get_slice_range(
+    index: &[i64],
+    start_idx: usize,
+    vertical_barrier: Option<i64>,
+    snap: bool,
+) -> Result<(usize, usize), String> {
+    let Some(vb) = vertical_barrier else {
+        return Ok((start_idx, index.len() - 1));
+    };
+    let end = start_idx + index[start_idx..].partition_point(|&r| r < vb);
+    if snap {
+        Ok((start_idx, end.min(index.len() - 1)))
+    } else if index.get(end) == Some(&vb) {
+        Ok((start_idx, end))
+    } else {
+        Err(format!("Vertical barrier {vb} not found in index"))
+    }
+}
+
+/// Label a single price path by whichever barrier is touched first.
+///
+/// `profit_taking` and `stop_loss` are return levels, so the stop loss is
+/// expected to be negative. A missing barrier is never touched. If neither
+/// horizontal barrier is touched the vertical barrier is, and the label is 0. If
+/// both are first touched by the same return, `tie_break` picks the winner. An
+/// empty path touches nothing and is a zero label with a zero return.
+///
+/// A `trailing_stop` is touched when the return falls that far below the highest
+/// return of the path so far, and is labeled like the stop loss.
+fn get_label(
+    returns: &[f64],
+    profit_taking: Option<f64>,
+    stop_loss: Option<f64>,
+    trailing_stop: Option<f64>,
+    tie_break: TieBreak,
+) -> Label {
+    let pt_touch_idx = profit_taking.and_then(|pt| returns.iter().position(|&r| r >= pt));
+    let fixed_sl_touch_idx = stop_loss.and_then(|sl| returns.iter().position(|&r| r <= sl));
+    let trailing_touch_idx = trailing_stop.and_then(|ts| {
+        let mut peak = f64::NEG_INFINITY;
+        returns.iter().position(|&r| {
+            peak = peak.max(r);
+            r <= peak - ts
+        })
+    });
+    let sl_touch_idx = [fixed_sl_touch_idx, trailing_touch_idx]
+        .into_iter()
+        .flatten()
+        .min();
+    let touch = |idx: usize, label: i32| Label {
+        ret: returns[idx],
+        label,
+        barrier_touch: idx,
+    };
+
+    match (pt_touch_idx, sl_touch_idx) {
+        (Some(pt), Some(sl)) if pt == sl => match tie_break {
+            TieBreak::Conservative => touch(sl, -1),
+            TieBreak::Optimistic => touch(pt, 1),
+        },
+        (Some(pt), Some(sl)) => {
+            if pt < sl {
+                touch(pt, 1)
+            } else {
+                touch(sl, -1)
+            }
+        },
+        (Some(pt), None) => touch(pt, 1),
+        (None, Some(sl)) => touch(sl, -1),
+        (None, None) if returns.is_empty() => Label {
+            ret: 0.0,
+            label: 0,
+            barrier_touch: 0,
+        },
+        (None, None) => touch(returns.len() - 1, 0),
+    }
+}
+
+/// Label a price path in a single forward scan, stopping at the first barrier
+/// touch.
+///
+/// This gives the same label as `get_label` on the path's returns, without
+/// computing the returns past the touch, which matters for paths that run to the
+/// end of the data.
+fn scan_label(
+    prices: &[f64],
+    profit_taking: Option<f64>,
+    stop_loss: Option<f64>,
+    options: LabelOptions,
+) -> Label {
+    let Some(&start) = prices.first() else {
+        return get_label(
+            &[],
+            profit_taking,
+            stop_loss,
+            options.trailing_stop,
+            options.tie_break,
+        );
+    };
+    let mut peak = f64::NEG_INFINITY;
+    for (idx, &price) in prices.iter().enumerate() {
+        let ret = options.return_type.path_return(price, start);
+        peak = peak.max(ret);
+        let pt_touched = profit_taking.is_some_and(|pt| ret >= pt);
+        let sl_touched = stop_loss.is_some_and(|sl| ret <= sl)
+            || options.trailing_stop.is_some_and(|ts| ret <= peak - ts);
+        let label = match (pt_touched, sl_touched) {
+            (true, true) => match options.tie_break {
+                TieBreak::Conservative => -1,
+                TieBreak::Optimistic => 1,
+            },
+            (true, false) => 1,
+            (false, true) => -1,
+            (false, false) => continue,
+        };
+        return Label {
+            ret,
+            label,
+            barrier_touch: idx,
+        };
+    }
+    Label {
+        ret: options
+            .return_type
+            .path_return(prices[prices.len() - 1], start),
+        label: 0,
+        barrier_touch: prices.len() - 1,
+    }
+}
+
+/// Calculate the meta-labels for a set of realized returns and bet sides.
+///
+/// The meta-label is 1 when the bet on `side` was profitable and 0 otherwise, so
+/// a secondary model can learn whether to act on the primary model's side.
+fn get_meta_labels(rets: &[Option<f64>], sides: &[Option<f64>]) -> Vec<Option<i32>> {
+    rets.iter()
+        .zip(sides)
+        .map(|(ret, side)| ret.zip(*side).map(|(r, s)| i32::from(r * s > 0.0)))
+        .collect()
+}
+
+/// Label the event at row `i`, returning its return, label, barrier touch,
+/// holding period and price path range.
+///
+/// When `options.positional_barrier_touch` is true the barrier touch is reported
+/// as the row position of the touch, otherwise it is the value of `index` at
+/// that row.
+fn label_row(
+    index: &[i64],
+    prices: &[f64],
+    profit_taking: &[Option<f64>],
+    stop_loss: &[Option<f64>],
+    vertical_barriers: &[Option<i64>],
+    options: LabelOptions,
+    i: usize,
+) -> Result<LabeledRow, String> {
+    let (start, end) = get_slice_range(
+        index,
+        i,
+        vertical_barriers[i],
+        options.snap_vertical_barrier,
+    )?;
+    let returns = calculate_price_path_return(&prices[start..=end], options.return_type);
+    let label = get_label(
+        &returns,
+        profit_taking[i],
+        stop_loss[i],
+        options.trailing_stop,
+        options.tie_break,
+    );
+    Ok(labeled_row(index, label, (start, end), options))
+}
+
+/// Label the event at row `i` when it has no vertical barrier, so its price path
+/// runs to the end of the data, by scanning forward to the first barrier touch.
+fn label_unbounded_row(
+    index: &[i64],
+    prices: &[f64],
+    profit_taking: &[Option<f64>],
+    stop_loss: &[Option<f64>],
+    options: LabelOptions,
+    i: usize,
+) -> LabeledRow {
+    let label = scan_label(&prices[i..], profit_taking[i], stop_loss[i], options);
+    labeled_row(index, label, (i, prices.len() - 1), options)
+}
+
+/// Convert the label of the path at `range` into a row of `calculate_labels`.
+fn labeled_row(
+    index: &[i64],
+    label: Label,
+    range: (usize, usize),
+    options: LabelOptions,
+) -> LabeledRow {
+    let barrier_touch = range.0 + label.barrier_touch;
+    let barrier_touch = if options.positional_barrier_touch {
+        barrier_touch as i64
+    } else {
+        index[barrier_touch]
+    };
+    (
+        label.ret,
+        label.label,
+        barrier_touch,
+        label.barrier_touch as i64,
+        range,
+    )
+}
+
+/// Calculate the triple barrier labels for every row.
+///
+/// Each row's price path is independent, so the rows are labeled in parallel. A
+/// vertical barrier that cannot be found in the index is a `ComputeError`. When
+/// no row has a vertical barrier, each path is scanned only up to its first
+/// barrier touch.
+fn calculate_labels(
+    index: &[i64],
+    prices: &[f64],
+    profit_taking: &[Option<f64>],
+    stop_loss: &[Option<f64>],
+    vertical_barriers: &[Option<i64>],
+    options: LabelOptions,
+) -> PolarsResult<TripleBarrierLabels> {
+    if vertical_barriers.iter().all(Option::is_none) {
+        let rows: Vec<_> = (0..prices.len())
+            .into_par_iter()
+            .map(|i| label_unbounded_row(index, prices, profit_taking, stop_loss, options, i))
+            .collect();
+        return Ok(TripleBarrierLabels::from_rows(&rows));
+    }
+    let rows = (0..prices.len())
+        .into_par_iter()
+        .map(|i| {
+            label_row(
+                index,
+                prices,
+                profit_taking,
+                stop_loss,
+                vertical_barriers,
+                options,
+                i,
+            )
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| PolarsError::ComputeError(e.into()))?;
+    Ok(TripleBarrierLabels::from_rows(&rows))
+}
+
+/// Check whether each row's vertical barrier can end a full price path.
+///
+/// A barrier must exist in the remaining index, or when `snap` is true must not
+/// be beyond the last index value. Rows without a vertical barrier run to the end
+/// of the data and are always valid.
+fn valid_vertical_barriers(
+    index: &[i64],
+    vertical_barriers: &[Option<i64>],
+    snap: bool,
+) -> Vec<bool> {
+    vertical_barriers
+        .iter()
+        .enumerate()
+        .map(|(i, vb)| match vb {
+            Some(vb) if snap => index.last().is_some_and(|last| vb <= last),
+            Some(_) => get_slice_range(index, i, *vb, false).is_ok(),
+            None => true,
+        })
+        .collect()
+}
+
+/// Scale a per-row volatility estimate into profit taking and stop loss return
+/// levels.
+///
+/// A missing multiplier disables that barrier for every row and a missing
+/// volatility disables both barriers for that row.
+fn barriers_from_volatility(
+    volatility: &[Option<f64>],
+    pt_mult: Option<f64>,
+    sl_mult: Option<f64>,
+) -> (Vec<Option<f64>>, Vec<Option<f64>>) {
+    let scale = |mult: Option<f64>, sign: f64| -> Vec<Option<f64>> {
+        volatility
+            .iter()
+            .map(|v| v.zip(mult).map(|(v, m)| sign * v * m))
+            .collect()
+    };
+    (scale(pt_mult, 1.0), scale(sl_mult, -1.0))
+}
+
+/// The most significant linear trend found ahead of an observation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TrendScan {
+    t_value: f64,
+    label: i32,
+    horizon: usize,
+}
+
+/// Calculate the t-value of the slope of an OLS fit of `values` on a linear time
+/// trend.
+///
+/// A perfect fit gives an infinite t-value and a flat perfect fit gives `None`.
+fn trend_t_value(values: &[f64]) -> Option<f64> {
+    let n = values.len() as f64;
+    let x_mean = (n - 1.0) / 2.0;
+    let y_mean = values.iter().sum::<f64>() / n;
+    let (sxx, sxy) = values
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(sxx, sxy), (x, y)| {
+            let dx = x as f64 - x_mean;
+            (sxx + dx * dx, sxy + dx * (y - y_mean))
+        });
+    let beta = sxy / sxx;
+    let alpha = y_mean - beta * x_mean;
+    let ssr: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(x, y)| (y - alpha - beta * x as f64).powi(2))
+        .sum();
+    let se = (ssr / (n - 2.0) / sxx).sqrt();
+    let t_value = beta / se;
+    (!t_value.is_nan()).then_some(t_value)
+}
+
+/// Label each observation by the sign of the most significant trend over the
+/// next `min_horizon..=max_horizon` observations (including itself).
+///
+/// Observations with fewer than `min_horizon` observations remaining are `None`.
+fn trend_scan_labels(
+    prices: &[f64],
+    min_horizon: usize,
+    max_horizon: usize,
+) -> Vec<Option<TrendScan>> {
+    (0..prices.len())
+        .map(|i| {
+            (min_horizon..=max_horizon)
+                .take_while(|h| i + h <= prices.len())
+                .filter_map(|h| {
+                    trend_t_value(&prices[i..i + h]).map(|t_value| TrendScan {
+                        t_value,
+                        label: t_value.signum() as i32,
+                        horizon: h,
+                    })
+                })
+                .max_by(|a, b| a.t_value.abs().total_cmp(&b.t_value.abs()))
+        })
+        .collect()
+}
+
+/// Label each price by its return `horizon` observations ahead.
+///
+/// The label is 1 if the return is above `threshold`, -1 if it is below
+/// `-threshold` and 0 otherwise. Prices without a full horizon ahead are `None`.
+fn fixed_horizon_labels(prices: &[Option<f64>], horizon: usize, threshold: f64) -> Vec<Option<i8>> {
+    (0..prices.len())
+        .map(|i| {
+            let start = prices[i]?;
+            let end = (*prices.get(i + horizon)?)?;
+            let ret = end / start - 1.0;
+            Some(if ret > threshold {
+                1
+            } else if ret < -threshold {
+                -1
+            } else {
+                0
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct TripleBarrierLabelKwargs {
+    #[serde(default)]
+    positional_barrier_touch: bool,
+    #[serde(default)]
+    snap_vertical_barrier: bool,
+    #[serde(default = "default_tie_break")]
+    tie_break: String,
+    #[serde(default)]
+    return_path: bool,
+    #[serde(default = "default_return_type")]
+    return_type: String,
+    trailing_stop: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct TripleBarrierVolKwargs {
+    pt_mult: Option<f64>,
+    sl_mult: Option<f64>,
+    #[serde(default)]
+    positional_barrier_touch: bool,
+    #[serde(default)]
+    snap_vertical_barrier: bool,
+    #[serde(default = "default_tie_break")]
+    tie_break: String,
+    #[serde(default)]
+    return_path: bool,
+    #[serde(default = "default_return_type")]
+    return_type: String,
+    trailing_stop: Option<f64>,
+}
+
+fn default_tie_break() -> String {
+    "conservative".to_string()
+}
+
+fn default_return_type() -> String {
+    "simple".to_string()
+}
+
+fn label_options(
+    positional_barrier_touch: bool,
+    snap_vertical_barrier: bool,
+    tie_break: &str,
+    return_path: bool,
+    return_type: &str,
+    trailing_stop: Option<f64>,
+) -> PolarsResult<LabelOptions> {
+    if trailing_stop.is_some_and(|ts| ts <= 0.0) {
+        return Err(PolarsError::ComputeError(
+            "trailing_stop must be positive".into(),
+        ));
+    }
+    let tie_break = TieBreak::from_name(tie_break).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown tie_break: {tie_break}").into())
+    })?;
+    let return_type = ReturnType::from_name(return_type).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown return_type: {return_type}").into())
+    })?;
+    Ok(LabelOptions {
+        positional_barrier_touch,
+        snap_vertical_barrier,
+        tie_break,
+        return_path,
+        return_type,
+        trailing_stop,
+    })
+}
+
+fn label_struct(
+    input_fields: &[Field],
+    positional_barrier_touch: bool,
+    has_side: bool,
+    return_path: bool,
+) -> PolarsResult<Field> {
+    let barrier_touch_dtype = if positional_barrier_touch {
+        DataType::Int64
+    } else {
+        input_fields[0].dtype().clone()
+    };
+    let mut fields = vec![
+        Field::new("ret".into(), DataType::Float64),
+        Field::new("label".into(), DataType::Int32),
+        Field::new("barrier_touch".into(), barrier_touch_dtype),
+        Field::new("holding_period".into(), DataType::Int64),
+    ];
+    if has_side {
+        fields.push(Field::new("meta_label".into(), DataType::Int32));
+    }
+    if return_path {
+        fields.push(Field::new(
+            "price_path".into(),
+            DataType::List(Box::new(DataType::Float64)),
+        ));
+    }
+    Ok(Field::new(
+        input_fields[1].name().clone(),
+        DataType::Struct(fields),
+    ))
+}
+
+fn triple_barrier_label_struct(
+    input_fields: &[Field],
+    kwargs: TripleBarrierLabelKwargs,
+) -> PolarsResult<Field> {
+    label_struct(
+        input_fields,
+        kwargs.positional_barrier_touch,
+        input_fields.len() > 5,
+        kwargs.return_path,
+    )
+}
+
+fn triple_barrier_vol_struct(
+    input_fields: &[Field],
+    kwargs: TripleBarrierVolKwargs,
+) -> PolarsResult<Field> {
+    label_struct(
+        input_fields,
+        kwargs.positional_barrier_touch,
+        false,
+        kwargs.return_path,
+    )
+}
+
+/// Cast a series to its physical `Int64` representation, broadcasting a unit
+/// length series to `len`.
+fn to_i64_values(s: &Series, len: usize) -> PolarsResult<Vec<Option<i64>>> {
+    let s = s.to_physical_repr().cast(&DataType::Int64)?;
+    let ca = s.i64()?;
+    if ca.len() == 1 {
+        Ok(vec![ca.get(0); len])
+    } else {
+        Ok(ca.iter().collect())
+    }
+}
+
+fn to_f64_values(s: &Series, len: usize) -> PolarsResult<Vec<Option<f64>>> {
+    let s = s.cast(&DataType::Float64)?;
+    let ca = s.f64()?;
+    if ca.len() == 1 {
+        Ok(vec![ca.get(0); len])
+    } else {
+        Ok(ca.iter().collect())
+    }
+}
+
+/// Label every row of `prices` and collect the results into the output struct.
+///
+/// When `side` is given the struct also contains the meta-labels, and when
+/// `options.return_path` is true it also contains each row's price path.
+fn label_series(
+    index: &Series,
+    prices: &Series,
+    profit_taking: &[Option<f64>],
+    stop_loss: &[Option<f64>],
+    vertical_barriers: &Series,
+    side: Option<&Series>,
+    options: LabelOptions,
+) -> PolarsResult<Series> {
+    let n = prices.len();
+    let index_values = index
+        .to_physical_repr()
+        .cast(&DataType::Int64)?
+        .i64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null index found".into()))?;
+    let price_values = prices
+        .cast(&DataType::Float64)?
+        .f64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let vertical_barriers = to_i64_values(vertical_barriers, n)?;
+
+    let labels = calculate_labels(
+        &index_values,
+        &price_values,
+        profit_taking,
+        stop_loss,
+        &vertical_barriers,
+        options,
+    )?;
+
+    let barrier_touch =
+        Int64Chunked::new("barrier_touch".into(), &labels.barrier_touches).into_series();
+    let barrier_touch = if options.positional_barrier_touch {
+        barrier_touch
+    } else {
+        barrier_touch.cast(index.dtype())?
+    };
+    let mut fields = vec![
+        Float64Chunked::new("ret".into(), &labels.rets).into_series(),
+        Int32Chunked::new("label".into(), &labels.labels).into_series(),
+        barrier_touch,
+        Int64Chunked::new("holding_period".into(), &labels.holding_periods).into_series(),
+    ];
+    if let Some(side) = side {
+        let sides = to_f64_values(side, n)?;
+        let meta_labels = get_meta_labels(&labels.rets, &sides);
+        fields.push(Int32Chunked::new("meta_label".into(), &meta_labels).into_series());
+    }
+    if options.return_path {
+        let mut builder = ListPrimitiveChunkedBuilder::<Float64Type>::new(
+            "price_path".into(),
+            n,
+            labels
+                .path_ranges
+                .iter()
+                .map(|(start, end)| end - start + 1)
+                .sum(),
+            DataType::Float64,
+        );
+        for &(start, end) in &labels.path_ranges {
+            builder.append_slice(&price_values[start..=end]);
+        }
+        fields.push(builder.finish().into_series());
+    }
+    Ok(StructChunked::from_series(prices.name().clone(), n, fields.iter())?.into_series())
+}
+
+#[polars_expr(output_type_func_with_kwargs=triple_barrier_label_struct)]
+fn triple_barrier_label(
+    inputs: &[Series],
+    kwargs: TripleBarrierLabelKwargs,
+) -> PolarsResult<Series> {
+    let n = inputs[1].len();
+    let profit_taking = to_f64_values(&inputs[2], n)?;
+    let stop_loss = to_f64_values(&inputs[3], n)?;
+    label_series(
+        &inputs[0],
+        &inputs[1],
+        &profit_taking,
+        &stop_loss,
+        &inputs[4],
+        inputs.get(5),
+        label_options(
+            kwargs.positional_barrier_touch,
+            kwargs.snap_vertical_barrier,
+            &kwargs.tie_break,
+            kwargs.return_path,
+            &kwargs.return_type,
+            kwargs.trailing_stop,
+        )?,
+    )
+}
+
+#[polars_expr(output_type_func_with_kwargs=triple_barrier_vol_struct)]
+fn triple_barrier_vol(inputs: &[Series], kwargs: TripleBarrierVolKwargs) -> PolarsResult<Series> {
+    let volatility = to_f64_values(&inputs[2], inputs[1].len())?;
+    let (profit_taking, stop_loss) =
+        barriers_from_volatility(&volatility, kwargs.pt_mult, kwargs.sl_mult);
+    label_series(
+        &inputs[0],
+        &inputs[1],
+        &profit_taking,
+        &stop_loss,
+        &inputs[3],
+        None,
+        label_options(
+            kwargs.positional_barrier_touch,
+            kwargs.snap_vertical_barrier,
+            &kwargs.tie_break,
+            kwargs.return_path,
+            &kwargs.return_type,
+            kwargs.trailing_stop,
+        )?,
+    )
+}
+
+#[derive(Deserialize)]
+struct LabelValidityMaskKwargs {
+    #[serde(default)]
+    snap_vertical_barrier: bool,
+}
+
+#[polars_expr(output_type=Boolean)]
+fn label_validity_mask(inputs: &[Series], kwargs: LabelValidityMaskKwargs) -> PolarsResult<Series> {
+    let index = inputs[0]
+        .to_physical_repr()
+        .cast(&DataType::Int64)?
+        .i64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null index found".into()))?;
+    let vertical_barriers = to_i64_values(&inputs[1], index.len())?;
+    let valid = valid_vertical_barriers(&index, &vertical_barriers, kwargs.snap_vertical_barrier);
+    Ok(BooleanChunked::from_slice("label_validity_mask".into(), &valid).into_series())
+}
+
+#[derive(Deserialize)]
+struct TrendScanKwargs {
+    min_horizon: usize,
+    max_horizon: usize,
+}
+
+fn trend_scan_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(vec![
+            Field::new("t_value".into(), DataType::Float64),
+            Field::new("label".into(), DataType::Int32),
+            Field::new("horizon".into(), DataType::Int64),
+        ]),
+    ))
+}
+
+#[polars_expr(output_type_func=trend_scan_struct)]
+fn trend_scan(inputs: &[Series], kwargs: TrendScanKwargs) -> PolarsResult<Series> {
+    if kwargs.min_horizon < 3 || kwargs.max_horizon < kwargs.min_horizon {
+        return Err(PolarsError::ComputeError(
+            "Horizons must satisfy 3 <= min_horizon <= max_horizon".into(),
+        ));
+    }
+    let prices = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let scans = trend_scan_labels(&prices, kwargs.min_horizon, kwargs.max_horizon);
+
+    let t_values: Vec<Option<f64>> = scans.iter().map(|s| s.map(|s| s.t_value)).collect();
+    let labels: Vec<Option<i32>> = scans.iter().map(|s| s.map(|s| s.label)).collect();
+    let horizons: Vec<Option<i64>> = scans.iter().map(|s| s.map(|s| s.horizon as i64)).collect();
+    let fields = [
+        Float64Chunked::new("t_value".into(), &t_values).into_series(),
+        Int32Chunked::new("label".into(), &labels).into_series(),
+        Int64Chunked::new("horizon".into(), &horizons).into_series(),
+    ];
+    Ok(
+        StructChunked::from_series(inputs[0].name().clone(), prices.len(), fields.iter())?
+            .into_series(),
+    )
+}
+
+#[derive(Deserialize)]
+struct FixedHorizonLabelKwargs {
+    horizon: usize,
+    threshold: f64,
+}
+
+#[polars_expr(output_type=Int8)]
+fn fixed_horizon_label(inputs: &[Series], kwargs: FixedHorizonLabelKwargs) -> PolarsResult<Series> {
+    let prices = to_f64_values(&inputs[0], inputs[0].len())?;
+    let labels = fixed_horizon_labels(&prices, kwargs.horizon, kwargs.threshold);
+    Ok(Int8Chunked::new("fixed_horizon_label".into(), &labels).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mixed_barrier_labels(positional_barrier_touch: bool) -> TripleBarrierLabels {
+        let index = vec![100, 101, 102, 103, 104, 105];
+        let prices = vec![10.0, 10.5, 11.5, 9.0, 9.5, 10.0];
+        let profit_taking = vec![Some(0.1); 6];
+        let stop_loss = vec![Some(-0.1); 6];
+        let vertical_barriers = vec![Some(103), Some(102), None, None, Some(105), None];
+        calculate_labels(
+            &index,
+            &prices,
+            &profit_taking,
+            &stop_loss,
+            &vertical_barriers,
+            LabelOptions {
+                positional_barrier_touch,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_calculate_price_path_return() {
+        let returns = calculate_price_path_return(&[1.0, 2.0, 3.0], ReturnType::Simple);
+        assert_eq!(returns, vec![0.0, 1.0, 2.0]);
+        // A round trip back to the start price is a zero return.
+        let returns = calculate_price_path_return(&[2.0, 1.0, 3.0, 2.0], ReturnType::Simple);
+        assert_eq!(returns, vec![0.0, -0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_calculate_labels_log_returns_near_barrier() {
+        // A 5.2% simple return is a 5.07% log return, and a -5% simple return a
+        // -5.13% log return.
+        let index = vec![0, 1, 2];
+        let prices = vec![100.0, 105.2, 95.0];
+        let label = |return_type, profit_taking, stop_loss| {
+            let options = LabelOptions {
+                return_type,
+                ..Default::default()
+            };
+            calculate_labels(
+                &index,
+                &prices,
+                &[Some(profit_taking); 3],
+                &[Some(stop_loss); 3],
+                &[Some(2); 3],
+                options,
+            )
+            .unwrap()
+            .labels[0]
+        };
+
+        assert_eq!(label(ReturnType::Simple, 0.051, -0.2), Some(1));
+        assert_eq!(label(ReturnType::Log, 0.051, -0.2), Some(0));
+        assert_eq!(label(ReturnType::Simple, 0.2, -0.051), Some(0));
+        assert_eq!(label(ReturnType::Log, 0.2, -0.051), Some(-1));
+        let returns = calculate_price_path_return(&prices, ReturnType::Log);
+        assert_eq!(returns[0], 0.0);
+        assert!((returns[1] - 1.052_f64.ln()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_get_label_trailing_stop() {
+        // The price runs up 8% then retraces to 3%, never reaching the fixed
+        // barriers.
+        let returns = [0.0, 0.04, 0.08, 0.06, 0.03, 0.05];
+
+        assert_eq!(
+            get_label(
+                &returns,
+                Some(0.1),
+                Some(-0.1),
+                None,
+                TieBreak::Conservative
+            ),
+            Label {
+                ret: 0.05,
+                label: 0,
+                barrier_touch: 5
+            }
+        );
+        // A 4% drop from the 8% peak stops out at 3%.
+        assert_eq!(
+            get_label(
+                &returns,
+                Some(0.1),
+                Some(-0.1),
+                Some(0.04),
+                TieBreak::Conservative
+            ),
+            Label {
+                ret: 0.03,
+                label: -1,
+                barrier_touch: 4
+            }
+        );
+        // The fixed stop loss still applies when it is touched first.
+        assert_eq!(
+            get_label(
+                &[0.0, -0.03, 0.05, 0.0],
+                None,
+                Some(-0.02),
+                Some(0.04),
+                TieBreak::Conservative
+            )
+            .barrier_touch,
+            1
+        );
+    }
+
+    #[test]
+    fn test_get_slice_range() {
+        let index = vec![1, 2, 3, 4];
+        assert_eq!(get_slice_range(&index, 1, Some(3), false), Ok((1, 2)));
+        assert_eq!(get_slice_range(&index, 1, None, false), Ok((1, 3)));
+        assert_eq!(
+            get_slice_range(&index, 1, Some(5), false),
+            Err("Vertical barrier 5 not found in index".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_slice_range_matches_linear_scan() {
+        let linear = |index: &[i64], start_idx: usize, vb: i64| {
+            index[start_idx..]
+                .iter()
+                .position(|&r| r == vb)
+                .map(|offset| (start_idx, start_idx + offset))
+        };
+        // Repeated index values resolve to the first match, as a linear scan does.
+        let index = vec![100, 101, 102, 102, 103, 104, 105];
+        for start_idx in 0..index.len() {
+            for vb in 99..=106 {
+                assert_eq!(
+                    get_slice_range(&index, start_idx, Some(vb), false).ok(),
+                    linear(&index, start_idx, vb)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_slice_range_snaps_between_index_values() {
+        let index = vec![10, 20, 30, 40];
+        assert_eq!(get_slice_range(&index, 0, Some(25), true), Ok((0, 2)));
+        assert_eq!(get_slice_range(&index, 0, Some(30), true), Ok((0, 2)));
+        assert_eq!(get_slice_range(&index, 2, Some(15), true), Ok((2, 2)));
+        assert_eq!(get_slice_range(&index, 1, Some(45), true), Ok((1, 3)));
+        assert!(get_slice_range(&index, 0, Some(25), false).is_err());
+    }
+
+    #[test]
+    fn test_calculate_labels_snapped_vertical_barrier() {
+        // Irregular bars with a barrier 15 units after each event, which snaps to
+        // the next bar. The first event only touches profit taking on that bar.
+        let index = vec![0, 10, 30, 35, 60];
+        let prices = vec![10.0, 10.5, 11.5, 9.0, 9.5];
+        let vertical_barriers: Vec<Option<i64>> = index.iter().map(|i| Some(i + 15)).collect();
+
+        let result = calculate_labels(
+            &index,
+            &prices,
+            &[Some(0.1); 5],
+            &[Some(-0.1); 5],
+            &vertical_barriers,
+            LabelOptions {
+                positional_barrier_touch: true,
+                snap_vertical_barrier: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.labels,
+            vec![Some(1), Some(0), Some(-1), Some(0), Some(0)]
+        );
+        assert_eq!(
+            result.barrier_touches,
+            vec![Some(2), Some(2), Some(3), Some(4), Some(4)]
+        );
+    }
+
+    #[test]
+    fn test_get_label() {
+        let returns = vec![0.0, 0.05, 0.15, -0.2];
+        assert_eq!(
+            get_label(
+                &returns,
+                Some(0.1),
+                Some(-0.1),
+                None,
+                TieBreak::Conservative
+            ),
+            Label {
+                ret: 0.15,
+                label: 1,
+                barrier_touch: 2
+            }
+        );
+        assert_eq!(
+            get_label(&returns, None, Some(-0.1), None, TieBreak::Conservative),
+            Label {
+                ret: -0.2,
+                label: -1,
+                barrier_touch: 3
+            }
+        );
+        assert_eq!(
+            get_label(&returns, None, None, None, TieBreak::Conservative),
+            Label {
+                ret: -0.2,
+                label: 0,
+                barrier_touch: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_price_path() {
+        let returns = calculate_price_path_return(&[], ReturnType::Simple);
+
+        assert!(returns.is_empty());
+        assert_eq!(
+            get_label(
+                &returns,
+                Some(0.1),
+                Some(-0.1),
+                None,
+                TieBreak::Conservative
+            ),
+            Label {
+                ret: 0.0,
+                label: 0,
+                barrier_touch: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_calculate_labels_final_row() {
+        let result = mixed_barrier_labels(true);
+
+        // The final row's path is just itself, so it touches the vertical barrier.
+        assert_eq!(result.rets[5], Some(0.0));
+        assert_eq!(result.labels[5], Some(0));
+        assert_eq!(result.barrier_touches[5], Some(5));
+        assert_eq!(result.path_ranges[5], (5, 5));
+    }
+
+    #[test]
+    fn test_get_label_tie_break_at_equal_touch() {
+        // Zero width barriers are both touched by the event itself.
+        let returns = vec![0.0, 0.05, -0.15];
+        let optimistic = get_label(&returns, Some(0.0), Some(0.0), None, TieBreak::Optimistic);
+        let conservative = get_label(&returns, Some(0.0), Some(0.0), None, TieBreak::Conservative);
+
+        assert_eq!(
+            optimistic,
+            Label {
+                ret: 0.0,
+                label: 1,
+                barrier_touch: 0
+            }
+        );
+        assert_eq!(
+            conservative,
+            Label {
+                ret: 0.0,
+                label: -1,
+                barrier_touch: 0
+            }
+        );
+        // An earlier touch wins regardless of the tie break.
+        assert_eq!(
+            get_label(
+                &returns,
+                Some(0.05),
+                Some(-0.1),
+                None,
+                TieBreak::Conservative
+            )
+            .label,
+            1
+        );
+        assert_eq!(
+            get_label(&returns, Some(0.1), Some(-0.1), None, TieBreak::Optimistic).label,
+            -1
+        );
+    }
+
+    #[test]
+    fn test_calculate_labels_positional() {
+        let result = mixed_barrier_labels(true);
+
+        assert_eq!(
+            result.labels,
+            vec![Some(1), Some(0), Some(-1), Some(1), Some(0), Some(0)]
+        );
+        assert_eq!(
+            result.barrier_touches,
+            vec![Some(2), Some(2), Some(3), Some(5), Some(5), Some(5)]
+        );
+    }
+
+    #[test]
+    fn test_calculate_labels_holding_periods() {
+        // Row 0 takes profit and row 2 stops out before their vertical barriers,
+        // while rows 1 and 4 run to theirs.
+        for positional_barrier_touch in [false, true] {
+            let result = mixed_barrier_labels(positional_barrier_touch);
+
+            assert_eq!(
+                result.labels,
+                vec![Some(1), Some(0), Some(-1), Some(1), Some(0), Some(0)]
+            );
+            assert_eq!(
+                result.holding_periods,
+                vec![Some(2), Some(1), Some(1), Some(2), Some(1), Some(0)]
+            );
+        }
+        // A path ending at the vertical barrier is held for its full horizon.
+        let result = mixed_barrier_labels(true);
+        for i in [1, 4, 5] {
+            let (start, end) = result.path_ranges[i];
+            assert_eq!(result.holding_periods[i], Some((end - start) as i64));
+        }
+    }
+
+    #[test]
+    fn test_calculate_labels_index_values() {
+        let result = mixed_barrier_labels(false);
+
+        assert_eq!(
+            result.labels,
+            vec![Some(1), Some(0), Some(-1), Some(1), Some(0), Some(0)]
+        );
+        assert_eq!(
+            result.barrier_touches,
+            vec![
+                Some(102),
+                Some(102),
+                Some(103),
+                Some(105),
+                Some(105),
+                Some(105)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_calculate_labels_parallel_matches_serial() {
+        let index = vec![100, 101, 102, 103, 104, 105];
+        let prices = vec![10.0, 10.5, 11.5, 9.0, 9.5, 10.0];
+        let profit_taking = vec![Some(0.1); 6];
+        let stop_loss = vec![Some(-0.1); 6];
+        let vertical_barriers = vec![Some(103), Some(102), None, None, Some(105), None];
+        for positional_barrier_touch in [false, true] {
+            let options = LabelOptions {
+                positional_barrier_touch,
+                ..Default::default()
+            };
+            let serial: Vec<LabeledRow> = (0..prices.len())
+                .map(|i| {
+                    label_row(
+                        &index,
+                        &prices,
+                        &profit_taking,
+                        &stop_loss,
+                        &vertical_barriers,
+                        options,
+                        i,
+                    )
+                    .unwrap()
+                })
+                .collect();
+
+            let result = mixed_barrier_labels(positional_barrier_touch);
+
+            assert_eq!(
+                result,
+                TripleBarrierLabels {
+                    rets: serial.iter().map(|row| Some(row.0)).collect(),
+                    labels: serial.iter().map(|row| Some(row.1)).collect(),
+                    barrier_touches: serial.iter().map(|row| Some(row.2)).collect(),
+                    holding_periods: serial.iter().map(|row| Some(row.3)).collect(),
+                    path_ranges: serial.iter().map(|row| row.4).collect(),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculate_labels_without_vertical_barriers_matches_general_path() {
+        let n = 3_000;
+        let index: Vec<i64> = (0..n as i64).map(|i| 1_000 + 2 * i).collect();
+        let mut log_price = 0.0;
+        let prices: Vec<f64> = (0..n)
+            .map(|i| {
+                log_price += 0.004 * (i as f64 * 1.7).sin() + 0.002 * (i as f64 * 0.05).cos();
+                100.0 * f64::exp(log_price)
+            })
+            .collect();
+        let profit_taking: Vec<Option<f64>> = (0..n)
+            .map(|i| (i % 7 != 0).then_some(0.01 + 0.01 * (i % 3) as f64))
+            .collect();
+        let stop_loss: Vec<Option<f64>> = (0..n).map(|i| (i % 5 != 0).then_some(-0.02)).collect();
+        let vertical_barriers = vec![None; n];
+        for (positional_barrier_touch, tie_break, trailing_stop) in [
+            (false, TieBreak::Conservative, None),
+            (true, TieBreak::Optimistic, Some(0.015)),
+        ] {
+            let options = LabelOptions {
+                positional_barrier_touch,
+                tie_break,
+                trailing_stop,
+                ..Default::default()
+            };
+            let general: Vec<LabeledRow> = (0..n)
+                .map(|i| {
+                    label_row(
+                        &index,
+                        &prices,
+                        &profit_taking,
+                        &stop_loss,
+                        &vertical_barriers,
+                        options,
+                        i,
+                    )
+                    .unwrap()
+                })
+                .collect();
+
+            let result = calculate_labels(
+                &index,
+                &prices,
+                &profit_taking,
+                &stop_loss,
+                &vertical_barriers,
+                options,
+            )
+            .unwrap();
+
+            assert_eq!(result, TripleBarrierLabels::from_rows(&general));
+            for label in [-1, 0, 1] {
+                assert!(result.labels.contains(&Some(label)));
+            }
+        }
+        assert_eq!(
+            scan_label(
+                &[10.0, 11.0],
+                Some(0.0),
+                Some(0.0),
+                LabelOptions {
+                    tie_break: TieBreak::Optimistic,
+                    ..Default::default()
+                }
+            ),
+            get_label(
+                &[0.0, 0.1],
+                Some(0.0),
+                Some(0.0),
+                None,
+                TieBreak::Optimistic
+            )
+        );
+    }
+
+    #[test]
+    fn test_calculate_labels_path_ranges_match_manual_slice() {
+        let prices = [10.0, 10.5, 11.5, 9.0, 9.5, 10.0];
+        let result = mixed_barrier_labels(true);
+
+        let paths: Vec<&[f64]> = result
+            .path_ranges
+            .iter()
+            .map(|&(start, end)| &prices[start..=end])
+            .collect();
+
+        assert_eq!(paths[0], &[10.0, 10.5, 11.5, 9.0]);
+        assert_eq!(paths[1], &[10.5, 11.5]);
+        assert_eq!(paths[4], &[9.5, 10.0]);
+        assert_eq!(paths[5], &[10.0]);
+        for (path, ret) in paths.iter().zip(&result.rets) {
+            let returns = calculate_price_path_return(path, ReturnType::Simple);
+            assert!(returns.contains(&ret.unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_calculate_labels_missing_vertical_barrier_errors() {
+        let index = vec![100, 101, 102, 103, 104, 105];
+        let prices = vec![10.0, 10.5, 11.5, 9.0, 9.5, 10.0];
+        let vertical_barriers = vec![Some(102), None, None, None, Some(106), None];
+
+        let result = calculate_labels(
+            &index,
+            &prices,
+            &[Some(0.1); 6],
+            &[Some(-0.1); 6],
+            &vertical_barriers,
+            LabelOptions::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(PolarsError::ComputeError(msg)) if &*msg == "Vertical barrier 106 not found in index"
+        ));
+    }
+
+    #[test]
+    fn test_valid_vertical_barriers_past_end_of_index() {
+        let index = vec![100, 101, 102, 103, 104];
+        // A fixed two step barrier runs past the data for the last two rows.
+        let vertical_barriers: Vec<Option<i64>> = index.iter().map(|i| Some(i + 2)).collect();
+
+        assert_eq!(
+            valid_vertical_barriers(&index, &vertical_barriers, false),
+            vec![true, true, true, false, false]
+        );
+        // A barrier between index values or behind the row is not in the
+        // remaining index, but one on the row itself or a missing barrier is valid.
+        assert_eq!(
+            valid_vertical_barriers(
+                &[0, 10, 20, 30, 40],
+                &[Some(0), Some(15), None, Some(20), Some(40)],
+                false
+            ),
+            vec![true, false, true, false, true]
+        );
+        assert_eq!(
+            valid_vertical_barriers(&index, &[Some(99), Some(100), None, None, None], false),
+            vec![false, false, true, true, true]
+        );
+        assert_eq!(
+            valid_vertical_barriers(&[0, 10, 20], &[Some(5), Some(20), Some(25)], true),
+            vec![true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_barriers_from_volatility_widen_with_volatility() {
+        let index = vec![0, 1, 2, 3, 4, 5];
+        let prices = vec![100.0, 103.0, 100.0, 103.0, 100.0, 103.0];
+        let volatility = vec![
+            Some(0.01),
+            Some(0.02),
+            Some(0.04),
+            Some(0.08),
+            Some(0.16),
+            None,
+        ];
+
+        let (profit_taking, stop_loss) =
+            barriers_from_volatility(&volatility, Some(1.0), Some(2.0));
+        assert_eq!(
+            profit_taking,
+            vec![
+                Some(0.01),
+                Some(0.02),
+                Some(0.04),
+                Some(0.08),
+                Some(0.16),
+                None
+            ]
+        );
+        assert_eq!(
+            stop_loss,
+            vec![
+                Some(-0.02),
+                Some(-0.04),
+                Some(-0.08),
+                Some(-0.16),
+                Some(-0.32),
+                None
+            ]
+        );
+
+        // The same 3% swings touch the narrow early barriers but not the wide late ones.
+        let (profit_taking, stop_loss) =
+            barriers_from_volatility(&volatility, Some(1.0), Some(1.0));
+        let result = calculate_labels(
+            &index,
+            &prices,
+            &profit_taking,
+            &stop_loss,
+            &[None; 6],
+            LabelOptions {
+                positional_barrier_touch: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            result.labels,
+            vec![Some(1), Some(-1), Some(0), Some(0), Some(0), Some(0)]
+        );
+    }
+
+    #[test]
+    fn test_get_meta_labels_long_and_short() {
+        let rets = vec![
+            Some(0.1),
+            Some(-0.1),
+            Some(0.1),
+            Some(-0.1),
+            Some(0.0),
+            None,
+        ];
+        let sides = vec![
+            Some(1.0),
+            Some(1.0),
+            Some(-1.0),
+            Some(-1.0),
+            Some(1.0),
+            Some(1.0),
+        ];
+
+        let meta_labels = get_meta_labels(&rets, &sides);
+
+        assert_eq!(
+            meta_labels,
+            vec![Some(1), Some(0), Some(0), Some(1), Some(0), None]
+        );
+    }
+
+    #[test]
+    fn test_trend_t_value() {
+        // Noise around an upward trend gives a large positive t-value.
+        let t_value = trend_t_value(&[1.1, 2.0, 3.1, 4.0, 5.1]).unwrap();
+        assert!(t_value > 10.0);
+        assert_eq!(trend_t_value(&[1.0, 2.0, 3.0]), Some(f64::INFINITY));
+        assert_eq!(trend_t_value(&[1.0, 1.0, 1.0]), None);
+    }
+
+    #[test]
+    fn test_trend_scan_labels_trending_then_reverting() {
+        let prices = vec![1.1, 2.0, 3.1, 4.0, 5.1, 6.0, 5.1, 4.0, 3.1, 2.0, 1.1];
+
+        let scans = trend_scan_labels(&prices, 3, 5);
+        let labels: Vec<Option<i32>> = scans.iter().map(|s| s.map(|s| s.label)).collect();
+
+        assert_eq!(
+            labels,
+            vec![
+                Some(1),
+                Some(1),
+                Some(1),
+                Some(1),
+                Some(-1),
+                Some(-1),
+                Some(-1),
+                Some(-1),
+                Some(-1),
+                None,
+                None
+            ]
+        );
+        assert!(scans.iter().flatten().all(|s| (3..=5).contains(&s.horizon)));
+    }
+
+    #[test]
+    fn test_fixed_horizon_labels() {
+        let prices = vec![
+            Some(100.0),
+            Some(101.0),
+            Some(103.0),
+            Some(100.0),
+            None,
+            Some(99.0),
+        ];
+
+        let labels = fixed_horizon_labels(&prices, 2, 0.02);
+
+        // 100 -> 103 is above the threshold, 101 -> 100 is inside it, 103 -> null has
+        // no return, 100 -> 99 is inside it and the last two rows run out of data.
+        assert_eq!(labels, vec![Some(1), Some(0), None, Some(0), None, None]);
+        assert_eq!(
+            fixed_horizon_labels(&prices, 1, 0.025),
+            vec![Some(0), Some(0), Some(-1), None, None, None]
+        );
+    }
+}
@@ -1,6 +1,23 @@
 mod bars;
+mod bet_sizing;
+mod black_scholes;
+mod cross_validation;
+mod entropy;
+mod filters;
 mod frac_diff;
 mod labels;
+mod math;
+mod metrics;
+mod microstructure;
+mod monte_carlo;
+mod pnl;
+mod returns;
+mod sample_weights;
+mod stats;
+mod structural_breaks;
+mod utils;
+mod vol_surface;
+mod volatility;
 
 use pyo3::prelude::*;
 
@@ -0,0 +1,141 @@
+/// Calculate the standard normal probability density function.
+pub(crate) fn norm_pdf(x: f64) -> f64 {
+    (-x * x / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// Calculate the standard normal cumulative distribution function.
+///
+/// Uses Hart's double precision algorithm as given by West (2005), "Better
+/// approximations to cumulative normal functions".
+pub(crate) fn norm_cdf(x: f64) -> f64 {
+    let x_abs = x.abs();
+    let tail = if x_abs > 37.0 {
+        0.0
+    } else {
+        let e = (-x_abs * x_abs / 2.0).exp();
+        if x_abs < 7.071_067_811_865_47 {
+            let numerator = [
+                0.700_383_064_443_688,
+                6.373_962_203_531_65,
+                33.912_866_078_383,
+                112.079_291_497_871,
+                221.213_596_169_931,
+                220.206_867_912_376,
+            ]
+            .iter()
+            .fold(3.526_249_659_989_11e-2, |acc, c| acc * x_abs + c);
+            let denominator = [
+                1.755_667_163_182_64,
+                16.064_177_579_207,
+                86.780_732_202_946_1,
+                296.564_248_779_674,
+                637.333_633_378_831,
+                793.826_512_519_948,
+                440.413_735_824_752,
+            ]
+            .iter()
+            .fold(8.838_834_764_831_84e-2, |acc, c| acc * x_abs + c);
+            e * numerator / denominator
+        } else {
+            let continued_fraction = [4.0, 3.0, 2.0, 1.0]
+                .iter()
+                .fold(x_abs + 0.65, |acc, c| x_abs + c / acc);
+            e / continued_fraction / 2.506_628_274_631
+        }
+    };
+    if x > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Evaluate a polynomial with coefficients ordered from the highest degree.
+fn polynomial(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().fold(0.0, |acc, c| acc * x + c)
+}
+
+/// Calculate the inverse of the standard normal cumulative distribution function.
+///
+/// Uses Acklam's rational approximation followed by one step of Halley's method,
+/// which is accurate to about machine precision. Probabilities outside `(0, 1)`
+/// are NaN.
+pub(crate) fn norm_ppf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 6] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+        1.0,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 5] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+        1.0,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    if !(p > 0.0 && p < 1.0) {
+        return f64::NAN;
+    }
+    let x = if p < P_LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        polynomial(&C, q) / polynomial(&D, q)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        polynomial(&A, q * q) * q / polynomial(&B, q * q)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -polynomial(&C, q) / polynomial(&D, q)
+    };
+    let u = (norm_cdf(x) - p) / norm_pdf(x);
+    x - u / (1.0 + x * u / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_norm_cdf_and_pdf() {
+        assert_eq!(norm_cdf(0.0), 0.5);
+        assert!((norm_cdf(1.959_963_984_540_054) - 0.975).abs() < 1e-15);
+        assert!((norm_cdf(-1.0) - 0.158_655_253_931_457_05).abs() < 1e-15);
+        assert_eq!(norm_cdf(-40.0), 0.0);
+        assert_eq!(norm_pdf(0.0), 1.0 / (2.0 * std::f64::consts::PI).sqrt());
+        assert!((norm_pdf(1.0) - 0.241_970_724_519_143_37).abs() < 1e-15);
+        assert_eq!(norm_pdf(-1.5), norm_pdf(1.5));
+    }
+
+    #[test]
+    fn test_norm_ppf_inverts_norm_cdf() {
+        assert_eq!(norm_ppf(0.5), 0.0);
+        assert!((norm_ppf(0.975) - 1.959_963_984_540_054).abs() < 1e-12);
+        for p in [1e-10, 0.001, 0.02, 0.3, 0.7, 0.98, 0.999] {
+            assert!((norm_cdf(norm_ppf(p)) - p).abs() / p < 1e-9);
+        }
+        assert!((norm_ppf(0.025) + norm_ppf(0.975)).abs() < 1e-12);
+        for p in [0.0, 1.0, -0.5, 1.5, f64::NAN] {
+            assert!(norm_ppf(p).is_nan());
+        }
+    }
+}
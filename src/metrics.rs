@@ -0,0 +1,499 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::math::{norm_cdf, norm_ppf};
+use crate::stats::rolling_statistic;
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn sample_std(values: &[f64]) -> f64 {
+    let m = mean(values);
+    (values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (values.len() as f64 - 1.0)).sqrt()
+}
+
+const NS_PER_HOUR: f64 = 3_600_000_000_000.0;
+const NS_PER_DAY: f64 = 24.0 * NS_PER_HOUR;
+
+/// Calculate the number of bars of `interval_ns` nanoseconds in a year of
+/// `trading_days` sessions of `hours_per_day` hours.
+///
+/// Intervals of a day or more are counted in trading days, so daily bars give
+/// `trading_days`. Intraday intervals are counted within a session, and an
+/// interval longer than a session but shorter than a day is one bar per session.
+fn periods_per_year_from_interval(interval_ns: i64, trading_days: f64, hours_per_day: f64) -> f64 {
+    let interval_ns = interval_ns as f64;
+    let bars_per_day = if interval_ns >= NS_PER_DAY {
+        NS_PER_DAY / interval_ns
+    } else {
+        (hours_per_day * NS_PER_HOUR / interval_ns).max(1.0)
+    };
+    trading_days * bars_per_day
+}
+
+/// Resolve the annualization factor of a metric from either an explicit
+/// `periods_per_year` or the `bar_interval_ns` of the returns, defaulting to
+/// daily returns.
+fn resolve_periods_per_year(
+    periods_per_year: Option<f64>,
+    bar_interval_ns: Option<i64>,
+    trading_days: f64,
+    hours_per_day: f64,
+) -> PolarsResult<f64> {
+    if trading_days <= 0.0 || hours_per_day <= 0.0 || hours_per_day > 24.0 {
+        return Err(PolarsError::ComputeError(
+            "trading_days must be positive and hours_per_day in (0, 24]".into(),
+        ));
+    }
+    match (periods_per_year, bar_interval_ns) {
+        (Some(_), Some(_)) => Err(PolarsError::ComputeError(
+            "Only one of periods_per_year and bar_interval_ns can be given".into(),
+        )),
+        (Some(periods_per_year), None) => Ok(periods_per_year),
+        (None, Some(interval_ns)) if interval_ns <= 0 => Err(PolarsError::ComputeError(
+            "bar_interval_ns must be positive".into(),
+        )),
+        (None, Some(interval_ns)) => Ok(periods_per_year_from_interval(
+            interval_ns,
+            trading_days,
+            hours_per_day,
+        )),
+        (None, None) => Ok(trading_days),
+    }
+}
+
+/// Calculate the annualized Sharpe ratio over a rolling window of returns.
+///
+/// The Sharpe ratio is `mean / std * sqrt(periods_per_year)` with the sample
+/// standard deviation. The first `window - 1` rows, windows containing a null and
+/// windows with (numerically) zero variance are `None`.
+fn rolling_sharpe_ratio(
+    returns: &[Option<f64>],
+    window: usize,
+    periods_per_year: f64,
+) -> Vec<Option<f64>> {
+    rolling_statistic(returns, window, |values| {
+        let std = sample_std(values);
+        (std > f64::EPSILON).then(|| mean(values) / std * periods_per_year.sqrt())
+    })
+}
+
+/// Calculate the running percentage drawdown of an equity curve from its
+/// running peak. Null values are `None` and do not affect the peak.
+pub(crate) fn drawdowns(equity: &[Option<f64>]) -> Vec<Option<f64>> {
+    let mut peak = f64::NEG_INFINITY;
+    equity
+        .iter()
+        .map(|value| {
+            let value = (*value)?;
+            peak = peak.max(value);
+            Some(value / peak - 1.0)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Drawdown {
+    drawdown: f64,
+    start: usize,
+    end: usize,
+}
+
+/// Find the maximum drawdown of an equity curve, along with the row positions of
+/// the peak it starts from and the trough it ends at.
+fn maximum_drawdown(equity: &[Option<f64>]) -> Option<Drawdown> {
+    let (mut peak_idx, mut peak_value) = (0, f64::NEG_INFINITY);
+    let mut worst: Option<Drawdown> = None;
+    for (i, value) in equity.iter().enumerate() {
+        let Some(value) = *value else {
+            continue;
+        };
+        if value > peak_value {
+            (peak_idx, peak_value) = (i, value);
+        }
+        let drawdown = value / peak_value - 1.0;
+        if worst.is_none_or(|w| drawdown < w.drawdown) {
+            worst = Some(Drawdown {
+                drawdown,
+                start: peak_idx,
+                end: i,
+            });
+        }
+    }
+    worst
+}
+
+/// Calculate the Calmar ratio, the annualized compound return over the magnitude
+/// of the maximum drawdown.
+///
+/// The equity curve compounds the simple returns from a starting value of 1, so
+/// a loss on the first return is a drawdown. Returns `None` without returns or
+/// without a drawdown.
+fn calmar_ratio_of(returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    let mut equity = vec![Some(1.0)];
+    for ret in returns {
+        equity.push(Some(equity[equity.len() - 1]? * (1.0 + ret)));
+    }
+    let growth = equity[returns.len()]?;
+    let annual_return = growth.powf(periods_per_year / returns.len() as f64) - 1.0;
+    let drawdown = maximum_drawdown(&equity)?.drawdown;
+    (drawdown < 0.0).then(|| annual_return / -drawdown)
+}
+
+/// Calculate the annualized Sortino ratio, the mean excess return over
+/// `target_return` divided by the downside deviation.
+///
+/// The downside deviation is the root mean square of the shortfalls below the
+/// target over all periods. Returns `None` without returns or if no return falls
+/// below the target.
+fn sortino_ratio_of(returns: &[f64], periods_per_year: f64, target_return: f64) -> Option<f64> {
+    if returns.is_empty() {
+        return None;
+    }
+    let downside_variance = returns
+        .iter()
+        .map(|r| (r - target_return).min(0.0).powi(2))
+        .sum::<f64>()
+        / returns.len() as f64;
+    let downside_deviation = downside_variance.sqrt();
+    (downside_deviation > f64::EPSILON)
+        .then(|| (mean(returns) - target_return) / downside_deviation * periods_per_year.sqrt())
+}
+
+/// The non-annualized Sharpe ratio of a track record and the higher moments of
+/// its returns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SharpeMoments {
+    sharpe: f64,
+    skew: f64,
+    kurtosis: f64,
+    n: usize,
+}
+
+impl SharpeMoments {
+    /// Calculate the Sharpe ratio with the sample standard deviation, and the
+    /// skewness and (non-excess) kurtosis from the population central moments.
+    fn from_returns(returns: &[f64]) -> Option<Self> {
+        if returns.len() < 3 {
+            return None;
+        }
+        let m = mean(returns);
+        let central_moment =
+            |k: i32| returns.iter().map(|r| (r - m).powi(k)).sum::<f64>() / returns.len() as f64;
+        let (m2, m3, m4) = (central_moment(2), central_moment(3), central_moment(4));
+        let std = sample_std(returns);
+        (std > f64::EPSILON).then(|| Self {
+            sharpe: m / std,
+            skew: m3 / m2.powf(1.5),
+            kurtosis: m4 / m2.powi(2),
+            n: returns.len(),
+        })
+    }
+
+    /// Calculate the probability that the true Sharpe ratio exceeds `benchmark`.
+    ///
+    /// Reference: Bailey and Lopez de Prado (2012), "The Sharpe Ratio Efficient
+    /// Frontier"
+    fn probabilistic_sharpe(&self, benchmark: f64) -> f64 {
+        let variance =
+            1.0 - self.skew * self.sharpe + (self.kurtosis - 1.0) / 4.0 * self.sharpe.powi(2);
+        norm_cdf((self.sharpe - benchmark) * ((self.n - 1) as f64).sqrt() / variance.sqrt())
+    }
+}
+
+/// Calculate the expected maximum Sharpe ratio of `num_trials` independent
+/// strategies with zero true Sharpe ratio and the given variance of Sharpe
+/// ratios across trials.
+///
+/// Reference: Bailey and Lopez de Prado (2014), "The Deflated Sharpe Ratio"
+fn expected_max_sharpe(num_trials: usize, variance_of_trial_sharpes: f64) -> f64 {
+    if num_trials <= 1 {
+        return 0.0;
+    }
+    const EULER_MASCHERONI: f64 = 0.577_215_664_901_532_9;
+    let n = num_trials as f64;
+    variance_of_trial_sharpes.sqrt()
+        * ((1.0 - EULER_MASCHERONI) * norm_ppf(1.0 - 1.0 / n)
+            + EULER_MASCHERONI * norm_ppf(1.0 - 1.0 / (n * std::f64::consts::E)))
+}
+
+#[derive(Deserialize)]
+struct RollingSharpeKwargs {
+    window: usize,
+    periods_per_year: Option<f64>,
+    bar_interval_ns: Option<i64>,
+    trading_days: f64,
+    hours_per_day: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn rolling_sharpe(inputs: &[Series], kwargs: RollingSharpeKwargs) -> PolarsResult<Series> {
+    if kwargs.window < 2 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 2".into(),
+        ));
+    }
+    let periods_per_year = resolve_periods_per_year(
+        kwargs.periods_per_year,
+        kwargs.bar_interval_ns,
+        kwargs.trading_days,
+        kwargs.hours_per_day,
+    )?;
+    let returns: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let sharpe = rolling_sharpe_ratio(&returns, kwargs.window, periods_per_year);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &sharpe).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn drawdown_series(inputs: &[Series]) -> PolarsResult<Series> {
+    let equity: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &drawdowns(&equity)).into_series())
+}
+
+fn max_drawdown_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(vec![
+            Field::new("drawdown".into(), DataType::Float64),
+            Field::new("start".into(), DataType::Int64),
+            Field::new("end".into(), DataType::Int64),
+        ]),
+    ))
+}
+
+#[polars_expr(output_type_func=max_drawdown_struct)]
+fn max_drawdown(inputs: &[Series]) -> PolarsResult<Series> {
+    let equity: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let worst = maximum_drawdown(&equity);
+    let fields = [
+        Float64Chunked::new("drawdown".into(), &[worst.map(|w| w.drawdown)]).into_series(),
+        Int64Chunked::new("start".into(), &[worst.map(|w| w.start as i64)]).into_series(),
+        Int64Chunked::new("end".into(), &[worst.map(|w| w.end as i64)]).into_series(),
+    ];
+    Ok(StructChunked::from_series(inputs[0].name().clone(), 1, fields.iter())?.into_series())
+}
+
+/// Read the non-null returns of a track record.
+fn to_returns(s: &Series) -> PolarsResult<Vec<f64>> {
+    Ok(s.cast(&DataType::Float64)?
+        .f64()?
+        .iter()
+        .flatten()
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct CalmarRatioKwargs {
+    periods_per_year: Option<f64>,
+    bar_interval_ns: Option<i64>,
+    trading_days: f64,
+    hours_per_day: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn calmar_ratio(inputs: &[Series], kwargs: CalmarRatioKwargs) -> PolarsResult<Series> {
+    let periods_per_year = resolve_periods_per_year(
+        kwargs.periods_per_year,
+        kwargs.bar_interval_ns,
+        kwargs.trading_days,
+        kwargs.hours_per_day,
+    )?;
+    let returns = to_returns(&inputs[0])?;
+    let ratio = calmar_ratio_of(&returns, periods_per_year);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &[ratio]).into_series())
+}
+
+#[derive(Deserialize)]
+struct SortinoRatioKwargs {
+    periods_per_year: Option<f64>,
+    bar_interval_ns: Option<i64>,
+    trading_days: f64,
+    hours_per_day: f64,
+    target_return: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn sortino_ratio(inputs: &[Series], kwargs: SortinoRatioKwargs) -> PolarsResult<Series> {
+    let periods_per_year = resolve_periods_per_year(
+        kwargs.periods_per_year,
+        kwargs.bar_interval_ns,
+        kwargs.trading_days,
+        kwargs.hours_per_day,
+    )?;
+    let returns = to_returns(&inputs[0])?;
+    let ratio = sortino_ratio_of(&returns, periods_per_year, kwargs.target_return);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &[ratio]).into_series())
+}
+
+#[derive(Deserialize)]
+struct ProbabilisticSharpeKwargs {
+    benchmark_sharpe: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn probabilistic_sharpe(
+    inputs: &[Series],
+    kwargs: ProbabilisticSharpeKwargs,
+) -> PolarsResult<Series> {
+    let returns = to_returns(&inputs[0])?;
+    let psr = SharpeMoments::from_returns(&returns)
+        .map(|moments| moments.probabilistic_sharpe(kwargs.benchmark_sharpe));
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &[psr]).into_series())
+}
+
+#[derive(Deserialize)]
+struct DeflatedSharpeKwargs {
+    num_trials: usize,
+    variance_of_trial_sharpes: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn deflated_sharpe(inputs: &[Series], kwargs: DeflatedSharpeKwargs) -> PolarsResult<Series> {
+    if kwargs.num_trials == 0 || kwargs.variance_of_trial_sharpes < 0.0 {
+        return Err(PolarsError::ComputeError(
+            "num_trials must be at least 1 and variance_of_trial_sharpes non-negative".into(),
+        ));
+    }
+    let returns = to_returns(&inputs[0])?;
+    let benchmark = expected_max_sharpe(kwargs.num_trials, kwargs.variance_of_trial_sharpes);
+    let dsr = SharpeMoments::from_returns(&returns)
+        .map(|moments| moments.probabilistic_sharpe(benchmark));
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &[dsr]).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_periods_per_year_from_interval() {
+        let hour = 3_600_000_000_000;
+
+        assert_eq!(periods_per_year_from_interval(24 * hour, 252.0, 6.5), 252.0);
+        assert_eq!(periods_per_year_from_interval(hour, 252.0, 6.5), 1638.0);
+        assert_eq!(
+            periods_per_year_from_interval(hour / 60, 252.0, 6.5),
+            98_280.0
+        );
+        assert_eq!(periods_per_year_from_interval(hour, 365.0, 24.0), 8760.0);
+        // Longer than a session but shorter than a day is one bar per session.
+        assert_eq!(periods_per_year_from_interval(12 * hour, 252.0, 6.5), 252.0);
+        assert_eq!(periods_per_year_from_interval(48 * hour, 252.0, 6.5), 126.0);
+
+        assert_eq!(
+            resolve_periods_per_year(None, None, 252.0, 6.5).unwrap(),
+            252.0
+        );
+        assert_eq!(
+            resolve_periods_per_year(Some(12.0), None, 252.0, 6.5).unwrap(),
+            12.0
+        );
+        assert_eq!(
+            resolve_periods_per_year(None, Some(hour), 252.0, 6.5).unwrap(),
+            1638.0
+        );
+        assert!(resolve_periods_per_year(Some(12.0), Some(hour), 252.0, 6.5).is_err());
+        assert!(resolve_periods_per_year(None, Some(0), 252.0, 6.5).is_err());
+    }
+
+    #[test]
+    fn test_rolling_sharpe_ratio() {
+        // The last window [0.01, 0.03, -0.01] has mean 0.01 and std 0.02.
+        let returns = vec![Some(0.01), Some(0.01), Some(0.01), Some(0.03), Some(-0.01)];
+
+        let sharpe = rolling_sharpe_ratio(&returns, 3, 252.0);
+
+        // Constant returns have no variance.
+        assert_eq!(sharpe[..3], [None, None, None]);
+        assert!((sharpe[4].unwrap() - 0.5 * 252f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_drawdowns_recover_then_deeper() {
+        // A 10% drawdown recovers to a new peak, then a deeper 20% drawdown follows.
+        let equity = vec![
+            Some(100.0),
+            Some(110.0),
+            Some(99.0),
+            None,
+            Some(115.0),
+            Some(92.0),
+            Some(100.0),
+        ];
+
+        let series = drawdowns(&equity);
+        let expected = [0.0, 0.0, -0.1, 0.0, 0.0, -0.2, 100.0 / 115.0 - 1.0];
+        for (i, e) in expected.iter().enumerate() {
+            if i == 3 {
+                assert_eq!(series[i], None);
+            } else {
+                assert!((series[i].unwrap() - e).abs() < 1e-12);
+            }
+        }
+
+        let worst = maximum_drawdown(&equity).unwrap();
+        assert!((worst.drawdown + 0.2).abs() < 1e-12);
+        assert_eq!((worst.start, worst.end), (4, 5));
+        assert_eq!(maximum_drawdown(&[None, None]), None);
+    }
+
+    #[test]
+    fn test_probabilistic_sharpe_hand_computed() {
+        let returns = [0.02, -0.01, 0.03, 0.01, -0.02, 0.04];
+
+        let moments = SharpeMoments::from_returns(&returns).unwrap();
+        // mean = 0.011667, sample std = 0.023166, m2 = 4.4722e-4,
+        // m3 = -2.0741e-6, m4 = 3.3137e-7
+        assert!((moments.sharpe - 0.503_610_155).abs() < 1e-8);
+        assert!((moments.skew - (-0.219_300_287)).abs() < 1e-8);
+        assert!((moments.kurtosis - 1.656_764_785).abs() < 1e-8);
+
+        // z = 0.50361 * sqrt(5) / sqrt(1 + 0.21930 * 0.50361 + 0.16419 * 0.25362)
+        //   = 1.04915
+        let psr = moments.probabilistic_sharpe(0.0);
+        assert!((psr - 0.852_945_301).abs() < 1e-8);
+        assert!(moments.probabilistic_sharpe(moments.sharpe) == 0.5);
+    }
+
+    #[test]
+    fn test_deflated_sharpe_drops_with_num_trials() {
+        let returns = [0.02, -0.01, 0.03, 0.01, -0.02, 0.04, 0.01, 0.0, 0.02, -0.01];
+        let moments = SharpeMoments::from_returns(&returns).unwrap();
+
+        assert_eq!(expected_max_sharpe(1, 0.01), 0.0);
+        let deflated: Vec<f64> = [1, 10, 100, 1000]
+            .iter()
+            .map(|&n| moments.probabilistic_sharpe(expected_max_sharpe(n, 0.01)))
+            .collect();
+
+        assert_eq!(deflated[0], moments.probabilistic_sharpe(0.0));
+        assert!(deflated.windows(2).all(|w| w[1] < w[0]));
+    }
+
+    #[test]
+    fn test_calmar_ratio() {
+        // Equity 1.0 -> 1.1 -> 0.88 -> 1.1, a 20% drawdown and 10% growth over a
+        // year of three periods.
+        let returns = [0.1, -0.2, 0.25];
+
+        assert!((calmar_ratio_of(&returns, 3.0).unwrap() - 0.5).abs() < 1e-12);
+        // A first period loss draws down from the starting value.
+        assert!((calmar_ratio_of(&[-0.1, 0.0], 2.0).unwrap() + 1.0).abs() < 1e-12);
+        assert_eq!(calmar_ratio_of(&[0.01, 0.02, 0.0], 252.0), None);
+        assert_eq!(calmar_ratio_of(&[], 252.0), None);
+    }
+
+    #[test]
+    fn test_sortino_ratio() {
+        // Mean 0.005 and downside deviation sqrt((0.01^2 + 0.02^2) / 4).
+        let returns = [0.02, -0.01, 0.03, -0.02];
+
+        let expected = 0.005 / 1.25e-4_f64.sqrt() * 12f64.sqrt();
+        assert!((sortino_ratio_of(&returns, 12.0, 0.0).unwrap() - expected).abs() < 1e-12);
+        assert!(sortino_ratio_of(&returns, 12.0, 0.01).unwrap() < 0.0);
+        assert_eq!(sortino_ratio_of(&[0.01, 0.02, 0.03], 252.0, 0.0), None);
+    }
+}
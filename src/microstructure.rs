@@ -0,0 +1,691 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::utils::broadcast_inputs;
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn sample_covariance(x: &[f64], y: &[f64]) -> f64 {
+    let (x_mean, y_mean) = (mean(x), mean(y));
+    x.iter()
+        .zip(y)
+        .map(|(x, y)| (x - x_mean) * (y - y_mean))
+        .sum::<f64>()
+        / (x.len() as f64 - 1.0)
+}
+
+/// Calculate the Roll (1984) effective spread over a rolling window of price
+/// changes.
+///
+/// The spread is `2 * sqrt(-cov(Δp_t, Δp_{t-1}))` over the last `window` pairs of
+/// consecutive price changes. It is `None` during warm-up, when the window
+/// contains a null, or when the covariance is positive and the model is undefined.
+fn rolling_roll_spread(prices: &[Option<f64>], window: usize) -> Vec<Option<f64>> {
+    let diffs: Vec<Option<f64>> = std::iter::once(None)
+        .chain(prices.windows(2).map(|w| Some(w[1]? - w[0]?)))
+        .collect();
+    (0..prices.len())
+        .map(|t| {
+            if t < window + 1 {
+                return None;
+            }
+            let (current, lagged): (Vec<f64>, Vec<f64>) = (t + 1 - window..=t)
+                .map(|s| Some((diffs[s]?, diffs[s - 1]?)))
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+            let cov = sample_covariance(&current, &lagged);
+            (cov <= 0.0).then(|| 2.0 * (-cov).sqrt())
+        })
+        .collect()
+}
+
+/// Calculate the Amihud (2002) illiquidity ratio over a rolling window.
+///
+/// The ratio is the mean of `|return| / dollar_volume` over the window. Bars with
+/// zero or missing dollar volume, or a missing return, are skipped rather than
+/// producing an infinite ratio. A window without any valid bars is `None`.
+fn rolling_amihud(
+    returns: &[Option<f64>],
+    dollar_volume: &[Option<f64>],
+    window: usize,
+) -> Vec<Option<f64>> {
+    let ratios: Vec<Option<f64>> = returns
+        .iter()
+        .zip(dollar_volume)
+        .map(|(r, dv)| match (r, dv) {
+            (Some(r), Some(dv)) if *dv > 0.0 => Some(r.abs() / dv),
+            _ => None,
+        })
+        .collect();
+    (0..ratios.len())
+        .map(|t| {
+            if t + 1 < window {
+                return None;
+            }
+            let valid: Vec<f64> = ratios[t + 1 - window..=t]
+                .iter()
+                .flatten()
+                .copied()
+                .collect();
+            (!valid.is_empty()).then(|| mean(&valid))
+        })
+        .collect()
+}
+
+/// Sign trades with the tick rule.
+///
+/// A trade above the previous price is a buy (+1) and below it is a sell (-1). A
+/// trade at the previous price carries the previous sign forward. Trades before
+/// the first price change, and null prices, are `None`.
+fn tick_rule_signs(prices: &[Option<f64>]) -> Vec<Option<i8>> {
+    let mut last_price: Option<f64> = None;
+    let mut last_sign: Option<i8> = None;
+    prices
+        .iter()
+        .map(|price| {
+            let price = (*price)?;
+            if let Some(last) = last_price {
+                if price > last {
+                    last_sign = Some(1);
+                } else if price < last {
+                    last_sign = Some(-1);
+                }
+            }
+            last_price = Some(price);
+            last_sign
+        })
+        .collect()
+}
+
+/// Classify a trade by the side of the quote it crossed.
+///
+/// A trade at or above the ask is buyer initiated (+1), at or below the bid
+/// seller initiated (-1), and strictly inside the spread 0. A crossed quote,
+/// with the bid above the ask, is `None`.
+fn aggressor_side(price: f64, bid: f64, ask: f64) -> Option<i8> {
+    if bid > ask {
+        return None;
+    }
+    Some(if price >= ask {
+        1
+    } else if price <= bid {
+        -1
+    } else {
+        0
+    })
+}
+
+/// The direction to round a price onto the tick grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RoundingMode {
+    Nearest,
+    Up,
+    Down,
+}
+
+impl RoundingMode {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nearest" => Some(Self::Nearest),
+            "up" => Some(Self::Up),
+            "down" => Some(Self::Down),
+            _ => None,
+        }
+    }
+}
+
+/// Snap a price to a multiple of `tick_size`.
+///
+/// Up and down round towards positive and negative infinity, so they hold for
+/// negative prices too, and nearest rounds halves away from zero. A price within
+/// floating point error of a tick stays on it rather than moving a whole tick. A
+/// zero tick size leaves the price unchanged, and a negative one is `None`.
+fn snap_price(price: f64, tick_size: f64, mode: RoundingMode) -> Option<f64> {
+    if tick_size == 0.0 {
+        return Some(price);
+    }
+    if tick_size.is_nan() || tick_size < 0.0 {
+        return None;
+    }
+    let ticks = price / tick_size;
+    let nearest = ticks.round();
+    let ticks = if (ticks - nearest).abs() < 1e-9 {
+        nearest
+    } else {
+        match mode {
+            RoundingMode::Nearest => nearest,
+            RoundingMode::Up => ticks.ceil(),
+            RoundingMode::Down => ticks.floor(),
+        }
+    };
+    Some(ticks * tick_size)
+}
+
+/// Estimate the Corwin-Schultz (2012) proportional spread from the high and low
+/// prices of two consecutive periods.
+///
+/// Negative estimates, which arise from large moves between the periods, are
+/// clamped to zero as the paper recommends.
+fn corwin_schultz(highs: [f64; 2], lows: [f64; 2]) -> f64 {
+    let beta: f64 = highs
+        .iter()
+        .zip(&lows)
+        .map(|(h, l)| (h / l).ln().powi(2))
+        .sum();
+    let gamma = (highs[0].max(highs[1]) / lows[0].min(lows[1])).ln().powi(2);
+    let k = 3.0 - 2.0 * std::f64::consts::SQRT_2;
+    let alpha = ((2.0 * beta).sqrt() - beta.sqrt()) / k - (gamma / k).sqrt();
+    let spread = 2.0 * (alpha.exp() - 1.0) / (1.0 + alpha.exp());
+    spread.max(0.0)
+}
+
+/// Calculate the Corwin-Schultz spread of each period with the period before it.
+fn corwin_schultz_spreads(highs: &[Option<f64>], lows: &[Option<f64>]) -> Vec<Option<f64>> {
+    std::iter::once(None)
+        .chain(
+            highs
+                .windows(2)
+                .zip(lows.windows(2))
+                .map(|(h, l)| Some(corwin_schultz([h[0]?, h[1]?], [l[0]?, l[1]?]))),
+        )
+        .take(highs.len())
+        .collect()
+}
+
+/// Calculate the size-weighted mid price from the best bid and ask.
+///
+/// The micro-price weights each side's price by the size on the opposite side, so
+/// depth on the bid pulls it towards the ask, where the price is more likely to
+/// move, and vice versa. It is `None` when the total size is zero.
+fn micro(bid: f64, ask: f64, bid_size: f64, ask_size: f64) -> Option<f64> {
+    let total_size = bid_size + ask_size;
+    (total_size > 0.0).then(|| (bid * ask_size + ask * bid_size) / total_size)
+}
+
+/// The best bid and ask prices and sizes after a quote update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quote {
+    bid: f64,
+    bid_size: f64,
+    ask: f64,
+    ask_size: f64,
+}
+
+/// Calculate the order flow imbalance contributed by a quote update.
+///
+/// Demand rises when the bid improves or its size grows, and supply rises when
+/// the ask improves or its size grows.
+///
+/// Reference: Cont, Kukanov and Stoikov (2014), "The Price Impact of Order Book
+/// Events"
+fn order_flow_increment(prev: &Quote, curr: &Quote) -> f64 {
+    let mut increment = 0.0;
+    if curr.bid >= prev.bid {
+        increment += curr.bid_size;
+    }
+    if curr.bid <= prev.bid {
+        increment -= prev.bid_size;
+    }
+    if curr.ask <= prev.ask {
+        increment -= curr.ask_size;
+    }
+    if curr.ask >= prev.ask {
+        increment += prev.ask_size;
+    }
+    increment
+}
+
+/// Calculate the order flow imbalance of each quote update. The first quote, and
+/// any quote with a missing value or after one, are `None`.
+fn order_flow_increments(quotes: &[Option<Quote>]) -> Vec<Option<f64>> {
+    std::iter::once(None)
+        .chain(
+            quotes
+                .windows(2)
+                .map(|w| Some(order_flow_increment(&w[0]?, &w[1]?))),
+        )
+        .take(quotes.len())
+        .collect()
+}
+
+/// Calculate the volume-synchronized probability of informed trading (VPIN) over
+/// a rolling window of volume buckets.
+///
+/// VPIN is the total absolute order imbalance `|buy - sell|` of the last `window`
+/// buckets divided by their total volume. Windows with a missing bucket or no
+/// volume are `None`.
+///
+/// Reference: Easley, Lopez de Prado and O'Hara (2012), "Flow Toxicity and
+/// Liquidity in a High-frequency World"
+fn rolling_vpin(
+    buy_volume: &[Option<f64>],
+    sell_volume: &[Option<f64>],
+    window: usize,
+) -> Vec<Option<f64>> {
+    let buckets: Vec<Option<(f64, f64)>> = buy_volume
+        .iter()
+        .zip(sell_volume)
+        .map(|(&buy, &sell)| Some(((buy? - sell?).abs(), buy? + sell?)))
+        .collect();
+    (0..buckets.len())
+        .map(|t| {
+            if t + 1 < window {
+                return None;
+            }
+            let (imbalance, volume) = buckets[t + 1 - window..=t].iter().try_fold(
+                (0.0, 0.0),
+                |(imbalance, volume), bucket| {
+                    let (i, v) = (*bucket)?;
+                    Some((imbalance + i, volume + v))
+                },
+            )?;
+            (volume > 0.0).then(|| imbalance / volume)
+        })
+        .collect()
+}
+
+fn to_f64_values(s: &Series) -> PolarsResult<Vec<Option<f64>>> {
+    Ok(s.cast(&DataType::Float64)?.f64()?.iter().collect())
+}
+
+fn to_f64_columns(inputs: &[Series]) -> PolarsResult<Vec<Vec<Option<f64>>>> {
+    broadcast_inputs(inputs)?
+        .iter()
+        .map(to_f64_values)
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct RollSpreadKwargs {
+    window: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn roll_spread(inputs: &[Series], kwargs: RollSpreadKwargs) -> PolarsResult<Series> {
+    if kwargs.window < 2 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 2".into(),
+        ));
+    }
+    let prices = to_f64_values(&inputs[0])?;
+    let spread = rolling_roll_spread(&prices, kwargs.window);
+    Ok(Float64Chunked::new("roll_spread".into(), &spread).into_series())
+}
+
+#[derive(Deserialize)]
+struct AmihudKwargs {
+    window: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn amihud_illiquidity(inputs: &[Series], kwargs: AmihudKwargs) -> PolarsResult<Series> {
+    if kwargs.window == 0 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 1".into(),
+        ));
+    }
+    let returns = to_f64_values(&inputs[0])?;
+    let dollar_volume = to_f64_values(&inputs[1])?;
+    let illiquidity = rolling_amihud(&returns, &dollar_volume, kwargs.window);
+    Ok(Float64Chunked::new("amihud_illiquidity".into(), &illiquidity).into_series())
+}
+
+#[polars_expr(output_type=Int8)]
+fn tick_rule(inputs: &[Series]) -> PolarsResult<Series> {
+    let prices = to_f64_values(&inputs[0])?;
+    Ok(Int8Chunked::new("tick_rule".into(), &tick_rule_signs(&prices)).into_series())
+}
+
+#[polars_expr(output_type=Int8)]
+fn trade_aggressor(inputs: &[Series]) -> PolarsResult<Series> {
+    let columns = to_f64_columns(inputs)?;
+    let (prices, bids, asks) = (&columns[0], &columns[1], &columns[2]);
+    let sides: Vec<Option<i8>> = (0..prices.len())
+        .map(|i| aggressor_side(prices[i]?, bids[i]?, asks[i]?))
+        .collect();
+    Ok(Int8Chunked::new("trade_aggressor".into(), &sides).into_series())
+}
+
+#[derive(Deserialize)]
+struct SnapToTickKwargs {
+    tick_size: Option<f64>,
+    mode: String,
+}
+
+#[polars_expr(output_type=Float64)]
+fn snap_to_tick(inputs: &[Series], kwargs: SnapToTickKwargs) -> PolarsResult<Series> {
+    let mode = RoundingMode::from_name(&kwargs.mode).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown mode: {}", kwargs.mode).into())
+    })?;
+    let columns = match (kwargs.tick_size, inputs.len()) {
+        (Some(tick_size), 1) => {
+            let prices = to_f64_values(&inputs[0])?;
+            let tick_sizes = vec![Some(tick_size); prices.len()];
+            vec![prices, tick_sizes]
+        },
+        (None, 2) => to_f64_columns(inputs)?,
+        _ => {
+            return Err(PolarsError::ComputeError(
+                "tick_size must be either a number or a column".into(),
+            ))
+        },
+    };
+    let (prices, tick_sizes) = (&columns[0], &columns[1]);
+    let snapped: Vec<Option<f64>> = prices
+        .iter()
+        .zip(tick_sizes)
+        .map(|(price, tick_size)| snap_price((*price)?, (*tick_size)?, mode))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &snapped).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn corwin_schultz_spread(inputs: &[Series]) -> PolarsResult<Series> {
+    let highs = to_f64_values(&inputs[0])?;
+    let lows = to_f64_values(&inputs[1])?;
+    let spread = corwin_schultz_spreads(&highs, &lows);
+    Ok(Float64Chunked::new("corwin_schultz_spread".into(), &spread).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn micro_price(inputs: &[Series]) -> PolarsResult<Series> {
+    let columns = to_f64_columns(inputs)?;
+    let (bids, asks, bid_sizes, ask_sizes) = (&columns[0], &columns[1], &columns[2], &columns[3]);
+    let prices: Vec<Option<f64>> = (0..bids.len())
+        .map(|i| micro(bids[i]?, asks[i]?, bid_sizes[i]?, ask_sizes[i]?))
+        .collect();
+    Ok(Float64Chunked::new("micro_price".into(), &prices).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn order_flow_imbalance(inputs: &[Series]) -> PolarsResult<Series> {
+    let columns = to_f64_columns(inputs)?;
+    let (bids, bid_sizes, asks, ask_sizes) = (&columns[0], &columns[1], &columns[2], &columns[3]);
+    let quotes: Vec<Option<Quote>> = (0..bids.len())
+        .map(|i| {
+            Some(Quote {
+                bid: bids[i]?,
+                bid_size: bid_sizes[i]?,
+                ask: asks[i]?,
+                ask_size: ask_sizes[i]?,
+            })
+        })
+        .collect();
+    let ofi = order_flow_increments(&quotes);
+    Ok(Float64Chunked::new("order_flow_imbalance".into(), &ofi).into_series())
+}
+
+#[derive(Deserialize)]
+struct VpinKwargs {
+    window: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn vpin(inputs: &[Series], kwargs: VpinKwargs) -> PolarsResult<Series> {
+    if kwargs.window == 0 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 1".into(),
+        ));
+    }
+    let buy_volume = to_f64_values(&inputs[0])?;
+    let sell_volume = to_f64_values(&inputs[1])?;
+    let vpin = rolling_vpin(&buy_volume, &sell_volume, kwargs.window);
+    Ok(Float64Chunked::new("vpin".into(), &vpin).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_roll_spread_bid_ask_bounce() {
+        // Trades bounce between the bid and ask around a constant mid price with a
+        // spread of 0.1. The trade direction cycles through a binary de Bruijn
+        // sequence so every direction pattern is equally likely, as in Roll's model.
+        let directions = [-1.0, -1.0, -1.0, 1.0, -1.0, 1.0, 1.0, 1.0];
+        let prices: Vec<Option<f64>> = directions
+            .iter()
+            .cycle()
+            .take(100)
+            .map(|q| Some(100.0 + 0.05 * q))
+            .collect();
+
+        let spread = rolling_roll_spread(&prices, 64);
+
+        assert!(spread[..65].iter().all(Option::is_none));
+        for s in &spread[65..] {
+            assert!((s.unwrap() - 0.1).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_rolling_roll_spread_positive_covariance() {
+        // A steadily accelerating price has positively correlated changes.
+        let prices: Vec<Option<f64>> = (0..10).map(|i| Some((i * i) as f64)).collect();
+
+        let spread = rolling_roll_spread(&prices, 3);
+
+        assert!(spread.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_rolling_amihud_liquid_vs_illiquid() {
+        // The same returns trade on far less dollar volume in the second segment.
+        let returns = vec![
+            Some(0.01),
+            Some(-0.02),
+            Some(0.01),
+            Some(0.01),
+            Some(-0.02),
+            Some(0.01),
+        ];
+        let dollar_volume = vec![
+            Some(1e6),
+            Some(2e6),
+            Some(1e6),
+            Some(1e4),
+            Some(2e4),
+            Some(1e4),
+        ];
+
+        let illiquidity = rolling_amihud(&returns, &dollar_volume, 3);
+
+        assert_eq!(illiquidity[..2], [None, None]);
+        assert!((illiquidity[2].unwrap() - 1e-8).abs() < 1e-20);
+        assert!((illiquidity[5].unwrap() - 1e-6).abs() < 1e-18);
+        assert!(illiquidity[5] > illiquidity[2]);
+    }
+
+    #[test]
+    fn test_rolling_amihud_skips_zero_dollar_volume() {
+        let returns = vec![Some(0.01), Some(0.05), Some(0.01), Some(0.05)];
+        let dollar_volume = vec![Some(1e4), Some(0.0), Some(1e4), Some(0.0)];
+
+        let illiquidity = rolling_amihud(&returns, &dollar_volume, 2);
+
+        assert_eq!(illiquidity, vec![None, Some(1e-6), Some(1e-6), Some(1e-6)]);
+    }
+
+    #[test]
+    fn test_tick_rule_signs_carries_forward_on_zero_tick() {
+        let prices = vec![
+            Some(10.0),
+            Some(10.0),
+            Some(10.1),
+            Some(10.1),
+            Some(10.0),
+            None,
+            Some(10.0),
+            Some(10.2),
+        ];
+
+        let signs = tick_rule_signs(&prices);
+
+        assert_eq!(
+            signs,
+            vec![
+                None,
+                None,
+                Some(1),
+                Some(1),
+                Some(-1),
+                None,
+                Some(-1),
+                Some(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aggressor_side() {
+        let (bid, ask) = (10.0, 10.1);
+
+        assert_eq!(aggressor_side(10.1, bid, ask), Some(1));
+        assert_eq!(aggressor_side(10.2, bid, ask), Some(1));
+        assert_eq!(aggressor_side(10.0, bid, ask), Some(-1));
+        assert_eq!(aggressor_side(9.9, bid, ask), Some(-1));
+        assert_eq!(aggressor_side(10.05, bid, ask), Some(0));
+        // A locked quote has no inside, and a crossed one is unusable.
+        assert_eq!(aggressor_side(10.0, 10.0, 10.0), Some(1));
+        assert_eq!(aggressor_side(10.05, 10.1, 10.0), None);
+    }
+
+    #[test]
+    fn test_aggressor_side_with_literal_quotes() {
+        let columns = to_f64_columns(&[
+            Series::new("price".into(), &[10.1, 10.0, 10.05]),
+            Series::new("bid".into(), &[10.0]),
+            Series::new("ask".into(), &[10.1]),
+        ])
+        .unwrap();
+        let sides: Vec<Option<i8>> = (0..columns[0].len())
+            .map(|i| aggressor_side(columns[0][i]?, columns[1][i]?, columns[2][i]?))
+            .collect();
+
+        assert_eq!(sides, vec![Some(1), Some(-1), Some(0)]);
+    }
+
+    #[test]
+    fn test_snap_price_rounding_modes() {
+        let snap = |price, mode| snap_price(price, 0.05, mode).unwrap();
+
+        assert!((snap(1.12, RoundingMode::Nearest) - 1.1).abs() < 1e-12);
+        assert!((snap(1.13, RoundingMode::Nearest) - 1.15).abs() < 1e-12);
+        assert!((snap(1.11, RoundingMode::Up) - 1.15).abs() < 1e-12);
+        assert!((snap(1.14, RoundingMode::Down) - 1.1).abs() < 1e-12);
+        // 1.15 / 0.05 is just below 23 in floating point, but is on the grid.
+        for mode in [RoundingMode::Nearest, RoundingMode::Up, RoundingMode::Down] {
+            assert!((snap(1.15, mode) - 1.15).abs() < 1e-12);
+        }
+        // Up and down keep their direction for negative prices.
+        assert!((snap(-1.12, RoundingMode::Up) + 1.1).abs() < 1e-12);
+        assert!((snap(-1.12, RoundingMode::Down) + 1.15).abs() < 1e-12);
+        assert!((snap(-1.125, RoundingMode::Nearest) + 1.15).abs() < 1e-12);
+
+        assert_eq!(snap_price(1.123, 0.0, RoundingMode::Up), Some(1.123));
+        assert_eq!(snap_price(1.123, -0.01, RoundingMode::Up), None);
+    }
+
+    #[test]
+    fn test_corwin_schultz_worked_example() {
+        // beta = ln(10.2/9.9)^2 + ln(10.3/10.0)^2 = 0.0017649
+        // gamma = ln(10.3/9.9)^2 = 0.0015689
+        // alpha = (sqrt(2 beta) - sqrt(beta)) / (3 - 2 sqrt(2)) - sqrt(gamma / (3 - 2 sqrt(2)))
+        //       = 0.0057986
+        // S = 2 (e^alpha - 1) / (1 + e^alpha) = 0.0057985
+        let spread = corwin_schultz([10.2, 10.3], [9.9, 10.0]);
+        assert!((spread - 0.005_798_536_107).abs() < 1e-12);
+
+        // A large move between the periods gives a negative estimate, clamped to zero.
+        assert_eq!(corwin_schultz([10.2, 11.0], [9.9, 10.6]), 0.0);
+    }
+
+    #[test]
+    fn test_corwin_schultz_spreads() {
+        let highs = vec![Some(10.2), Some(10.3), None];
+        let lows = vec![Some(9.9), Some(10.0), Some(10.0)];
+
+        let spreads = corwin_schultz_spreads(&highs, &lows);
+
+        assert_eq!(spreads.len(), 3);
+        assert_eq!(spreads[0], None);
+        assert!(spreads[1].is_some());
+        assert_eq!(spreads[2], None);
+    }
+
+    #[test]
+    fn test_micro_price_leans_towards_depth() {
+        // Equal depth gives the mid price.
+        assert_eq!(micro(99.0, 101.0, 500.0, 500.0), Some(100.0));
+        // With more depth on the bid, the next move is more likely up.
+        assert_eq!(micro(99.0, 101.0, 900.0, 100.0), Some(100.8));
+        assert_eq!(micro(99.0, 101.0, 100.0, 900.0), Some(99.2));
+        assert_eq!(micro(99.0, 101.0, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_order_flow_increments() {
+        let quote = |bid, bid_size, ask, ask_size| {
+            Some(Quote {
+                bid,
+                bid_size,
+                ask,
+                ask_size,
+            })
+        };
+        let quotes = vec![
+            quote(99.0, 100.0, 101.0, 100.0),
+            // The bid size grows at the same price.
+            quote(99.0, 150.0, 101.0, 100.0),
+            // The bid improves.
+            quote(99.5, 80.0, 101.0, 100.0),
+            // The ask improves.
+            quote(99.5, 80.0, 100.5, 60.0),
+            // The ask size grows and the bid retreats.
+            quote(99.0, 200.0, 100.5, 90.0),
+            None,
+            quote(99.0, 200.0, 100.5, 90.0),
+        ];
+
+        let ofi = order_flow_increments(&quotes);
+
+        assert_eq!(
+            ofi,
+            vec![
+                None,
+                Some(50.0),
+                Some(80.0),
+                Some(-60.0),
+                Some(-80.0 - 30.0),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rolling_vpin_alternating_buckets() {
+        // Buy-heavy and sell-heavy buckets alternate, then the flow balances.
+        let buy_volume = vec![Some(80.0), Some(20.0), Some(80.0), Some(50.0), Some(50.0)];
+        let sell_volume = vec![Some(20.0), Some(80.0), Some(20.0), Some(50.0), Some(50.0)];
+
+        let vpin = rolling_vpin(&buy_volume, &sell_volume, 2);
+
+        assert_eq!(vpin, vec![None, Some(0.6), Some(0.6), Some(0.3), Some(0.0)]);
+    }
+
+    #[test]
+    fn test_rolling_vpin_missing_bucket() {
+        let buy_volume = vec![Some(80.0), None, Some(80.0), Some(0.0)];
+        let sell_volume = vec![Some(20.0), Some(80.0), Some(20.0), Some(0.0)];
+
+        let vpin = rolling_vpin(&buy_volume, &sell_volume, 1);
+
+        assert_eq!(vpin, vec![Some(0.6), None, Some(0.6), None]);
+    }
+}
@@ -0,0 +1,229 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::black_scholes::{option_contracts, OptionContract};
+use crate::utils::broadcast_inputs;
+
+/// Draw a standard normal variate with the Box-Muller transform.
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    // Shift the first uniform to (0, 1] so its log is finite.
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// The discounted mean payoff of the simulated paths and its standard error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MonteCarloEstimate {
+    pub(crate) price: f64,
+    pub(crate) std_error: f64,
+}
+
+/// Fill `path` with the prices of a geometric Brownian motion driven by `shocks`,
+/// negated for the antithetic path.
+fn fill_path(
+    log_spot: f64,
+    drift: f64,
+    diffusion: f64,
+    shocks: &[f64],
+    sign: f64,
+    path: &mut [f64],
+) {
+    let mut log_price = log_spot;
+    for (price, shock) in path.iter_mut().zip(shocks) {
+        log_price += drift + sign * diffusion * shock;
+        *price = log_price.exp();
+    }
+}
+
+/// Price an option by simulating `n_paths` risk-neutral geometric Brownian motion
+/// paths of `n_steps` equal steps to expiry.
+///
+/// `path_payoff` receives the prices at the end of each step, excluding the
+/// spot. With `antithetic`, each path is paired with the path of its negated
+/// shocks and the pair's mean payoff is one sample, so `n_paths / 2` sets of
+/// shocks are drawn.
+pub(crate) fn simulate_price(
+    contract: &OptionContract,
+    n_paths: usize,
+    n_steps: usize,
+    antithetic: bool,
+    rng: &mut StdRng,
+    path_payoff: impl Fn(&[f64]) -> f64,
+) -> MonteCarloEstimate {
+    let dt = contract.t / n_steps as f64;
+    let drift = (contract.rate - contract.sigma.powi(2) / 2.0) * dt;
+    let diffusion = contract.sigma * dt.sqrt();
+    let log_spot = contract.spot.ln();
+    let n_samples = if antithetic {
+        n_paths.div_ceil(2)
+    } else {
+        n_paths
+    };
+    let mut shocks = vec![0.0; n_steps];
+    let mut path = vec![0.0; n_steps];
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for _ in 0..n_samples {
+        shocks.iter_mut().for_each(|z| *z = standard_normal(rng));
+        fill_path(log_spot, drift, diffusion, &shocks, 1.0, &mut path);
+        let mut payoff = path_payoff(&path);
+        if antithetic {
+            fill_path(log_spot, drift, diffusion, &shocks, -1.0, &mut path);
+            payoff = (payoff + path_payoff(&path)) / 2.0;
+        }
+        sum += payoff;
+        sum_sq += payoff * payoff;
+    }
+    let n = n_samples as f64;
+    let mean = sum / n;
+    let variance = (sum_sq - n * mean * mean).max(0.0) / (n - 1.0).max(1.0);
+    let discount = contract.discount();
+    MonteCarloEstimate {
+        price: discount * mean,
+        std_error: discount * (variance / n).sqrt(),
+    }
+}
+
+/// Price a European option by Monte Carlo simulation.
+fn european_price(
+    contract: &OptionContract,
+    n_paths: usize,
+    n_steps: usize,
+    antithetic: bool,
+    rng: &mut StdRng,
+) -> MonteCarloEstimate {
+    simulate_price(contract, n_paths, n_steps, antithetic, rng, |path| {
+        contract
+            .option_type
+            .payoff(path[path.len() - 1], contract.strike)
+    })
+}
+
+/// Price an arithmetic average Asian option by Monte Carlo simulation.
+///
+/// The payoff is that of the option on the mean price at the end of each of the
+/// `n_steps` steps, discounted like a European payoff.
+fn asian_price(
+    contract: &OptionContract,
+    n_paths: usize,
+    n_steps: usize,
+    antithetic: bool,
+    rng: &mut StdRng,
+) -> MonteCarloEstimate {
+    simulate_price(contract, n_paths, n_steps, antithetic, rng, |path| {
+        let average = path.iter().sum::<f64>() / path.len() as f64;
+        contract.option_type.payoff(average, contract.strike)
+    })
+}
+
+#[derive(Deserialize)]
+struct MonteCarloKwargs {
+    n_paths: usize,
+    n_steps: usize,
+    antithetic: bool,
+    seed: Option<u64>,
+}
+
+impl MonteCarloKwargs {
+    fn rng(&self) -> PolarsResult<StdRng> {
+        if self.n_paths < 2 || self.n_steps == 0 {
+            return Err(PolarsError::ComputeError(
+                "n_paths must be at least 2 and n_steps positive".into(),
+            ));
+        }
+        Ok(match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        })
+    }
+}
+
+#[polars_expr(output_type=Float64)]
+fn monte_carlo_price(inputs: &[Series], kwargs: MonteCarloKwargs) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let mut rng = kwargs.rng()?;
+    let prices: Vec<Option<f64>> = option_contracts(inputs)?
+        .iter()
+        .map(|contract| {
+            let estimate = european_price(
+                contract.as_ref()?,
+                kwargs.n_paths,
+                kwargs.n_steps,
+                kwargs.antithetic,
+                &mut rng,
+            );
+            Some(estimate.price)
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &prices).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn asian_option(inputs: &[Series], kwargs: MonteCarloKwargs) -> PolarsResult<Series> {
+    let inputs = &broadcast_inputs(inputs)?;
+    let mut rng = kwargs.rng()?;
+    let prices: Vec<Option<f64>> = option_contracts(inputs)?
+        .iter()
+        .map(|contract| {
+            let estimate = asian_price(
+                contract.as_ref()?,
+                kwargs.n_paths,
+                kwargs.n_steps,
+                kwargs.antithetic,
+                &mut rng,
+            );
+            Some(estimate.price)
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &prices).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::black_scholes::{black_scholes_price, OptionType};
+
+    #[test]
+    fn test_european_price_converges_to_black_scholes() {
+        let call = OptionContract::new(100.0, 100.0, 0.05, 0.2, 1.0, OptionType::Call).unwrap();
+        let put = OptionContract {
+            option_type: OptionType::Put,
+            ..call
+        };
+
+        for contract in [call, put] {
+            let estimate =
+                european_price(&contract, 50_000, 4, true, &mut StdRng::seed_from_u64(7));
+            let error = (estimate.price - black_scholes_price(&contract)).abs();
+            assert!(error < 4.0 * estimate.std_error, "{error} vs {estimate:?}");
+        }
+
+        let plain = european_price(&call, 20_000, 1, false, &mut StdRng::seed_from_u64(7));
+        let antithetic = european_price(&call, 20_000, 1, true, &mut StdRng::seed_from_u64(7));
+        assert!(antithetic.std_error < plain.std_error);
+        assert_eq!(
+            european_price(&call, 1_000, 3, true, &mut StdRng::seed_from_u64(1)),
+            european_price(&call, 1_000, 3, true, &mut StdRng::seed_from_u64(1))
+        );
+    }
+
+    #[test]
+    fn test_asian_price_cheaper_than_european() {
+        let call = OptionContract::new(100.0, 100.0, 0.05, 0.2, 1.0, OptionType::Call).unwrap();
+
+        let asian = asian_price(&call, 20_000, 12, true, &mut StdRng::seed_from_u64(7));
+        let european = european_price(&call, 20_000, 12, true, &mut StdRng::seed_from_u64(7));
+
+        assert!(asian.price > 0.0);
+        assert!(asian.price + 4.0 * asian.std_error < european.price);
+        // The average of a single step is the price at expiry.
+        assert_eq!(
+            asian_price(&call, 1_000, 1, false, &mut StdRng::seed_from_u64(1)),
+            european_price(&call, 1_000, 1, false, &mut StdRng::seed_from_u64(1))
+        );
+    }
+}
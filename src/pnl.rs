@@ -0,0 +1,330 @@
+#![allow(clippy::unused_unit)]
+use std::collections::VecDeque;
+
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+/// A flat-to-flat trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RoundTrip {
+    entry_idx: usize,
+    exit_idx: usize,
+    pnl: f64,
+    /// The PnL as a fraction of the notional of the fills that opened or added to
+    /// the position.
+    ret: f64,
+}
+
+/// Group signed fills into round trips from flat to flat.
+///
+/// Positions are carried at their average entry price, so a fill that reduces
+/// the position realizes `closed quantity * (price - average price)` in the
+/// direction of the position. A fill that flips the position closes the trip at
+/// that fill and opens the next one with the remaining quantity. Fills with a
+/// null or zero quantity or a null price are skipped, and a trip still open
+/// after the last fill is not returned.
+fn round_trips_of(quantities: &[Option<f64>], prices: &[Option<f64>]) -> Vec<RoundTrip> {
+    let mut trips = Vec::new();
+    let (mut position, mut average_price, mut pnl, mut notional) = (0.0_f64, 0.0, 0.0, 0.0);
+    let mut entry_idx = 0;
+    for (i, (quantity, price)) in quantities.iter().zip(prices).enumerate() {
+        let (Some(mut quantity), Some(price)) = (*quantity, *price) else {
+            continue;
+        };
+        if quantity == 0.0 {
+            continue;
+        }
+        if position != 0.0 && position.signum() != quantity.signum() {
+            let closed = quantity.abs().min(position.abs());
+            pnl += closed * (price - average_price) * position.signum();
+            position += closed * quantity.signum();
+            quantity -= closed * quantity.signum();
+            if position.abs() > f64::EPSILON {
+                continue;
+            }
+            trips.push(RoundTrip {
+                entry_idx,
+                exit_idx: i,
+                pnl,
+                ret: pnl / notional,
+            });
+            (position, pnl, notional) = (0.0, 0.0, 0.0);
+            if quantity == 0.0 {
+                continue;
+            }
+        }
+        if position == 0.0 {
+            entry_idx = i;
+        }
+        average_price = (average_price * position.abs() + price * quantity.abs())
+            / (position.abs() + quantity.abs());
+        notional += price * quantity.abs();
+        position += quantity;
+    }
+    trips
+}
+
+/// How a fill that reduces the position is matched against the open lots.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LotMethod {
+    /// Close the oldest lots first.
+    Fifo,
+    /// Close the newest lots first.
+    Lifo,
+    /// Merge the lots into one at their average price.
+    Average,
+}
+
+impl LotMethod {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fifo" => Some(Self::Fifo),
+            "lifo" => Some(Self::Lifo),
+            "average" => Some(Self::Average),
+            _ => None,
+        }
+    }
+}
+
+/// The realized PnL of a fill and the average price of the lots left open.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FillAccounting {
+    realized_pnl: f64,
+    /// `None` when the position is flat.
+    avg_cost: Option<f64>,
+}
+
+/// Match each signed fill against the open lots with `method`.
+///
+/// A fill against the position closes lots, oldest or newest first, realizing
+/// `closed quantity * (price - lot price)` in the direction of the position. A
+/// fill that flips the position closes every lot and opens a new one with the
+/// remainder. Fills with a null quantity or price are `None` and leave the lots
+/// unchanged.
+fn account_fills(
+    quantities: &[Option<f64>],
+    prices: &[Option<f64>],
+    method: LotMethod,
+) -> Vec<Option<FillAccounting>> {
+    // The open lots as (signed quantity, price), oldest first.
+    let mut lots: VecDeque<(f64, f64)> = VecDeque::new();
+    quantities
+        .iter()
+        .zip(prices)
+        .map(|(quantity, price)| {
+            let (mut quantity, price) = ((*quantity)?, (*price)?);
+            let mut realized_pnl = 0.0;
+            while quantity != 0.0 {
+                let lot = match method {
+                    LotMethod::Lifo => lots.back_mut(),
+                    LotMethod::Fifo | LotMethod::Average => lots.front_mut(),
+                };
+                let Some(lot) = lot.filter(|lot| lot.0.signum() != quantity.signum()) else {
+                    break;
+                };
+                let closed = quantity.abs().min(lot.0.abs());
+                realized_pnl += closed * (price - lot.1) * lot.0.signum();
+                lot.0 += closed * quantity.signum();
+                quantity -= closed * quantity.signum();
+                if lot.0.abs() <= f64::EPSILON {
+                    match method {
+                        LotMethod::Lifo => lots.pop_back(),
+                        LotMethod::Fifo | LotMethod::Average => lots.pop_front(),
+                    };
+                }
+            }
+            // Whatever is left of the fill adds to the position.
+            match (method, lots.front_mut()) {
+                (LotMethod::Average, Some(lot)) => {
+                    lot.1 = (lot.0 * lot.1 + quantity * price) / (lot.0 + quantity);
+                    lot.0 += quantity;
+                },
+                _ if quantity != 0.0 => lots.push_back((quantity, price)),
+                _ => {},
+            }
+            let position: f64 = lots.iter().map(|lot| lot.0).sum();
+            let cost: f64 = lots.iter().map(|lot| lot.0 * lot.1).sum();
+            Some(FillAccounting {
+                realized_pnl,
+                avg_cost: (!lots.is_empty()).then(|| cost / position),
+            })
+        })
+        .collect()
+}
+
+fn round_trips_list(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::List(Box::new(DataType::Struct(vec![
+            Field::new("entry_idx".into(), DataType::Int64),
+            Field::new("exit_idx".into(), DataType::Int64),
+            Field::new("pnl".into(), DataType::Float64),
+            Field::new("return".into(), DataType::Float64),
+        ]))),
+    ))
+}
+
+#[polars_expr(output_type_func=round_trips_list)]
+fn round_trips(inputs: &[Series]) -> PolarsResult<Series> {
+    let quantities: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let prices: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let trips = round_trips_of(&quantities, &prices);
+    let fields = [
+        Int64Chunked::from_vec(
+            "entry_idx".into(),
+            trips.iter().map(|trip| trip.entry_idx as i64).collect(),
+        )
+        .into_series(),
+        Int64Chunked::from_vec(
+            "exit_idx".into(),
+            trips.iter().map(|trip| trip.exit_idx as i64).collect(),
+        )
+        .into_series(),
+        Float64Chunked::from_vec("pnl".into(), trips.iter().map(|trip| trip.pnl).collect())
+            .into_series(),
+        Float64Chunked::from_vec("return".into(), trips.iter().map(|trip| trip.ret).collect())
+            .into_series(),
+    ];
+    let table = StructChunked::from_series("".into(), trips.len(), fields.iter())?.into_series();
+    Ok(std::iter::once(Some(table))
+        .collect::<ListChunked>()
+        .with_name(inputs[0].name().clone())
+        .into_series())
+}
+
+#[derive(Deserialize)]
+struct CostBasisKwargs {
+    method: String,
+}
+
+fn cost_basis_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(vec![
+            Field::new("realized_pnl".into(), DataType::Float64),
+            Field::new("avg_cost".into(), DataType::Float64),
+        ]),
+    ))
+}
+
+#[polars_expr(output_type_func=cost_basis_struct)]
+fn cost_basis(inputs: &[Series], kwargs: CostBasisKwargs) -> PolarsResult<Series> {
+    let method = LotMethod::from_name(&kwargs.method).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown method: {}", kwargs.method).into())
+    })?;
+    let quantities: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let prices: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let fills = account_fills(&quantities, &prices, method);
+
+    let realized: Vec<Option<f64>> = fills.iter().map(|f| f.map(|f| f.realized_pnl)).collect();
+    let avg_costs: Vec<Option<f64>> = fills.iter().map(|f| f.and_then(|f| f.avg_cost)).collect();
+    let fields = [
+        Float64Chunked::new("realized_pnl".into(), &realized).into_series(),
+        Float64Chunked::new("avg_cost".into(), &avg_costs).into_series(),
+    ];
+    Ok(
+        StructChunked::from_series(inputs[0].name().clone(), fills.len(), fields.iter())?
+            .into_series(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_trip(trip: &RoundTrip, entry_idx: usize, exit_idx: usize, pnl: f64, ret: f64) {
+        assert_eq!((trip.entry_idx, trip.exit_idx), (entry_idx, exit_idx));
+        assert!((trip.pnl - pnl).abs() < 1e-9, "{trip:?}");
+        assert!((trip.ret - ret).abs() < 1e-12, "{trip:?}");
+    }
+
+    #[test]
+    fn test_round_trips_long_then_flip_to_short() {
+        // Buy 10 @ 100 and 10 @ 110, sell 5 @ 120, then sell 25 @ 115 to flip
+        // short 10, which is covered @ 105. The last buy is never closed.
+        let quantities = [
+            Some(10.0),
+            Some(10.0),
+            Some(-5.0),
+            None,
+            Some(-25.0),
+            Some(10.0),
+            Some(5.0),
+        ];
+        let prices = [
+            Some(100.0),
+            Some(110.0),
+            Some(120.0),
+            Some(130.0),
+            Some(115.0),
+            Some(105.0),
+            Some(100.0),
+        ];
+
+        let trips = round_trips_of(&quantities, &prices);
+
+        assert_eq!(trips.len(), 2);
+        // 5 * (120 - 105) + 15 * (115 - 105) on 2100 of buys.
+        assert_trip(&trips[0], 0, 4, 225.0, 225.0 / 2100.0);
+        // 10 * (115 - 105) on 1150 of sales.
+        assert_trip(&trips[1], 4, 5, 100.0, 100.0 / 1150.0);
+        assert!(round_trips_of(&[Some(1.0), Some(0.0)], &[Some(1.0); 2]).is_empty());
+    }
+
+    #[test]
+    fn test_account_fills_fifo_vs_lifo() {
+        // Buy 10 @ 100 and 10 @ 120, sell 10 @ 130, then sell 15 @ 110 to go
+        // short 5.
+        let quantities = [Some(10.0), Some(10.0), Some(-10.0), None, Some(-15.0)];
+        let prices = [Some(100.0), Some(120.0), Some(130.0), None, Some(110.0)];
+        let realized = |method| -> Vec<Option<(f64, Option<f64>)>> {
+            account_fills(&quantities, &prices, method)
+                .iter()
+                .map(|f| f.map(|f| (f.realized_pnl, f.avg_cost)))
+                .collect()
+        };
+
+        // FIFO sells the lot bought @ 100 first, LIFO the lot bought @ 120.
+        assert_eq!(
+            realized(LotMethod::Fifo),
+            vec![
+                Some((0.0, Some(100.0))),
+                Some((0.0, Some(110.0))),
+                Some((300.0, Some(120.0))),
+                None,
+                Some((-100.0, Some(110.0))),
+            ]
+        );
+        assert_eq!(
+            realized(LotMethod::Lifo),
+            vec![
+                Some((0.0, Some(100.0))),
+                Some((0.0, Some(110.0))),
+                Some((100.0, Some(100.0))),
+                None,
+                Some((100.0, Some(110.0))),
+            ]
+        );
+        assert_eq!(
+            realized(LotMethod::Average),
+            vec![
+                Some((0.0, Some(100.0))),
+                Some((0.0, Some(110.0))),
+                Some((200.0, Some(110.0))),
+                None,
+                Some((0.0, Some(110.0))),
+            ]
+        );
+        // The total realized PnL over a flat-to-flat sequence does not depend on
+        // the method.
+        let flat = [Some(10.0), Some(10.0), Some(-10.0), Some(-10.0)];
+        let flat_prices = [Some(100.0), Some(120.0), Some(130.0), Some(110.0)];
+        for method in [LotMethod::Fifo, LotMethod::Lifo, LotMethod::Average] {
+            let fills = account_fills(&flat, &flat_prices, method);
+            let total: f64 = fills.iter().map(|f| f.unwrap().realized_pnl).sum();
+            assert_eq!(total, 200.0);
+            assert_eq!(fills[3].unwrap().avg_cost, None);
+        }
+    }
+}
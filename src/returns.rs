@@ -0,0 +1,202 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::utils::broadcast_inputs;
+
+/// Calculate the log return `log(p_t / p_{t-1})` of each price in `base`, or
+/// the natural log if there is no base.
+///
+/// The first price has no previous price and is `fill_first`. Steps from or to a
+/// null or non-positive price are `None`.
+fn log_returns_of(
+    prices: &[Option<f64>],
+    base: Option<f64>,
+    fill_first: Option<f64>,
+) -> Vec<Option<f64>> {
+    let scale = base.map_or(1.0, f64::ln);
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, price)| {
+            if i == 0 {
+                return fill_first;
+            }
+            let (price, previous) = ((*price)?, prices[i - 1]?);
+            (price > 0.0 && previous > 0.0).then(|| (price / previous).ln() / scale)
+        })
+        .collect()
+}
+
+/// How per-period returns compound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReturnMethod {
+    /// `∏(1 + r)`
+    Simple,
+    /// `exp(Σr)`
+    Log,
+}
+
+impl ReturnMethod {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "simple" => Some(Self::Simple),
+            "log" => Some(Self::Log),
+            _ => None,
+        }
+    }
+}
+
+/// Build the equity curve of `starting_value` invested at the start of the
+/// returns. A null return leaves the equity unchanged.
+fn equity_curve(returns: &[Option<f64>], method: ReturnMethod, starting_value: f64) -> Vec<f64> {
+    let mut equity = starting_value;
+    returns
+        .iter()
+        .map(|ret| {
+            if let Some(ret) = ret {
+                equity *= match method {
+                    ReturnMethod::Simple => 1.0 + ret,
+                    ReturnMethod::Log => ret.exp(),
+                };
+            }
+            equity
+        })
+        .collect()
+}
+
+/// Calculate the cost in return terms of trading `turnover`, the change in
+/// position as a fraction of capital, at `commission_bps` plus half of
+/// `spread_bps`.
+fn transaction_cost(turnover: f64, commission_bps: f64, spread_bps: f64) -> f64 {
+    turnover.abs() * (commission_bps + spread_bps / 2.0) / 10_000.0
+}
+
+#[derive(Deserialize)]
+struct LogReturnsKwargs {
+    base: Option<f64>,
+    fill_first: Option<f64>,
+}
+
+#[polars_expr(output_type=Float64)]
+fn log_returns(inputs: &[Series], kwargs: LogReturnsKwargs) -> PolarsResult<Series> {
+    if kwargs.base.is_some_and(|base| base <= 0.0 || base == 1.0) {
+        return Err(PolarsError::ComputeError(
+            "base must be positive and not 1".into(),
+        ));
+    }
+    let prices: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let returns = log_returns_of(&prices, kwargs.base, kwargs.fill_first);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &returns).into_series())
+}
+
+#[derive(Deserialize)]
+struct CumulativeReturnsKwargs {
+    method: String,
+    starting_value: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn cumulative_returns(inputs: &[Series], kwargs: CumulativeReturnsKwargs) -> PolarsResult<Series> {
+    let method = ReturnMethod::from_name(&kwargs.method).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown method: {}", kwargs.method).into())
+    })?;
+    let returns: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let equity = equity_curve(&returns, method, kwargs.starting_value);
+    Ok(Float64Chunked::from_vec(inputs[0].name().clone(), equity).into_series())
+}
+
+#[derive(Deserialize)]
+struct ApplyCostsKwargs {
+    commission_bps: f64,
+    spread_bps: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn apply_costs(inputs: &[Series], kwargs: ApplyCostsKwargs) -> PolarsResult<Series> {
+    if kwargs.commission_bps < 0.0 || kwargs.spread_bps < 0.0 {
+        return Err(PolarsError::ComputeError(
+            "commission_bps and spread_bps must be non-negative".into(),
+        ));
+    }
+    let inputs = &broadcast_inputs(inputs)?;
+    let returns = inputs[0].cast(&DataType::Float64)?;
+    let turnover = inputs[1].cast(&DataType::Float64)?;
+    let net: Vec<Option<f64>> = returns
+        .f64()?
+        .iter()
+        .zip(turnover.f64()?.iter())
+        .map(|(ret, turnover)| {
+            Some(ret? - transaction_cost(turnover?, kwargs.commission_bps, kwargs.spread_bps))
+        })
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &net).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_returns_of() {
+        let prices = [Some(100.0), Some(110.0), Some(99.0)];
+
+        let returns = log_returns_of(&prices, None, None);
+        assert_eq!(returns[0], None);
+        assert_eq!(returns[1], Some((1.1_f64).ln()));
+        assert_eq!(returns[2], Some((0.9_f64).ln()));
+        assert_eq!(log_returns_of(&prices, None, Some(0.0))[0], Some(0.0));
+
+        let returns = log_returns_of(&[Some(10.0), Some(100.0)], Some(10.0), Some(0.0));
+        assert!((returns[1].unwrap() - 1.0).abs() < 1e-15);
+        assert!(log_returns_of(&[], None, Some(0.0)).is_empty());
+    }
+
+    #[test]
+    fn test_log_returns_of_non_positive_prices() {
+        let prices = [
+            Some(100.0),
+            Some(0.0),
+            Some(100.0),
+            None,
+            Some(-5.0),
+            Some(50.0),
+            Some(55.0),
+        ];
+
+        assert_eq!(
+            log_returns_of(&prices, None, Some(0.0)),
+            vec![Some(0.0), None, None, None, None, None, Some(1.1_f64.ln())]
+        );
+    }
+
+    #[test]
+    fn test_equity_curve_simple_and_log_agree() {
+        let simple = [Some(0.1), None, Some(-0.2), Some(0.05)];
+        let log: Vec<Option<f64>> = simple.iter().map(|r| r.map(f64::ln_1p)).collect();
+
+        let simple_curve = equity_curve(&simple, ReturnMethod::Simple, 100.0);
+        let log_curve = equity_curve(&log, ReturnMethod::Log, 100.0);
+
+        // The null return carries the equity forward.
+        assert!((simple_curve[0] - 110.0).abs() < 1e-12);
+        assert_eq!(simple_curve[1], simple_curve[0]);
+        assert!((simple_curve[3] - 92.4).abs() < 1e-12);
+        for (s, l) in simple_curve.iter().zip(&log_curve) {
+            assert!((s - l).abs() < 1e-12);
+        }
+        assert_eq!(
+            equity_curve(&[None, Some(0.5)], ReturnMethod::Simple, 1.0),
+            vec![1.0, 1.5]
+        );
+    }
+
+    #[test]
+    fn test_transaction_cost() {
+        // 2bps of commission and half of a 4bps spread on each unit traded.
+        assert_eq!(transaction_cost(0.0, 2.0, 4.0), 0.0);
+        assert!((transaction_cost(0.5, 2.0, 4.0) - 0.0002).abs() < 1e-15);
+        assert!((transaction_cost(-2.0, 2.0, 4.0) - 0.0008).abs() < 1e-15);
+    }
+}
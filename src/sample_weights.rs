@@ -0,0 +1,325 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+
+/// Count the number of labels spanning each bar.
+///
+/// Each span is the inclusive `(start, end)` bar position of a label. The output
+/// covers every bar up to the last end position.
+fn label_concurrency_counts(spans: &[Option<(usize, usize)>]) -> Vec<i64> {
+    let n_bars = spans
+        .iter()
+        .flatten()
+        .map(|&(_, end)| end + 1)
+        .max()
+        .unwrap_or(0);
+    let mut deltas = vec![0i64; n_bars + 1];
+    for &(start, end) in spans.iter().flatten() {
+        deltas[start] += 1;
+        deltas[end + 1] -= 1;
+    }
+    deltas
+        .iter()
+        .take(n_bars)
+        .scan(0, |count, delta| {
+            *count += delta;
+            Some(*count)
+        })
+        .collect()
+}
+
+/// Calculate the average uniqueness of each label over its lifespan.
+///
+/// The uniqueness of a label at a bar is `1 / c_t` where `c_t` is the number of
+/// labels spanning that bar.
+fn average_uniqueness(spans: &[Option<(usize, usize)>]) -> Vec<Option<f64>> {
+    let counts = label_concurrency_counts(spans);
+    spans
+        .iter()
+        .map(|span| {
+            span.map(|(start, end)| {
+                let bars = &counts[start..=end];
+                bars.iter().map(|&c| 1.0 / c as f64).sum::<f64>() / bars.len() as f64
+            })
+        })
+        .collect()
+}
+
+/// Calculate the return attribution weight of each label.
+///
+/// Each bar's log return is shared equally among the labels spanning it, and a
+/// label's weight is the absolute sum of its shares over `(start, end]`. The
+/// weights are scaled to sum to the number of labels unless they are all zero.
+fn return_attribution_weights(
+    prices: &[f64],
+    spans: &[Option<(usize, usize)>],
+) -> Vec<Option<f64>> {
+    let counts = label_concurrency_counts(spans);
+    let weights: Vec<Option<f64>> = spans
+        .iter()
+        .map(|span| {
+            span.map(|(start, end)| {
+                ((start + 1)..=end)
+                    .map(|t| (prices[t] / prices[t - 1]).ln() / counts[t] as f64)
+                    .sum::<f64>()
+                    .abs()
+            })
+        })
+        .collect();
+    let total: f64 = weights.iter().flatten().sum();
+    let n_labels = weights.iter().flatten().count() as f64;
+    let scale = if total > 0.0 { n_labels / total } else { 1.0 };
+    weights.into_iter().map(|w| w.map(|w| w * scale)).collect()
+}
+
+/// Calculate linear time decay weights over an ordered index.
+///
+/// The newest observation has weight 1 and the oldest has weight `last_weight`.
+/// A negative `last_weight` gives zero weight to the oldest `-last_weight`
+/// fraction of the index, decaying linearly to zero before that.
+fn time_decay(index: &[f64], last_weight: f64) -> Vec<f64> {
+    let (Some(&first), Some(&last)) = (index.first(), index.last()) else {
+        return Vec::new();
+    };
+    let span = last - first;
+    index
+        .iter()
+        .map(|&x| {
+            let t = if span > 0.0 { (x - first) / span } else { 1.0 };
+            if last_weight >= 0.0 {
+                last_weight + (1.0 - last_weight) * t
+            } else {
+                (1.0 - (1.0 - t) / (1.0 + last_weight)).max(0.0)
+            }
+        })
+        .collect()
+}
+
+/// Draw label indices with the sequential bootstrap.
+///
+/// Each draw picks a label with probability proportional to its average
+/// uniqueness given the labels already drawn, so that labels overlapping the
+/// sample are less likely to be drawn again. Labels without a span are never
+/// drawn.
+///
+/// Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 63
+fn sequential_bootstrap_indices(
+    spans: &[Option<(usize, usize)>],
+    sample_length: usize,
+    rng: &mut StdRng,
+) -> Vec<usize> {
+    let n_bars = spans
+        .iter()
+        .flatten()
+        .map(|&(_, end)| end + 1)
+        .max()
+        .unwrap_or(0);
+    let mut counts = vec![0usize; n_bars];
+    let mut sample = Vec::with_capacity(sample_length);
+    for _ in 0..sample_length {
+        let uniqueness: Vec<f64> = spans
+            .iter()
+            .map(|span| {
+                span.map_or(0.0, |(start, end)| {
+                    let bars = &counts[start..=end];
+                    bars.iter().map(|&c| 1.0 / (c + 1) as f64).sum::<f64>() / bars.len() as f64
+                })
+            })
+            .collect();
+        let Ok(distribution) = WeightedIndex::new(&uniqueness) else {
+            break;
+        };
+        let i = distribution.sample(rng);
+        let (start, end) = spans[i].expect("labels without a span have zero weight");
+        counts[start..=end].iter_mut().for_each(|c| *c += 1);
+        sample.push(i);
+    }
+    sample
+}
+
+/// Read the start and end position columns into label spans, validating that
+/// every span is a non-negative and ordered pair of bar positions.
+pub(crate) fn to_spans(
+    starts: &Series,
+    ends: &Series,
+) -> PolarsResult<Vec<Option<(usize, usize)>>> {
+    let starts = starts.cast(&DataType::Int64)?;
+    let ends = ends.cast(&DataType::Int64)?;
+    starts
+        .i64()?
+        .iter()
+        .zip(ends.i64()?.iter())
+        .map(|(start, end)| match (start, end) {
+            (Some(start), Some(end)) if 0 <= start && start <= end => {
+                Ok(Some((start as usize, end as usize)))
+            },
+            (Some(_), Some(_)) => Err(PolarsError::ComputeError(
+                "Label spans must satisfy 0 <= start <= end".into(),
+            )),
+            _ => Ok(None),
+        })
+        .collect()
+}
+
+#[polars_expr(output_type=Int64)]
+fn label_concurrency(inputs: &[Series]) -> PolarsResult<Series> {
+    let spans = to_spans(&inputs[0], &inputs[1])?;
+    let n_bars = inputs[0].len();
+    if spans.iter().flatten().any(|&(_, end)| end >= n_bars) {
+        return Err(PolarsError::ComputeError(
+            "Label end position is beyond the last bar".into(),
+        ));
+    }
+    let mut counts = label_concurrency_counts(&spans);
+    counts.resize(n_bars, 0);
+    Ok(Int64Chunked::from_vec("label_concurrency".into(), counts).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn label_uniqueness(inputs: &[Series]) -> PolarsResult<Series> {
+    let spans = to_spans(&inputs[0], &inputs[1])?;
+    Ok(Float64Chunked::new("label_uniqueness".into(), &average_uniqueness(&spans)).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn label_return_weights(inputs: &[Series]) -> PolarsResult<Series> {
+    let prices = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let spans = to_spans(&inputs[1], &inputs[2])?;
+    if spans.iter().flatten().any(|&(_, end)| end >= prices.len()) {
+        return Err(PolarsError::ComputeError(
+            "Label end position is beyond the end of the prices".into(),
+        ));
+    }
+    let weights = return_attribution_weights(&prices, &spans);
+    Ok(Float64Chunked::new("label_return_weights".into(), &weights).into_series())
+}
+
+#[derive(Deserialize)]
+struct TimeDecayKwargs {
+    last_weight: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn time_decay_weights(inputs: &[Series], kwargs: TimeDecayKwargs) -> PolarsResult<Series> {
+    if kwargs.last_weight <= -1.0 || kwargs.last_weight > 1.0 {
+        return Err(PolarsError::ComputeError(
+            "last_weight must be in (-1, 1]".into(),
+        ));
+    }
+    let index = inputs[0]
+        .to_physical_repr()
+        .cast(&DataType::Float64)?
+        .f64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null index found".into()))?;
+    let weights = time_decay(&index, kwargs.last_weight);
+    Ok(Float64Chunked::from_vec("time_decay_weights".into(), weights).into_series())
+}
+
+#[derive(Deserialize)]
+struct SequentialBootstrapKwargs {
+    sample_length: Option<usize>,
+    seed: Option<u64>,
+}
+
+#[polars_expr(output_type=Int64)]
+fn sequential_bootstrap(
+    inputs: &[Series],
+    kwargs: SequentialBootstrapKwargs,
+) -> PolarsResult<Series> {
+    let spans = to_spans(&inputs[0], &inputs[1])?;
+    let sample_length = kwargs.sample_length.unwrap_or(spans.len());
+    let mut rng = match kwargs.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let sample: Vec<i64> = sequential_bootstrap_indices(&spans, sample_length, &mut rng)
+        .into_iter()
+        .map(|i| i as i64)
+        .collect();
+    Ok(Int64Chunked::from_vec("sequential_bootstrap".into(), sample).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_uniqueness_overlapping_and_isolated() {
+        let spans = vec![Some((0, 2)), Some((1, 3)), Some((5, 6)), None];
+
+        assert_eq!(label_concurrency_counts(&spans), vec![1, 2, 2, 1, 0, 1, 1]);
+
+        let uniqueness = average_uniqueness(&spans);
+        assert!((uniqueness[0].unwrap() - 2.0 / 3.0).abs() < 1e-12);
+        assert!((uniqueness[1].unwrap() - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(uniqueness[2], Some(1.0));
+        assert_eq!(uniqueness[3], None);
+    }
+
+    #[test]
+    fn test_label_concurrency_counts_three_events() {
+        // The first event overlaps the second on bars 2 and 3, and the second the
+        // third on bar 4.
+        let spans = [Some((0, 3)), Some((2, 4)), Some((4, 5))];
+
+        assert_eq!(label_concurrency_counts(&spans), vec![1, 1, 2, 2, 2, 1]);
+        assert_eq!(
+            label_concurrency_counts(&[Some((1, 1)), Some((1, 2)), Some((1, 1))]),
+            vec![0, 3, 1]
+        );
+        assert!(label_concurrency_counts(&[None]).is_empty());
+    }
+
+    #[test]
+    fn test_return_attribution_weights() {
+        let prices = vec![100.0, 101.0, 100.5, 101.0, 100.0, 120.0];
+        // Two overlapping labels over small moves and one isolated large move.
+        let spans = vec![Some((0, 2)), Some((1, 3)), Some((4, 5))];
+
+        let weights: Vec<f64> = return_attribution_weights(&prices, &spans)
+            .into_iter()
+            .flatten()
+            .collect();
+
+        assert!(weights[2] > weights[0]);
+        assert!(weights[2] > weights[1]);
+        assert!((weights.iter().sum::<f64>() - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_time_decay() {
+        let index = vec![0.0, 1.0, 2.0, 3.0, 4.0];
+
+        assert_eq!(time_decay(&index, 1.0), vec![1.0; 5]);
+        assert_eq!(time_decay(&index, 0.0), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(time_decay(&index, 0.5), vec![0.5, 0.625, 0.75, 0.875, 1.0]);
+        // The oldest half of the index is clipped to zero.
+        assert_eq!(time_decay(&index, -0.5), vec![0.0, 0.0, 0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_sequential_bootstrap_deterministic_with_seed() {
+        let spans = vec![Some((0, 2)), Some((2, 3)), Some((4, 5)), None];
+
+        let sample = sequential_bootstrap_indices(&spans, 20, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(sample.len(), 20);
+        assert!(sample.iter().all(|&i| i < 3));
+        assert_eq!(
+            sample,
+            sequential_bootstrap_indices(&spans, 20, &mut StdRng::seed_from_u64(7))
+        );
+        assert!(sequential_bootstrap_indices(&[None], 3, &mut StdRng::seed_from_u64(7)).is_empty());
+    }
+}
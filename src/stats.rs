@@ -0,0 +1,1015 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+use crate::math;
+
+/// The result of an ordinary least squares regression.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OlsFit {
+    pub coefficients: Vec<f64>,
+    pub std_errors: Vec<f64>,
+    pub ssr: f64,
+}
+
+/// Invert a square matrix with Gauss-Jordan elimination and partial pivoting.
+///
+/// Returns `None` if the matrix is singular.
+pub(crate) fn invert(mut matrix: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let scale = matrix[col][col];
+        for j in 0..n {
+            matrix[col][j] /= scale;
+            inverse[col][j] /= scale;
+        }
+        for row in 0..n {
+            if row == col {
+                continue;
+            }
+            let factor = matrix[row][col];
+            for j in 0..n {
+                matrix[row][j] -= factor * matrix[col][j];
+                inverse[row][j] -= factor * inverse[col][j];
+            }
+        }
+    }
+    Some(inverse)
+}
+
+/// Fit `y = X b` by ordinary least squares, where each row of `x` is one
+/// observation of the regressors.
+///
+/// Returns `None` if there are no more observations than regressors or the
+/// regressors are collinear.
+pub(crate) fn ols(x: &[Vec<f64>], y: &[f64]) -> Option<OlsFit> {
+    let k = x.first()?.len();
+    let n = y.len();
+    if n <= k {
+        return None;
+    }
+    let xtx: Vec<Vec<f64>> = (0..k)
+        .map(|i| {
+            (0..k)
+                .map(|j| x.iter().map(|row| row[i] * row[j]).sum())
+                .collect()
+        })
+        .collect();
+    let xty: Vec<f64> = (0..k)
+        .map(|i| x.iter().zip(y).map(|(row, y)| row[i] * y).sum())
+        .collect();
+    let xtx_inv = invert(xtx)?;
+    let coefficients: Vec<f64> = xtx_inv
+        .iter()
+        .map(|row| row.iter().zip(&xty).map(|(a, b)| a * b).sum())
+        .collect();
+    let ssr: f64 = x
+        .iter()
+        .zip(y)
+        .map(|(row, y)| {
+            let fitted: f64 = row.iter().zip(&coefficients).map(|(a, b)| a * b).sum();
+            (y - fitted).powi(2)
+        })
+        .sum();
+    let sigma2 = ssr / (n - k) as f64;
+    let std_errors = (0..k).map(|i| (sigma2 * xtx_inv[i][i]).sqrt()).collect();
+    Some(OlsFit {
+        coefficients,
+        std_errors,
+        ssr,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AdfResult {
+    pub statistic: f64,
+    pub used_lag: usize,
+}
+
+/// Fit the ADF regression `Δy_t = a + b y_{t-1} + sum_i c_i Δy_{t-i}` with `lag`
+/// lagged differences, using the differences from position `start` onwards.
+fn adf_regression(values: &[f64], lag: usize, start: usize) -> Option<OlsFit> {
+    let diffs: Vec<f64> = values.windows(2).map(|w| w[1] - w[0]).collect();
+    let (x, y): (Vec<Vec<f64>>, Vec<f64>) = (start.max(lag)..diffs.len())
+        .map(|t| {
+            let mut row = vec![1.0, values[t]];
+            row.extend((1..=lag).map(|i| diffs[t - i]));
+            (row, diffs[t])
+        })
+        .unzip();
+    ols(&x, &y)
+}
+
+/// Run the Augmented Dickey-Fuller test with a constant.
+///
+/// The number of lagged differences is chosen from `0..=max_lag` by minimising the
+/// AIC over a common sample, then the regression is refit with the chosen lag on
+/// all available observations. The statistic is the t-value of the coefficient on
+/// `y_{t-1}`, so more negative values are stronger evidence of stationarity.
+pub(crate) fn adf(values: &[f64], max_lag: usize) -> Option<AdfResult> {
+    let used_lag = (0..=max_lag)
+        .filter_map(|lag| {
+            let fit = adf_regression(values, lag, max_lag)?;
+            let n = (values.len() - 1 - max_lag) as f64;
+            let k = (lag + 2) as f64;
+            Some((lag, n * (fit.ssr / n).ln() + 2.0 * k))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))?
+        .0;
+    Some(AdfResult {
+        statistic: adf_statistic(values, used_lag)?,
+        used_lag,
+    })
+}
+
+/// Calculate the ADF statistic with a fixed number of lagged differences.
+pub(crate) fn adf_statistic(values: &[f64], lag: usize) -> Option<f64> {
+    let fit = adf_regression(values, lag, lag)?;
+    Some(fit.coefficients[1] / fit.std_errors[1])
+}
+
+/// Estimate the Hurst exponent from the scaling of lagged differences.
+///
+/// The standard deviation of `y_{t+lag} - y_t` grows like `lag^H`, so `H` is the
+/// slope of a log-log fit over lags `1..=max_lag`. `H > 0.5` indicates a trending
+/// series, `H = 0.5` a random walk and `H < 0.5` a mean-reverting series.
+pub(crate) fn hurst(values: &[f64], max_lag: usize) -> Option<f64> {
+    let (x, y): (Vec<Vec<f64>>, Vec<f64>) = (1..=max_lag)
+        .filter(|&lag| lag + 1 < values.len())
+        .filter_map(|lag| {
+            let diffs: Vec<f64> = values.windows(lag + 1).map(|w| w[lag] - w[0]).collect();
+            let mean = diffs.iter().sum::<f64>() / diffs.len() as f64;
+            let variance =
+                diffs.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / diffs.len() as f64;
+            (variance > 0.0).then(|| (vec![1.0, (lag as f64).ln()], 0.5 * variance.ln()))
+        })
+        .unzip();
+    Some(ols(&x, &y)?.coefficients[1])
+}
+
+/// The exponentially weighted moments of a series up to and including a value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EwmMoments {
+    pub mean: f64,
+    /// `None` when the bias corrected variance is undefined, i.e. after a single
+    /// observation.
+    pub variance: Option<f64>,
+    /// The number of non-null observations so far.
+    pub count: usize,
+}
+
+/// Calculate the exponentially weighted mean and variance at every value.
+///
+/// The weight of the value `i` steps back is `(1 - alpha)^i`, normalised by the
+/// sum of the weights, so the first values are not biased towards zero. The
+/// weighted variance is bias corrected by `(sum w)^2 / ((sum w)^2 - sum w^2)`
+/// unless `bias` is true. Nulls are `None` but still decay the weights of the
+/// values before them.
+pub(crate) fn ewm_moments(
+    values: &[Option<f64>],
+    alpha: f64,
+    bias: bool,
+) -> Vec<Option<EwmMoments>> {
+    let decay = 1.0 - alpha;
+    let (mut sum_w, mut sum_w2, mut mean, mut variance, mut count) = (0.0, 0.0, 0.0, 0.0, 0);
+    values
+        .iter()
+        .map(|value| {
+            sum_w *= decay;
+            sum_w2 *= decay * decay;
+            let value = (*value)?;
+            let new_mean = (sum_w * mean + value) / (sum_w + 1.0);
+            variance = (sum_w * (variance + (mean - new_mean).powi(2))
+                + (value - new_mean).powi(2))
+                / (sum_w + 1.0);
+            mean = new_mean;
+            sum_w += 1.0;
+            sum_w2 += 1.0;
+            count += 1;
+            let correction = sum_w * sum_w - sum_w2;
+            Some(EwmMoments {
+                mean,
+                variance: if bias {
+                    Some(variance)
+                } else {
+                    (correction > 0.0).then(|| variance * sum_w * sum_w / correction)
+                },
+                count,
+            })
+        })
+        .collect()
+}
+
+/// Calculate the exponentially weighted correlation of two series at every pair.
+///
+/// The means, variances and covariance are weighted as in `ewm_moments` over the
+/// pairs where both values are present. The bias corrections cancel, so none is
+/// applied. Pairs with a null are `None` but still decay the weights, and the
+/// correlation is `None` until `min_periods` pairs have been seen or while either
+/// series has no variance.
+fn ewm_correlation(
+    x: &[Option<f64>],
+    y: &[Option<f64>],
+    alpha: f64,
+    min_periods: usize,
+) -> Vec<Option<f64>> {
+    let decay = 1.0 - alpha;
+    let (mut sum_w, mut mean_x, mut mean_y) = (0.0, 0.0, 0.0);
+    let (mut var_x, mut var_y, mut cov, mut count) = (0.0, 0.0, 0.0, 0);
+    x.iter()
+        .zip(y)
+        .map(|(x, y)| {
+            sum_w *= decay;
+            let (x, y) = ((*x)?, (*y)?);
+            let new_mean_x = (sum_w * mean_x + x) / (sum_w + 1.0);
+            let new_mean_y = (sum_w * mean_y + y) / (sum_w + 1.0);
+            let (dx, dy) = (mean_x - new_mean_x, mean_y - new_mean_y);
+            var_x = (sum_w * (var_x + dx * dx) + (x - new_mean_x).powi(2)) / (sum_w + 1.0);
+            var_y = (sum_w * (var_y + dy * dy) + (y - new_mean_y).powi(2)) / (sum_w + 1.0);
+            cov = (sum_w * (cov + dx * dy) + (x - new_mean_x) * (y - new_mean_y)) / (sum_w + 1.0);
+            (mean_x, mean_y) = (new_mean_x, new_mean_y);
+            sum_w += 1.0;
+            count += 1;
+            (count >= min_periods && var_x > 0.0 && var_y > 0.0)
+                .then(|| (cov / (var_x * var_y).sqrt()).clamp(-1.0, 1.0))
+        })
+        .collect()
+}
+
+/// The fit of a simple linear regression `y = alpha + beta x`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LinearFit {
+    pub alpha: f64,
+    pub beta: f64,
+    pub r_squared: f64,
+}
+
+/// Fit `y = alpha + beta x` by ordinary least squares.
+///
+/// Returns `None` if `x` has no variance. A constant `y` is fit perfectly and has
+/// an `r_squared` of 1.
+pub(crate) fn linear_fit(x: &[f64], y: &[f64]) -> Option<LinearFit> {
+    let n = x.len() as f64;
+    let x_mean = x.iter().sum::<f64>() / n;
+    let y_mean = y.iter().sum::<f64>() / n;
+    let (sxx, sxy, syy) = x
+        .iter()
+        .zip(y)
+        .fold((0.0, 0.0, 0.0), |(sxx, sxy, syy), (x, y)| {
+            let (dx, dy) = (x - x_mean, y - y_mean);
+            (sxx + dx * dx, sxy + dx * dy, syy + dy * dy)
+        });
+    if sxx <= 0.0 {
+        return None;
+    }
+    let beta = sxy / sxx;
+    Some(LinearFit {
+        alpha: y_mean - beta * x_mean,
+        beta,
+        r_squared: if syy > 0.0 {
+            sxy * sxy / (sxx * syy)
+        } else {
+            1.0
+        },
+    })
+}
+
+/// Calculate the standard error of the slope of a simple linear regression.
+///
+/// With no `lags`, this is the usual OLS standard error, which assumes the
+/// residuals are uncorrelated. Otherwise it is the Newey-West HAC standard error
+/// with Bartlett weights `1 - l / (lags + 1)` on the residual autocovariances up
+/// to `lags`, scaled by `n / (n - 2)` like the OLS one. Returns `None` with two or
+/// fewer observations.
+///
+/// Reference: Newey and West (1987), "A Simple, Positive Semi-Definite,
+/// Heteroskedasticity and Autocorrelation Consistent Covariance Matrix"
+fn newey_west_beta_se(x: &[f64], y: &[f64], fit: &LinearFit, lags: usize) -> Option<f64> {
+    let n = x.len();
+    if n <= 2 {
+        return None;
+    }
+    let x_mean = x.iter().sum::<f64>() / n as f64;
+    let dx: Vec<f64> = x.iter().map(|x| x - x_mean).collect();
+    let residuals: Vec<f64> = x
+        .iter()
+        .zip(y)
+        .map(|(x, y)| y - fit.alpha - fit.beta * x)
+        .collect();
+    let sxx: f64 = dx.iter().map(|dx| dx * dx).sum();
+    let dof = (n - 2) as f64;
+    let variance = if lags == 0 {
+        residuals.iter().map(|u| u * u).sum::<f64>() / dof / sxx
+    } else {
+        let scores: Vec<f64> = dx.iter().zip(&residuals).map(|(dx, u)| dx * u).collect();
+        let long_run: f64 = (0..=lags.min(n - 1))
+            .map(|lag| {
+                let autocovariance: f64 =
+                    scores[lag..].iter().zip(&scores).map(|(a, b)| a * b).sum();
+                if lag == 0 {
+                    autocovariance
+                } else {
+                    2.0 * (1.0 - lag as f64 / (lags + 1) as f64) * autocovariance
+                }
+            })
+            .sum();
+        long_run / (sxx * sxx) * n as f64 / dof
+    };
+    Some(variance.max(0.0).sqrt())
+}
+
+/// Fit `y = alpha + beta x` over a rolling window ending at each observation,
+/// along with the Newey-West standard error of `beta` with `lags` lags.
+///
+/// Windows containing a null, or in which `x` has no variance, are `None`, as are
+/// the first `window - 1` observations. The standard error is `None` for windows
+/// of two observations.
+fn rolling_linear_fit(
+    y: &[Option<f64>],
+    x: &[Option<f64>],
+    window: usize,
+    lags: usize,
+) -> Vec<Option<(LinearFit, Option<f64>)>> {
+    let pairs: Vec<Option<(f64, f64)>> =
+        y.iter().zip(x).map(|(y, x)| Some(((*y)?, (*x)?))).collect();
+    rolling_statistic(&pairs, window, |pairs| {
+        let (y, x): (Vec<f64>, Vec<f64>) = pairs.iter().copied().unzip();
+        let fit = linear_fit(&x, &y)?;
+        Some((fit, newey_west_beta_se(&x, &y, &fit, lags)))
+    })
+}
+
+/// Run the Engle-Granger cointegration test of `y` and `x`.
+///
+/// `y` is regressed on `x` and the ADF statistic of the residual spread is
+/// returned, with the lag chosen as in `adf`. The statistic must be compared with
+/// Engle-Granger critical values, which are more negative than the ADF ones
+/// because the spread is estimated.
+fn engle_granger_statistic(y: &[f64], x: &[f64], max_lag: usize) -> Option<f64> {
+    let fit = linear_fit(x, y)?;
+    let spread: Vec<f64> = y
+        .iter()
+        .zip(x)
+        .map(|(y, x)| y - fit.alpha - fit.beta * x)
+        .collect();
+    Some(adf(&spread, max_lag)?.statistic)
+}
+
+/// Estimate the half-life of mean reversion of a spread.
+///
+/// `Δs_t` is regressed on `s_{t-1}` and, for a slope `λ < 0`, the half-life is
+/// `-ln(2) / λ` observations. A spread that is not mean reverting gives `None`.
+fn half_life(spread: &[f64]) -> Option<f64> {
+    let lagged = &spread[..spread.len().saturating_sub(1)];
+    let diffs: Vec<f64> = spread.windows(2).map(|w| w[1] - w[0]).collect();
+    let fit = linear_fit(lagged, &diffs)?;
+    (fit.beta < 0.0).then(|| -std::f64::consts::LN_2 / fit.beta)
+}
+
+/// Standardize each value by the mean and sample standard deviation of the
+/// rolling window ending at it.
+///
+/// The first `window - 1` values, windows containing a null and windows with no
+/// variance are `None`.
+fn rolling_zscores(values: &[Option<f64>], window: usize) -> Vec<Option<f64>> {
+    rolling_statistic(values, window, |window_values| {
+        let n = window as f64;
+        let mean = window_values.iter().sum::<f64>() / n;
+        let variance = window_values
+            .iter()
+            .map(|v| (v - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        (variance > 0.0).then(|| (window_values[window - 1] - mean) / variance.sqrt())
+    })
+}
+
+/// Calculate the population central moments `m2`, `m3` and `m4` of `values`.
+fn central_moments(values: &[f64]) -> (f64, f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let (m2, m3, m4) = values.iter().fold((0.0, 0.0, 0.0), |(m2, m3, m4), v| {
+        let d = v - mean;
+        (m2 + d * d, m3 + d.powi(3), m4 + d.powi(4))
+    });
+    (m2 / n, m3 / n, m4 / n)
+}
+
+/// Calculate the bias-corrected sample skewness `G1` of `values`.
+///
+/// Returns `None` for fewer than 3 values or (numerically) zero variance.
+fn sample_skew(values: &[f64]) -> Option<f64> {
+    let n = values.len() as f64;
+    let (m2, m3, _) = central_moments(values);
+    (n >= 3.0 && m2.sqrt() > f64::EPSILON)
+        .then(|| (n * (n - 1.0)).sqrt() / (n - 2.0) * m3 / m2.powf(1.5))
+}
+
+/// Calculate the bias-corrected sample excess kurtosis `G2` of `values`.
+///
+/// Returns `None` for fewer than 4 values or (numerically) zero variance.
+fn sample_excess_kurtosis(values: &[f64]) -> Option<f64> {
+    let n = values.len() as f64;
+    let (m2, _, m4) = central_moments(values);
+    (n >= 4.0 && m2.sqrt() > f64::EPSILON).then(|| {
+        let g2 = m4 / m2.powi(2) - 3.0;
+        (n - 1.0) / ((n - 2.0) * (n - 3.0)) * ((n + 1.0) * g2 + 6.0)
+    })
+}
+
+/// Apply `statistic` to the window ending at each observation.
+///
+/// The first `window - 1` observations and windows containing a null are `None`.
+pub(crate) fn rolling_statistic<T: Copy, U>(
+    values: &[Option<T>],
+    window: usize,
+    statistic: impl Fn(&[T]) -> Option<U>,
+) -> Vec<Option<U>> {
+    (0..values.len())
+        .map(|t| {
+            if t + 1 < window {
+                return None;
+            }
+            let window_values: Vec<T> = values[t + 1 - window..=t]
+                .iter()
+                .copied()
+                .collect::<Option<_>>()?;
+            statistic(&window_values)
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct AdfKwargs {
+    max_lag: usize,
+}
+
+fn adf_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(vec![
+            Field::new("statistic".into(), DataType::Float64),
+            Field::new("used_lag".into(), DataType::Int64),
+        ]),
+    ))
+}
+
+#[polars_expr(output_type_func=adf_struct)]
+fn adf_test(inputs: &[Series], kwargs: AdfKwargs) -> PolarsResult<Series> {
+    // Nulls are skipped so that the leading nulls of e.g. a fractionally
+    // differentiated series can be tested directly.
+    let values: Vec<f64> = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .iter()
+        .flatten()
+        .collect();
+    let result = adf(&values, kwargs.max_lag);
+    let fields = [
+        Float64Chunked::new("statistic".into(), &[result.map(|r| r.statistic)]).into_series(),
+        Int64Chunked::new("used_lag".into(), &[result.map(|r| r.used_lag as i64)]).into_series(),
+    ];
+    Ok(StructChunked::from_series(inputs[0].name().clone(), 1, fields.iter())?.into_series())
+}
+
+#[derive(Deserialize)]
+struct HurstKwargs {
+    max_lag: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn hurst_exponent(inputs: &[Series], kwargs: HurstKwargs) -> PolarsResult<Series> {
+    if kwargs.max_lag < 2 {
+        return Err(PolarsError::ComputeError(
+            "max_lag must be at least 2".into(),
+        ));
+    }
+    let values: Vec<f64> = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .iter()
+        .flatten()
+        .collect();
+    let exponent = hurst(&values, kwargs.max_lag);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &[exponent]).into_series())
+}
+
+#[derive(Deserialize)]
+struct EwmKwargs {
+    span: Option<f64>,
+    alpha: Option<f64>,
+    #[serde(default)]
+    bias: bool,
+    #[serde(default)]
+    min_periods: usize,
+}
+
+impl EwmKwargs {
+    fn alpha(&self) -> PolarsResult<f64> {
+        let alpha = match (self.span, self.alpha) {
+            (Some(span), None) if span >= 1.0 => 2.0 / (span + 1.0),
+            (None, Some(alpha)) if alpha > 0.0 && alpha <= 1.0 => alpha,
+            _ => {
+                return Err(PolarsError::ComputeError(
+                    "Exactly one of span >= 1 or alpha in (0, 1] must be given".into(),
+                ))
+            },
+        };
+        Ok(alpha)
+    }
+}
+
+fn ewm_values(inputs: &[Series], kwargs: &EwmKwargs) -> PolarsResult<Vec<Option<EwmMoments>>> {
+    let values: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    Ok(ewm_moments(&values, kwargs.alpha()?, kwargs.bias)
+        .into_iter()
+        .map(|m| m.filter(|m| m.count >= kwargs.min_periods))
+        .collect())
+}
+
+#[polars_expr(output_type=Float64)]
+fn ewm_mean(inputs: &[Series], kwargs: EwmKwargs) -> PolarsResult<Series> {
+    let means: Vec<Option<f64>> = ewm_values(inputs, &kwargs)?
+        .iter()
+        .map(|m| m.map(|m| m.mean))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &means).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn ewm_std(inputs: &[Series], kwargs: EwmKwargs) -> PolarsResult<Series> {
+    let stds: Vec<Option<f64>> = ewm_values(inputs, &kwargs)?
+        .iter()
+        .map(|m| m.and_then(|m| m.variance).map(f64::sqrt))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &stds).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn ewm_corr(inputs: &[Series], kwargs: EwmKwargs) -> PolarsResult<Series> {
+    let x: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let y: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let correlations = ewm_correlation(&x, &y, kwargs.alpha()?, kwargs.min_periods);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &correlations).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn engle_granger(inputs: &[Series], kwargs: AdfKwargs) -> PolarsResult<Series> {
+    // Rows where either series is null are skipped.
+    let (y, x): (Vec<f64>, Vec<f64>) = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .iter()
+        .zip(inputs[1].cast(&DataType::Float64)?.f64()?.iter())
+        .filter_map(|(y, x)| y.zip(x))
+        .unzip();
+    let statistic = engle_granger_statistic(&y, &x, kwargs.max_lag);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &[statistic]).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn mean_reversion_half_life(inputs: &[Series]) -> PolarsResult<Series> {
+    let spread: Vec<f64> = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .iter()
+        .flatten()
+        .collect();
+    let half_life = half_life(&spread);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &[half_life]).into_series())
+}
+
+#[derive(Deserialize)]
+struct RollingZScoreKwargs {
+    window: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn rolling_zscore(inputs: &[Series], kwargs: RollingZScoreKwargs) -> PolarsResult<Series> {
+    if kwargs.window < 2 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 2".into(),
+        ));
+    }
+    let values: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let zscores = rolling_zscores(&values, kwargs.window);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &zscores).into_series())
+}
+
+#[derive(Deserialize)]
+struct RollingOlsKwargs {
+    window: usize,
+    #[serde(default)]
+    lags: usize,
+}
+
+fn rolling_ols_struct(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        DataType::Struct(vec![
+            Field::new("alpha".into(), DataType::Float64),
+            Field::new("beta".into(), DataType::Float64),
+            Field::new("r_squared".into(), DataType::Float64),
+            Field::new("beta_se".into(), DataType::Float64),
+        ]),
+    ))
+}
+
+#[polars_expr(output_type_func=rolling_ols_struct)]
+fn rolling_ols(inputs: &[Series], kwargs: RollingOlsKwargs) -> PolarsResult<Series> {
+    if kwargs.window < 2 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 2".into(),
+        ));
+    }
+    let y: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let x: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let fits = rolling_linear_fit(&y, &x, kwargs.window, kwargs.lags);
+
+    let alphas: Vec<Option<f64>> = fits.iter().map(|f| f.map(|(f, _)| f.alpha)).collect();
+    let betas: Vec<Option<f64>> = fits.iter().map(|f| f.map(|(f, _)| f.beta)).collect();
+    let r_squared: Vec<Option<f64>> = fits.iter().map(|f| f.map(|(f, _)| f.r_squared)).collect();
+    let beta_se: Vec<Option<f64>> = fits.iter().map(|f| f.and_then(|(_, se)| se)).collect();
+    let fields = [
+        Float64Chunked::new("alpha".into(), &alphas).into_series(),
+        Float64Chunked::new("beta".into(), &betas).into_series(),
+        Float64Chunked::new("r_squared".into(), &r_squared).into_series(),
+        Float64Chunked::new("beta_se".into(), &beta_se).into_series(),
+    ];
+    Ok(StructChunked::from_series(inputs[0].name().clone(), y.len(), fields.iter())?.into_series())
+}
+
+#[derive(Deserialize)]
+struct RollingMomentKwargs {
+    window: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn rolling_skew(inputs: &[Series], kwargs: RollingMomentKwargs) -> PolarsResult<Series> {
+    if kwargs.window < 3 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 3".into(),
+        ));
+    }
+    let values: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let skews = rolling_statistic(&values, kwargs.window, sample_skew);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &skews).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn rolling_kurtosis(inputs: &[Series], kwargs: RollingMomentKwargs) -> PolarsResult<Series> {
+    if kwargs.window < 4 {
+        return Err(PolarsError::ComputeError(
+            "window must be at least 4".into(),
+        ));
+    }
+    let values: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let kurtoses = rolling_statistic(&values, kwargs.window, sample_excess_kurtosis);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &kurtoses).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn norm_ppf(inputs: &[Series]) -> PolarsResult<Series> {
+    let probs = inputs[0].cast(&DataType::Float64)?;
+    let quantiles: Vec<Option<f64>> = probs
+        .f64()?
+        .iter()
+        .map(|p| Some(math::norm_ppf(p?)).filter(|q| !q.is_nan()))
+        .collect();
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &quantiles).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generate deterministic standard normal noise with a xorshift generator and
+    /// the Box-Muller transform.
+    fn normal_noise(n: usize, mut seed: u64) -> Vec<f64> {
+        let mut uniform = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        (0..n)
+            .map(|_| {
+                let (u1, u2) = (uniform().max(f64::MIN_POSITIVE), uniform());
+                (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rolling_skew_and_kurtosis_hand_computed() {
+        let values = vec![Some(1.0), Some(2.0), Some(3.0), Some(10.0), None];
+
+        let skews = rolling_statistic(&values, 4, sample_skew);
+        let kurtoses = rolling_statistic(&values, 4, sample_excess_kurtosis);
+
+        // The window 1, 2, 3, 10 has central moments m2 = 12.5, m3 = 45 and
+        // m4 = 348.5.
+        assert_eq!(skews[..3], [None, None, None]);
+        assert!((skews[3].unwrap() - 1.763_632_614_803_888).abs() < 1e-12);
+        assert!((kurtoses[3].unwrap() - 3.228).abs() < 1e-12);
+        assert_eq!(skews[4], None);
+        assert!(
+            (rolling_statistic(&values, 3, sample_skew)[3].unwrap() - 1.630_059_161_711_886_3)
+                .abs()
+                < 1e-12
+        );
+
+        // A constant window has no variance.
+        let constant = vec![Some(0.1); 5];
+        assert_eq!(rolling_statistic(&constant, 4, sample_skew)[4], None);
+        assert_eq!(
+            rolling_statistic(&constant, 4, sample_excess_kurtosis)[4],
+            None
+        );
+    }
+
+    #[test]
+    fn test_ols_recovers_coefficients() {
+        let x: Vec<Vec<f64>> = (0..5).map(|i| vec![1.0, i as f64]).collect();
+        let y = vec![1.0, 3.0, 5.0, 7.0, 9.0];
+
+        let fit = ols(&x, &y).unwrap();
+
+        assert!((fit.coefficients[0] - 1.0).abs() < 1e-12);
+        assert!((fit.coefficients[1] - 2.0).abs() < 1e-12);
+        assert!(fit.ssr < 1e-20);
+        // Collinear regressors cannot be fit.
+        let collinear: Vec<Vec<f64>> = (0..5).map(|i| vec![i as f64, 2.0 * i as f64]).collect();
+        assert_eq!(ols(&collinear, &y), None);
+    }
+
+    #[test]
+    fn test_adf_random_walk_vs_mean_reverting() {
+        let noise = normal_noise(500, 42);
+        let random_walk: Vec<f64> = noise
+            .iter()
+            .scan(0.0, |level, e| {
+                *level += e;
+                Some(*level)
+            })
+            .collect();
+        let mean_reverting: Vec<f64> = noise
+            .iter()
+            .scan(0.0, |level, e| {
+                *level = 0.5 * *level + e;
+                Some(*level)
+            })
+            .collect();
+
+        // The 5% critical value with a constant is about -2.87.
+        let random_walk = adf(&random_walk, 4).unwrap();
+        assert!(random_walk.statistic > -2.87);
+        assert!(random_walk.used_lag <= 4);
+        let mean_reverting = adf(&mean_reverting, 4).unwrap();
+        assert!(mean_reverting.statistic < -2.87);
+    }
+
+    #[test]
+    fn test_hurst_trending_vs_mean_reverting() {
+        let noise = normal_noise(2000, 7);
+        // Positively autocorrelated increments make a persistent, trending path.
+        let trending: Vec<f64> = noise
+            .iter()
+            .scan((0.0, 0.0), |(level, step), e| {
+                *step = 0.7 * *step + e;
+                *level += *step;
+                Some(*level)
+            })
+            .collect();
+        let mean_reverting: Vec<f64> = noise
+            .iter()
+            .scan(0.0, |level, e| {
+                *level = 0.5 * *level + e;
+                Some(*level)
+            })
+            .collect();
+
+        assert!(hurst(&trending, 20).unwrap() > 0.5);
+        assert!(hurst(&mean_reverting, 20).unwrap() < 0.5);
+        assert_eq!(hurst(&[1.0, 1.0, 1.0, 1.0], 2), None);
+    }
+
+    #[test]
+    fn test_ewm_moments_hand_computed() {
+        let values = vec![Some(1.0), Some(2.0), Some(3.0)];
+
+        let moments = ewm_moments(&values, 0.5, false);
+
+        // Weights of 0.25, 0.5 and 1 on the last value give a mean of 4.25 / 1.75,
+        // a biased variance of 0.928571 / 1.75 and a bias correction of 1.75.
+        let last = moments[2].unwrap();
+        assert!((last.mean - 4.25 / 1.75).abs() < 1e-12);
+        assert!((last.variance.unwrap() - 0.928_571_428_571).abs() < 1e-9);
+        let second = moments[1].unwrap();
+        assert!((second.mean - 5.0 / 3.0).abs() < 1e-12);
+        assert!((second.variance.unwrap() - 0.5).abs() < 1e-12);
+        assert_eq!(moments[0].unwrap().variance, None);
+
+        let biased = ewm_moments(&values, 0.5, true);
+        assert_eq!(biased[0].unwrap().variance, Some(0.0));
+        assert!((biased[2].unwrap().variance.unwrap() - 0.928_571_428_571 / 1.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ewm_moments_null_decays_weights() {
+        let moments = ewm_moments(&[Some(1.0), None, Some(3.0)], 0.5, false);
+
+        assert_eq!(moments[1], None);
+        // The first value's weight decays twice to 0.25.
+        assert!((moments[2].unwrap().mean - 3.25 / 1.25).abs() < 1e-12);
+        assert_eq!(moments[2].unwrap().count, 2);
+    }
+
+    #[test]
+    fn test_ewm_correlation_decorrelates() {
+        // y tracks x for 100 steps, then is independent noise.
+        let x: Vec<Option<f64>> = normal_noise(300, 3).into_iter().map(Some).collect();
+        let noise = normal_noise(300, 5);
+        let y: Vec<Option<f64>> = (0..300)
+            .map(|t| {
+                Some(if t < 100 {
+                    2.0 * x[t].unwrap() + 1.0
+                } else {
+                    noise[t]
+                })
+            })
+            .collect();
+
+        let corr = ewm_correlation(&x, &y, 2.0 / 21.0, 3);
+
+        assert_eq!(corr[..2], [None, None]);
+        assert!((corr[99].unwrap() - 1.0).abs() < 1e-12);
+        // The correlation falls away within a span once y decorrelates.
+        assert!(corr[100].unwrap() < corr[99].unwrap());
+        assert!(corr[110].unwrap() < 0.5);
+        let late: Vec<f64> = corr[200..].iter().map(|c| c.unwrap()).collect();
+        assert!((late.iter().sum::<f64>() / late.len() as f64).abs() < 0.2);
+
+        let with_null = ewm_correlation(&[Some(1.0), None, Some(2.0)], &[Some(1.0); 3], 0.5, 0);
+        assert_eq!(with_null, vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_rolling_linear_fit_recovers_beta() {
+        let noise = normal_noise(200, 11);
+        let x: Vec<Option<f64>> = (0..200)
+            .map(|i| Some((i as f64 * 0.1).sin() * 10.0))
+            .collect();
+        let y: Vec<Option<f64>> = x
+            .iter()
+            .zip(&noise)
+            .map(|(x, e)| Some(2.0 * x.unwrap() + 1.0 + 0.1 * e))
+            .collect();
+
+        let fits = rolling_linear_fit(&y, &x, 50, 0);
+
+        assert!(fits[..49].iter().all(Option::is_none));
+        for fit in fits[49..].iter() {
+            let (fit, _) = fit.unwrap();
+            assert!((fit.beta - 2.0).abs() < 0.01);
+            assert!((fit.alpha - 1.0).abs() < 0.05);
+            assert!(fit.r_squared > 0.99);
+        }
+    }
+
+    #[test]
+    fn test_rolling_linear_fit_zero_x_variance() {
+        let x = vec![Some(1.0), Some(1.0), Some(2.0), None];
+        let y = vec![Some(1.0), Some(2.0), Some(3.0), Some(4.0)];
+
+        let fits = rolling_linear_fit(&y, &x, 2, 0);
+
+        assert_eq!(fits[0], None);
+        assert_eq!(fits[1], None);
+        assert_eq!(
+            fits[2],
+            Some((
+                LinearFit {
+                    alpha: 1.0,
+                    beta: 1.0,
+                    r_squared: 1.0
+                },
+                None
+            ))
+        );
+        assert_eq!(fits[3], None);
+    }
+
+    #[test]
+    fn test_newey_west_beta_se_autocorrelated_residuals() {
+        // A persistent regressor and AR(1) residuals make the OLS standard error
+        // understate the uncertainty of the slope.
+        let noise = normal_noise(1000, 5);
+        let shocks = normal_noise(1000, 9);
+        let (mut x, mut y) = (Vec::new(), Vec::new());
+        let (mut x_t, mut u_t) = (0.0, 0.0);
+        for (e, v) in noise.iter().zip(&shocks) {
+            x_t = 0.9 * x_t + v;
+            u_t = 0.9 * u_t + e;
+            x.push(x_t);
+            y.push(1.0 + 2.0 * x_t + u_t);
+        }
+        let fit = linear_fit(&x, &y).unwrap();
+
+        let ols_se = newey_west_beta_se(&x, &y, &fit, 0).unwrap();
+        let hac_se = newey_west_beta_se(&x, &y, &fit, 20).unwrap();
+
+        let sxx: f64 = {
+            let x_mean = x.iter().sum::<f64>() / x.len() as f64;
+            x.iter().map(|x| (x - x_mean).powi(2)).sum()
+        };
+        let ssr: f64 = x
+            .iter()
+            .zip(&y)
+            .map(|(x, y)| (y - fit.alpha - fit.beta * x).powi(2))
+            .sum();
+        assert!((ols_se - (ssr / 998.0 / sxx).sqrt()).abs() < 1e-12);
+        assert!(hac_se > 2.0 * ols_se, "{hac_se} vs {ols_se}");
+        assert_eq!(newey_west_beta_se(&x[..2], &y[..2], &fit, 1), None);
+    }
+
+    #[test]
+    fn test_engle_granger_cointegrated_vs_independent() {
+        let random_walk = |seed| -> Vec<f64> {
+            normal_noise(500, seed)
+                .iter()
+                .scan(0.0, |level, e| {
+                    *level += e;
+                    Some(*level)
+                })
+                .collect()
+        };
+        let x = random_walk(3);
+        let cointegrated: Vec<f64> = x
+            .iter()
+            .zip(normal_noise(500, 5))
+            .map(|(x, e)| 0.5 * x + 2.0 + e)
+            .collect();
+        let independent = random_walk(9);
+
+        // The 5% Engle-Granger critical value for two series is about -3.34.
+        assert!(engle_granger_statistic(&cointegrated, &x, 4).unwrap() < -3.34);
+        assert!(engle_granger_statistic(&independent, &x, 4).unwrap() > -3.34);
+    }
+
+    #[test]
+    fn test_half_life_ornstein_uhlenbeck() {
+        // Reverting 10% of the way to the mean each step halves a deviation in
+        // about ln(2) / 0.1 steps.
+        let spread: Vec<f64> = normal_noise(5000, 21)
+            .iter()
+            .scan(5.0, |level, e| {
+                *level += 0.1 * (1.0 - *level) + 0.2 * e;
+                Some(*level)
+            })
+            .collect();
+        let expected = std::f64::consts::LN_2 / 0.1;
+
+        let estimate = half_life(&spread).unwrap();
+
+        assert!((estimate - expected).abs() / expected < 0.15);
+        let explosive: Vec<f64> = (0..50).map(|i| 1.05_f64.powi(i)).collect();
+        assert_eq!(half_life(&explosive), None);
+        assert_eq!(half_life(&[1.0]), None);
+    }
+
+    #[test]
+    fn test_rolling_zscores_excursion() {
+        let mut values: Vec<Option<f64>> = (0..20)
+            .map(|i| Some(if i % 2 == 0 { 1.0 } else { -1.0 }))
+            .collect();
+        values[15] = Some(10.0);
+
+        let zscores = rolling_zscores(&values, 5);
+
+        assert!(zscores[..4].iter().all(Option::is_none));
+        // A window of [1, -1, 1, -1, 1] has a mean of 0.2 and a variance of 1.2.
+        assert!((zscores[4].unwrap() - 0.8 / 1.2_f64.sqrt()).abs() < 1e-12);
+        assert!(zscores[..15].iter().flatten().all(|z| z.abs() < 1.0));
+        assert!(zscores[15].unwrap() > 1.7);
+        assert_eq!(
+            rolling_zscores(&[Some(1.0), Some(1.0)], 2),
+            vec![None, None]
+        );
+        assert_eq!(
+            rolling_zscores(&[Some(1.0), None, Some(2.0)], 2),
+            vec![None; 3]
+        );
+    }
+}
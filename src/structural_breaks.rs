@@ -0,0 +1,161 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use rayon::prelude::*;
+use serde::Deserialize;
+
+use crate::stats::adf_statistic;
+
+/// Calculate the Chu-Stinchcombe-White CUSUM test statistic on levels.
+///
+/// For each observation `t` the statistic is the supremum over earlier
+/// observations `n` of `(y_t - y_n) / (σ_t * sqrt(t - n))`, where `σ_t^2` is the
+/// mean squared first difference up to `t`. Large values indicate that the series
+/// has departed from a random walk, e.g. during a bubble. The first
+/// `min_periods` observations, and any with zero estimated volatility, are `None`.
+///
+/// Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 251
+fn chu_stinchcombe_white(values: &[f64], min_periods: usize) -> Vec<Option<f64>> {
+    let mut sum_sq_diffs = 0.0;
+    (0..values.len())
+        .map(|t| {
+            if t > 0 {
+                sum_sq_diffs += (values[t] - values[t - 1]).powi(2);
+            }
+            if t < min_periods.max(1) {
+                return None;
+            }
+            let sigma = (sum_sq_diffs / t as f64).sqrt();
+            if sigma == 0.0 {
+                return None;
+            }
+            (0..t)
+                .map(|n| (values[t] - values[n]) / (sigma * ((t - n) as f64).sqrt()))
+                .max_by(f64::total_cmp)
+        })
+        .collect()
+}
+
+/// Calculate the supremum ADF statistic at each observation.
+///
+/// At observation `t` the ADF statistic is calculated on every window ending at
+/// `t` with at least `min_window` observations, and the supremum is taken. The
+/// statistic spikes when the series becomes explosive. The ending points are
+/// independent, so they are computed in parallel.
+///
+/// Reference: Marco Lopez de Prado, Advances in Financial Machine Learning, pg. 258
+pub(crate) fn supremum_adf(values: &[f64], min_window: usize, lag: usize) -> Vec<Option<f64>> {
+    (0..values.len())
+        .into_par_iter()
+        .map(|t| {
+            if t + 1 < min_window {
+                return None;
+            }
+            (0..=t + 1 - min_window)
+                .filter_map(|start| adf_statistic(&values[start..=t], lag))
+                .max_by(f64::total_cmp)
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct CusumBreakKwargs {
+    min_periods: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn cusum_break_test(inputs: &[Series], kwargs: CusumBreakKwargs) -> PolarsResult<Series> {
+    let values = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let statistic = chu_stinchcombe_white(&values, kwargs.min_periods);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &statistic).into_series())
+}
+
+#[derive(Deserialize)]
+struct SadfKwargs {
+    min_window: usize,
+    lag: usize,
+}
+
+#[polars_expr(output_type=Float64)]
+fn sadf(inputs: &[Series], kwargs: SadfKwargs) -> PolarsResult<Series> {
+    if kwargs.min_window < 2 * kwargs.lag + 4 {
+        return Err(PolarsError::ComputeError(
+            "min_window must be at least 2 * lag + 4".into(),
+        ));
+    }
+    let values = inputs[0]
+        .cast(&DataType::Float64)?
+        .f64()?
+        .to_vec_null_aware()
+        .left()
+        .ok_or_else(|| PolarsError::InvalidOperation("Null price found".into()))?;
+    let statistic = supremum_adf(&values, kwargs.min_window, kwargs.lag);
+    Ok(Float64Chunked::new(inputs[0].name().clone(), &statistic).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chu_stinchcombe_white_regime_shift() {
+        // The log price oscillates around a constant level, then trends upwards.
+        let mut values: Vec<f64> = (0..50)
+            .map(|i| if i % 2 == 0 { 0.0 } else { 0.01 })
+            .collect();
+        values.extend((1..=30).map(|i| 0.01 * i as f64));
+
+        let statistic = chu_stinchcombe_white(&values, 10);
+
+        assert!(statistic[..10].iter().all(Option::is_none));
+        let before = statistic[10..50]
+            .iter()
+            .flatten()
+            .copied()
+            .fold(f64::MIN, f64::max);
+        let after = statistic[79].unwrap();
+        assert!(before < 1.5);
+        assert!(after > 3.0);
+    }
+
+    #[test]
+    fn test_chu_stinchcombe_white_constant_series() {
+        let statistic = chu_stinchcombe_white(&[1.0; 5], 1);
+
+        assert_eq!(statistic, vec![None; 5]);
+    }
+
+    #[test]
+    fn test_supremum_adf_spikes_during_explosive_segment() {
+        // A random-looking walk followed by a segment growing 5% per step.
+        let steps = [0.3, -0.5, 0.2, 0.4, -0.1, -0.6, 0.5, -0.2, 0.1, -0.3];
+        let mut values: Vec<f64> = steps
+            .iter()
+            .cycle()
+            .take(60)
+            .scan(10.0, |level, step| {
+                *level += step;
+                Some(*level)
+            })
+            .collect();
+        let last = *values.last().unwrap();
+        values.extend((1..=20).map(|i| last * 1.05f64.powi(i) + steps[i as usize % 10]));
+
+        let statistic = supremum_adf(&values, 20, 1);
+
+        assert!(statistic[..19].iter().all(Option::is_none));
+        let before = statistic[19..60]
+            .iter()
+            .flatten()
+            .copied()
+            .fold(f64::MIN, f64::max);
+        let after = statistic[79].unwrap();
+        assert!(after > before);
+        assert!(after > 1.0);
+    }
+}
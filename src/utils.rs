@@ -0,0 +1,39 @@
+use polars::prelude::*;
+
+/// Broadcast unit length inputs, e.g. a literal rate for a whole strike ladder,
+/// to the length of the other inputs.
+pub(crate) fn broadcast_inputs(inputs: &[Series]) -> PolarsResult<Vec<Series>> {
+    let len = inputs
+        .iter()
+        .map(Series::len)
+        .find(|&len| len != 1)
+        .unwrap_or(1);
+    inputs
+        .iter()
+        .map(|s| match s.len() {
+            1 if len != 1 => Ok(s.new_from_index(0, len)),
+            n if n == len => Ok(s.clone()),
+            _ => Err(PolarsError::ComputeError(
+                "Inputs must have the same length or a length of 1".into(),
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast_inputs() {
+        let prices = Series::new("price".into(), &[10.1, 10.0, 10.05]);
+        let bid = Series::new("bid".into(), &[10.0]);
+
+        let inputs = broadcast_inputs(&[prices.clone(), bid]).unwrap();
+        assert_eq!(inputs[0], prices);
+        assert_eq!(inputs[1], Series::new("bid".into(), &[10.0, 10.0, 10.0]));
+
+        let short = Series::new("short".into(), &[1.0, 2.0]);
+        assert!(broadcast_inputs(&[prices, short]).is_err());
+    }
+}
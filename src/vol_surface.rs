@@ -0,0 +1,178 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+
+use crate::black_scholes::compute_log_moneyness;
+use crate::stats::invert;
+
+/// Fit the quadratic smile `vol = a + b k + c k^2` in the log-moneyness
+/// `k = ln(strike / forward)` by least squares, and return the coefficients.
+///
+/// Returns `None` with fewer than three points or fewer than three distinct
+/// log-moneyness values.
+fn quadratic_smile(log_moneyness: &[f64], vols: &[f64]) -> Option<[f64; 3]> {
+    if log_moneyness.len() < 3 {
+        return None;
+    }
+    let powers = |k: f64| [1.0, k, k * k];
+    let mut xtx = vec![vec![0.0; 3]; 3];
+    let mut xty = [0.0; 3];
+    for (&k, vol) in log_moneyness.iter().zip(vols) {
+        let row = powers(k);
+        for i in 0..3 {
+            xty[i] += row[i] * vol;
+            for j in 0..3 {
+                xtx[i][j] += row[i] * row[j];
+            }
+        }
+    }
+    let xtx_inv = invert(xtx)?;
+    let mut coefficients = [0.0; 3];
+    for (coefficient, row) in coefficients.iter_mut().zip(&xtx_inv) {
+        *coefficient = row.iter().zip(&xty).map(|(a, b)| a * b).sum();
+    }
+    Some(coefficients)
+}
+
+/// Fit a quadratic smile in log-moneyness to the implied vols of one expiry and
+/// evaluate it at each strike.
+///
+/// Rows with a null implied vol are left out of the fit but still get a fitted
+/// vol. Rows with a null or non-positive strike or forward are `None`, as are
+/// all rows if the smile cannot be fit.
+fn fit_vol_smile(
+    strikes: &[Option<f64>],
+    vols: &[Option<f64>],
+    forwards: &[Option<f64>],
+) -> Vec<Option<f64>> {
+    let log_moneyness: Vec<Option<f64>> = strikes
+        .iter()
+        .zip(forwards)
+        .map(|(strike, forward)| compute_log_moneyness((*strike)?, (*forward)?))
+        .collect();
+    let (ks, ys): (Vec<f64>, Vec<f64>) = log_moneyness
+        .iter()
+        .zip(vols)
+        .filter_map(|(k, vol)| Some(((*k)?, (*vol)?)))
+        .unzip();
+    let Some([a, b, c]) = quadratic_smile(&ks, &ys) else {
+        return vec![None; strikes.len()];
+    };
+    log_moneyness
+        .iter()
+        .map(|k| k.map(|k| a + b * k + c * k * k))
+        .collect()
+}
+
+/// Average the implied vols of one expiry weighted by their vega.
+///
+/// Vega peaks at the money, so the average is dominated by the near the money
+/// strikes. Rows with a null or non-positive strike, a null vol or a null or
+/// negative vega are left out. Returns `None` if the total vega is zero.
+fn vega_weighted_iv(
+    strikes: &[Option<f64>],
+    vols: &[Option<f64>],
+    vegas: &[Option<f64>],
+) -> Option<f64> {
+    let (weighted, total) = strikes
+        .iter()
+        .zip(vols)
+        .zip(vegas)
+        .filter_map(|((strike, vol), vega)| {
+            let vega = vega.filter(|&vega| vega >= 0.0)?;
+            strike.filter(|&strike| strike > 0.0)?;
+            Some((vol.as_ref()? * vega, vega))
+        })
+        .fold((0.0, 0.0), |(weighted, total), (vol_vega, vega)| {
+            (weighted + vol_vega, total + vega)
+        });
+    (total > 0.0).then(|| weighted / total)
+}
+
+#[polars_expr(output_type=Float64)]
+fn vol_smile_fit(inputs: &[Series]) -> PolarsResult<Series> {
+    let strikes: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let vols: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let forwards: Vec<Option<f64>> = inputs[2].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let fitted = fit_vol_smile(&strikes, &vols, &forwards);
+    Ok(Float64Chunked::new(inputs[1].name().clone(), &fitted).into_series())
+}
+
+#[polars_expr(output_type=Float64)]
+fn atm_iv(inputs: &[Series]) -> PolarsResult<Series> {
+    let strikes: Vec<Option<f64>> = inputs[0].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let vols: Vec<Option<f64>> = inputs[1].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let vegas: Vec<Option<f64>> = inputs[2].cast(&DataType::Float64)?.f64()?.iter().collect();
+    let iv = vega_weighted_iv(&strikes, &vols, &vegas);
+    Ok(Float64Chunked::new(inputs[1].name().clone(), &[iv]).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_vol_smile_recovers_curvature() {
+        let smile = |k: f64| 0.2 - 0.05 * k + 0.3 * k * k;
+        let strikes: Vec<Option<f64>> = (0..9).map(|i| Some(80.0 + 5.0 * i as f64)).collect();
+        // Bumping the vols alternately up and down leaves a noisy smile.
+        let mut vols: Vec<Option<f64>> = strikes
+            .iter()
+            .enumerate()
+            .map(|(i, strike)| {
+                let bump = if i % 2 == 0 { 0.002 } else { -0.002 };
+                Some(smile((strike.unwrap() / 100.0).ln()) + bump)
+            })
+            .collect();
+        vols[4] = None;
+        let forwards = vec![Some(100.0); 9];
+
+        let fitted = fit_vol_smile(&strikes, &vols, &forwards);
+
+        let ks: Vec<f64> = strikes.iter().map(|s| (s.unwrap() / 100.0).ln()).collect();
+        let ys: Vec<f64> = ks.iter().map(|&k| smile(k)).collect();
+        let [a, b, c] = quadratic_smile(&ks, &ys).unwrap();
+        assert!((a - 0.2).abs() < 1e-10 && (b + 0.05).abs() < 1e-10 && (c - 0.3).abs() < 1e-10);
+        for (fit, k) in fitted.iter().zip(&ks) {
+            assert!((fit.unwrap() - smile(*k)).abs() < 2e-3);
+        }
+        // The null vol at the forward still gets a fitted vol.
+        assert!((fitted[4].unwrap() - 0.2).abs() < 2e-3);
+    }
+
+    #[test]
+    fn test_fit_vol_smile_needs_three_points() {
+        let strikes = [Some(90.0), Some(100.0), Some(110.0)];
+        let forwards = [Some(100.0); 3];
+
+        let fitted = fit_vol_smile(&strikes, &[Some(0.25), Some(0.2), None], &forwards);
+        assert_eq!(fitted, vec![None; 3]);
+
+        let fitted = fit_vol_smile(&strikes, &[Some(0.25), Some(0.2), Some(0.22)], &forwards);
+        assert!((fitted[2].unwrap() - 0.22).abs() < 1e-12);
+        assert_eq!(
+            fit_vol_smile(&[Some(100.0); 3], &[Some(0.2); 3], &forwards),
+            vec![None; 3]
+        );
+    }
+
+    #[test]
+    fn test_vega_weighted_iv_leans_to_highest_vega() {
+        let strikes = [Some(90.0), Some(100.0), Some(110.0)];
+        let vols = [Some(0.3), Some(0.2), Some(0.25)];
+
+        // Equal vegas give the plain mean, while the at-the-money strike's larger
+        // vega pulls the average towards its vol.
+        let equal = vega_weighted_iv(&strikes, &vols, &[Some(10.0); 3]).unwrap();
+        let weighted = vega_weighted_iv(&strikes, &vols, &[Some(10.0), Some(40.0), Some(10.0)]);
+        assert!((equal - 0.25).abs() < 1e-12);
+        assert!((weighted.unwrap() - 0.225).abs() < 1e-12);
+        assert!(weighted.unwrap() < equal);
+
+        assert_eq!(vega_weighted_iv(&strikes, &vols, &[Some(0.0); 3]), None);
+        assert_eq!(
+            vega_weighted_iv(&strikes, &[None, Some(0.2), None], &[Some(10.0); 3]),
+            Some(0.2)
+        );
+    }
+}
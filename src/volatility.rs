@@ -0,0 +1,290 @@
+#![allow(clippy::unused_unit)]
+use polars::prelude::*;
+use pyo3_polars::derive::polars_expr;
+use serde::Deserialize;
+
+/// The range-based and close-to-close estimators supported by `realized_vol`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VolEstimator {
+    Close,
+    Parkinson,
+    GarmanKlass,
+    RogersSatchell,
+}
+
+impl VolEstimator {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "close" => Some(Self::Close),
+            "parkinson" => Some(Self::Parkinson),
+            "garman_klass" => Some(Self::GarmanKlass),
+            "rogers_satchell" => Some(Self::RogersSatchell),
+            _ => None,
+        }
+    }
+}
+
+/// OHLC prices of a single bar. Only the fields an estimator needs are read.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Bar {
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+/// Calculate the per-bar variance contribution of a range-based estimator.
+fn range_variance(bar: &Bar, estimator: VolEstimator) -> f64 {
+    let hl = (bar.high / bar.low).ln();
+    match estimator {
+        VolEstimator::Parkinson => hl.powi(2) / (4.0 * std::f64::consts::LN_2),
+        VolEstimator::GarmanKlass => {
+            let co = (bar.close / bar.open).ln();
+            0.5 * hl.powi(2) - (2.0 * std::f64::consts::LN_2 - 1.0) * co.powi(2)
+        },
+        VolEstimator::RogersSatchell => {
+            (bar.high / bar.close).ln() * (bar.high / bar.open).ln()
+                + (bar.low / bar.close).ln() * (bar.low / bar.open).ln()
+        },
+        VolEstimator::Close => unreachable!("close-to-close is not a range estimator"),
+    }
+}
+
+/// Calculate the per-bar volatility over a rolling window of `window` bars.
+///
+/// The close-to-close estimator is the sample standard deviation of log returns,
+/// so its first `window` bars are `None`. The range-based estimators are the
+/// square root of the mean per-bar variance, so their first `window - 1` bars are
+/// `None`. A window with a missing bar or a negative variance is `None`.
+fn rolling_realized_vol(
+    bars: &[Option<Bar>],
+    window: usize,
+    estimator: VolEstimator,
+) -> Vec<Option<f64>> {
+    let terms: Vec<Option<f64>> = match estimator {
+        VolEstimator::Close => std::iter::once(None)
+            .chain(
+                bars.windows(2)
+                    .map(|w| Some((w[1]?.close / w[0]?.close).ln())),
+            )
+            .take(bars.len())
+            .collect(),
+        _ => bars
+            .iter()
+            .map(|bar| bar.map(|bar| range_variance(&bar, estimator)))
+            .collect(),
+    };
+    (0..terms.len())
+        .map(|t| {
+            if t + 1 < window {
+                return None;
+            }
+            let values = terms[t + 1 - window..=t]
+                .iter()
+                .copied()
+                .collect::<Option<Vec<f64>>>()?;
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let variance = match estimator {
+                VolEstimator::Close => {
+                    values.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)
+                },
+                _ => mean,
+            };
+            (variance >= 0.0).then(|| variance.sqrt())
+        })
+        .collect()
+}
+
+/// Filter the GARCH(1,1) conditional variance `σ²_t = ω + α r²_{t-1} + β σ²_{t-1}`
+/// of a returns series with fixed parameters.
+///
+/// Each variance is the forecast for its return given the returns before it, so
+/// the first is the unconditional variance `ω / (1 - α - β)`. A null return
+/// carries the variance forward unchanged.
+fn garch11_variance(returns: &[Option<f64>], omega: f64, alpha: f64, beta: f64) -> Vec<f64> {
+    let mut variance = omega / (1.0 - alpha - beta);
+    returns
+        .iter()
+        .map(|ret| {
+            let forecast = variance;
+            if let Some(ret) = ret {
+                variance = omega + alpha * ret * ret + beta * variance;
+            }
+            forecast
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct RealizedVolKwargs {
+    window: usize,
+    estimator: String,
+}
+
+fn to_f64_values(s: &Series) -> PolarsResult<Vec<Option<f64>>> {
+    Ok(s.cast(&DataType::Float64)?.f64()?.iter().collect())
+}
+
+#[polars_expr(output_type=Float64)]
+fn realized_vol(inputs: &[Series], kwargs: RealizedVolKwargs) -> PolarsResult<Series> {
+    let estimator = VolEstimator::from_name(&kwargs.estimator).ok_or_else(|| {
+        PolarsError::ComputeError(format!("Unknown estimator: {}", kwargs.estimator).into())
+    })?;
+    let min_window = if estimator == VolEstimator::Close {
+        2
+    } else {
+        1
+    };
+    if kwargs.window < min_window {
+        return Err(PolarsError::ComputeError(
+            format!("window must be at least {min_window}").into(),
+        ));
+    }
+    // The close estimator takes [close], parkinson takes [high, low] and the others
+    // take [open, high, low, close].
+    let columns = inputs
+        .iter()
+        .map(to_f64_values)
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let bars: Vec<Option<Bar>> = (0..inputs[0].len())
+        .map(|i| match estimator {
+            VolEstimator::Close => Some(Bar {
+                close: columns[0][i]?,
+                ..Default::default()
+            }),
+            VolEstimator::Parkinson => Some(Bar {
+                high: columns[0][i]?,
+                low: columns[1][i]?,
+                ..Default::default()
+            }),
+            VolEstimator::GarmanKlass | VolEstimator::RogersSatchell => Some(Bar {
+                open: columns[0][i]?,
+                high: columns[1][i]?,
+                low: columns[2][i]?,
+                close: columns[3][i]?,
+            }),
+        })
+        .collect();
+    let vol = rolling_realized_vol(&bars, kwargs.window, estimator);
+    Ok(Float64Chunked::new("realized_vol".into(), &vol).into_series())
+}
+
+#[derive(Deserialize)]
+struct Garch11Kwargs {
+    omega: f64,
+    alpha: f64,
+    beta: f64,
+}
+
+#[polars_expr(output_type=Float64)]
+fn garch11(inputs: &[Series], kwargs: Garch11Kwargs) -> PolarsResult<Series> {
+    let Garch11Kwargs { omega, alpha, beta } = kwargs;
+    if omega <= 0.0 || alpha < 0.0 || beta < 0.0 || alpha + beta >= 1.0 {
+        return Err(PolarsError::ComputeError(
+            "omega must be positive, alpha and beta non-negative and alpha + beta below 1".into(),
+        ));
+    }
+    let returns = to_f64_values(&inputs[0])?;
+    let variance = garch11_variance(&returns, omega, alpha, beta);
+    Ok(Float64Chunked::from_vec("garch11".into(), variance).into_series())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant_range_bars() -> Vec<Option<Bar>> {
+        // Every bar opens and closes at 100 and trades 2% either side in log terms.
+        let bar = Bar {
+            open: 100.0,
+            high: 100.0 * 0.02f64.exp(),
+            low: 100.0 * (-0.02f64).exp(),
+            close: 100.0,
+        };
+        vec![Some(bar); 4]
+    }
+
+    fn assert_vol(vol: &[Option<f64>], expected: &[Option<f64>]) {
+        assert_eq!(vol.len(), expected.len());
+        for (v, e) in vol.iter().zip(expected) {
+            match (v, e) {
+                (Some(v), Some(e)) => assert!((v - e).abs() < 1e-12, "{v} != {e}"),
+                _ => assert_eq!(v, e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_rolling_realized_vol_close() {
+        // Log returns alternate between +1% and -1%.
+        let bars: Vec<Option<Bar>> = [0.0, 0.01, 0.0, 0.01]
+            .iter()
+            .map(|r: &f64| {
+                Some(Bar {
+                    close: 100.0 * r.exp(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let vol = rolling_realized_vol(&bars, 2, VolEstimator::Close);
+
+        let expected = Some(0.0002f64.sqrt());
+        assert_vol(&vol, &[None, None, expected, expected]);
+    }
+
+    #[test]
+    fn test_rolling_realized_vol_parkinson() {
+        let vol = rolling_realized_vol(&constant_range_bars(), 2, VolEstimator::Parkinson);
+
+        let expected = Some(0.04 / (2.0 * std::f64::consts::LN_2.sqrt()));
+        assert_vol(&vol, &[None, expected, expected, expected]);
+    }
+
+    #[test]
+    fn test_rolling_realized_vol_garman_klass() {
+        let vol = rolling_realized_vol(&constant_range_bars(), 2, VolEstimator::GarmanKlass);
+
+        // 0.5 * 0.04^2 with no open-to-close move.
+        let expected = Some(0.0008f64.sqrt());
+        assert_vol(&vol, &[None, expected, expected, expected]);
+    }
+
+    #[test]
+    fn test_rolling_realized_vol_rogers_satchell() {
+        let vol = rolling_realized_vol(&constant_range_bars(), 2, VolEstimator::RogersSatchell);
+
+        // 0.02 * 0.02 + (-0.02) * (-0.02)
+        let expected = Some(0.0008f64.sqrt());
+        assert_vol(&vol, &[None, expected, expected, expected]);
+    }
+
+    #[test]
+    fn test_garch11_variance_shock_persistence() {
+        let (omega, alpha, beta): (f64, f64, f64) = (0.02, 0.1, 0.8);
+        // Returns the size of the unconditional standard deviation hold the
+        // variance at its long-run level of 0.2.
+        let long_run = omega / (1.0 - alpha - beta);
+        let calm = Some(long_run.sqrt());
+        let mut returns = vec![calm, Some(-long_run.sqrt()), Some(2.0)];
+        returns.extend([calm; 5]);
+
+        let variance = garch11_variance(&returns, omega, alpha, beta);
+
+        for v in &variance[..3] {
+            assert!((v - long_run).abs() < 1e-12);
+        }
+        // The shock raises the next variance by alpha times its excess square,
+        // and the excess then decays by beta each calm step.
+        let mut excess = alpha * (4.0 - long_run);
+        for v in &variance[3..] {
+            assert!((v - long_run - excess).abs() < 1e-12, "{v}");
+            excess *= beta;
+        }
+        assert!(variance.windows(2).skip(3).all(|w| w[1] < w[0]));
+
+        let with_null = garch11_variance(&[Some(2.0), None, calm], omega, alpha, beta);
+        assert_eq!(with_null[1], with_null[2]);
+    }
+}
@@ -1,4 +1,8 @@
-from polars_trading.features.frac_diff import frac_diff
+from polars_trading.features.frac_diff import (
+    frac_diff,
+    frac_diff_analysis,
+    frac_diff_multi,
+)
 import pytest
 from polars.testing import assert_frame_equal
 import polars as pl
@@ -25,3 +29,43 @@ def test__frac_diff__matches_pandas(trade_data):
         out2.sort("ts_event", "symbol"),
         check_column_order=False,
     )
+
+
+def test__frac_diff_multi__matches_frac_diff():
+    df = pl.DataFrame(
+        {
+            "high": [10.0, 10.5, 10.2, 10.8, 11.0, 10.9, 11.3],
+            "low": [9.5, 9.9, 9.8, 10.1, 10.4, 10.3, 10.6],
+        }
+    )
+    out = df.select(frac_diff_multi("high", "low", d=0.4, window=3).alias("fd"))
+    assert out.schema["fd"] == pl.Struct({"high": pl.Float64, "low": pl.Float64})
+    expected = df.select(
+        frac_diff("high", 0.4, window=3).alias("high"),
+        frac_diff("low", 0.4, window=3).alias("low"),
+    )
+    assert_frame_equal(out.unnest("fd"), expected)
+
+
+def test__frac_diff__skip_leading_nulls():
+    prices = [10.0, 10.5, 10.2, 10.8, 11.0, 10.9]
+    df = pl.DataFrame({"padded": [None, None, None, *prices]})
+    out = df.select(
+        frac_diff("padded", 0.4, window=3, skip_leading_nulls=True).alias("fd")
+    )
+    expected = pl.DataFrame({"price": prices}).select(
+        frac_diff("price", 0.4, window=3).alias("fd")
+    )
+    assert out["fd"].head(3).null_count() == 3
+    assert_frame_equal(out.tail(len(prices)), expected)
+    with pytest.raises(pl.exceptions.InvalidOperationError):
+        df.select(frac_diff("padded", 0.4, window=3))
+
+
+def test__frac_diff_analysis__one_row_per_d():
+    df = pl.DataFrame({"price": [100.0 + (i % 7) * 0.5 + i * 0.1 for i in range(200)]})
+    out = df.select(frac_diff_analysis("price", [0.0, 0.5, 1.0], window=10))
+    table = out.explode("price").unnest("price")
+    assert table["d"].to_list() == [0.0, 0.5, 1.0]
+    assert table["corr_with_original"][0] == pytest.approx(1.0)
+    assert table["adf_stat"].null_count() == 0
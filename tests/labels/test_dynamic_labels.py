@@ -9,6 +9,7 @@ from polars_trading.config import Config
 from polars_trading.labels.dynamic_labels import (
     daily_vol,
     get_vertical_barrier_by_timedelta,
+    triple_barrier_label,
 )
 
 
@@ -235,3 +236,75 @@ def test__get_vertical_barrier_by_timedelta__timedelta():
             df.lazy(), timedelta(hours=2)
         ).collect()
     assert_frame_equal(result, expected)
+
+
+def test__triple_barrier_label__barrier_touch_index_and_position():
+    timestamps = [datetime(2024, 1, 1, hour) for hour in range(6)]
+    df = pl.DataFrame(
+        {"ts_event": timestamps, "price": [10.0, 10.5, 11.5, 9.0, 9.5, 10.0]}
+    )
+
+    result = df.select(
+        triple_barrier_label("ts_event", "price", 0.1, -0.1)
+        .struct.field("barrier_touch")
+        .alias("index_touch"),
+        triple_barrier_label(
+            "ts_event", "price", 0.1, -0.1, positional_barrier_touch=True
+        )
+        .struct.field("barrier_touch")
+        .alias("positional_touch"),
+    )
+
+    expected = pl.DataFrame(
+        {
+            "index_touch": [timestamps[i] for i in [2, 3, 3, 5, 5, 5]],
+            "positional_touch": [2, 3, 3, 5, 5, 5],
+        }
+    )
+    assert_frame_equal(result, expected)
+
+
+def test__triple_barrier_label__snap_vertical_barrier_between_index_values():
+    timestamps = [datetime(2024, 1, 1, hour) for hour in [0, 1, 3, 4, 7]]
+    df = pl.DataFrame(
+        {"ts_event": timestamps, "price": [10.0, 10.5, 11.5, 9.0, 9.5]}
+    ).with_columns(vertical_barrier=pl.col("ts_event") + timedelta(minutes=90))
+
+    result = df.select(
+        triple_barrier_label(
+            "ts_event",
+            "price",
+            0.1,
+            -0.1,
+            "vertical_barrier",
+            positional_barrier_touch=True,
+            snap_vertical_barrier=True,
+        ).struct.unnest()
+    )
+
+    assert result["label"].to_list() == [1, 0, -1, 0, 0]
+    assert result["barrier_touch"].to_list() == [2, 2, 3, 4, 4]
+    assert result["holding_period"].to_list() == [2, 1, 1, 1, 0]
+
+
+def test__triple_barrier_label__missing_vertical_barrier_raises():
+    df = pl.DataFrame(
+        {"index": [100, 101, 102, 103], "price": [10.0, 10.5, 11.5, 9.0]}
+    ).with_columns(vertical_barrier=pl.col("index") + 2)
+
+    with pytest.raises(pl.exceptions.ComputeError, match="not found in index"):
+        df.select(
+            triple_barrier_label("index", "price", 0.1, -0.1, "vertical_barrier")
+        )
+
+
+def test__triple_barrier_label__final_row_touches_vertical_barrier():
+    df = pl.DataFrame({"index": [100, 101, 102], "price": [10.0, 10.5, 10.2]})
+
+    result = df.select(
+        triple_barrier_label(
+            "index", "price", 0.1, -0.1, positional_barrier_touch=True
+        ).struct.unnest()
+    ).row(-1)
+
+    assert result == (0.0, 0, 2, 0)
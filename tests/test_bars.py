@@ -4,7 +4,13 @@ import polars as pl
 import pytest
 from polars.testing import assert_frame_equal
 
-from polars_trading.bars import dollar_bars, tick_bars, time_bars, volume_bars
+from polars_trading.bars import (
+    _bar_groups_expr,
+    dollar_bars,
+    tick_bars,
+    time_bars,
+    volume_bars,
+)
 from polars_trading.config import Config
 from tests.testing_utils.pd_bars_helpers import (
     pandas_tick_bars,
@@ -75,6 +81,46 @@ def test__volume_bars__matches_pandas(trade_data):
     )
 
 
+def test__bar_groups__over_symbol_resets_state():
+    """Test that each symbol's bars start empty when grouped with over."""
+    df = pl.DataFrame(
+        {
+            "symbol": ["A", "B", "A", "B", "A"],
+            "size": [3, 3, 3, 1, 3],
+        }
+    )
+    res = df.with_columns(
+        _bar_groups_expr("size", 4).over("symbol").alias("bar_groups")
+    )
+
+    # Symbol A fills 3, splits the next 3 into 1 + 2 and then 2 + 1, while B's
+    # 3 + 1 exactly fills its own first bar.
+    groups = {
+        symbol: res.filter(pl.col("symbol") == symbol)["bar_groups"].to_list()
+        for symbol in ["A", "B"]
+    }
+    assert groups["A"] == [
+        [{"bar_group__id": 0, "bar_group__amount": 3}],
+        [
+            {"bar_group__id": 0, "bar_group__amount": 1},
+            {"bar_group__id": 1, "bar_group__amount": 2},
+        ],
+        [
+            {"bar_group__id": 1, "bar_group__amount": 2},
+            {"bar_group__id": 2, "bar_group__amount": 1},
+        ],
+    ]
+    assert groups["B"] == [
+        [{"bar_group__id": 0, "bar_group__amount": 3}],
+        [{"bar_group__id": 0, "bar_group__amount": 1}],
+    ]
+    for symbol in ["A", "B"]:
+        alone = df.filter(pl.col("symbol") == symbol).select(
+            _bar_groups_expr("size", 4).alias("bar_groups")
+        )
+        assert alone["bar_groups"].to_list() == groups[symbol]
+
+
 def test__volume_bars__overflow_trade():
     """Test volume bars with overflow trade."""
     df = pl.DataFrame(
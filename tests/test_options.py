@@ -0,0 +1,31 @@
+import polars as pl
+import pytest
+from polars.testing import assert_series_equal
+
+from polars_trading.options import black_scholes
+
+
+def test__black_scholes__broadcasts_scalar_inputs():
+    df = pl.DataFrame(
+        {
+            "spot": [95.0, 100.0, 105.0],
+            "strike": [100.0, 100.0, 110.0],
+            "r": [0.05, 0.05, 0.05],
+            "sigma": [0.2, 0.2, 0.2],
+            "t": [1.0, 1.0, 1.0],
+            "type": ["call", "put", "call"],
+        }
+    )
+
+    result = df.select(
+        black_scholes(
+            "spot", "strike", pl.lit(0.05), pl.lit(0.2), pl.lit(1.0), "type"
+        ).alias("price")
+    )["price"]
+    expected = df.select(
+        black_scholes("spot", "strike", "r", "sigma", "t", "type").alias("price")
+    )["price"]
+
+    assert_series_equal(result, expected)
+    # The at-the-money put from the textbook example.
+    assert result[1] == pytest.approx(5.5735, abs=1e-4)